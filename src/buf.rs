@@ -1,5 +1,8 @@
-use crate::util::lru_cache;
+use std::cmp::min;
+use std::collections::BTreeMap;
+use crate::util::cache;
 use crate::driver::disk_manager;
+use crate::metrics;
 
 #[derive(Clone, Copy)]
 pub struct Buf {
@@ -16,55 +19,163 @@ impl Buf {
     }
 }
 
+// 缺页时的预读策略，预读不会跨越Block
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ReadAhead {
+    None,           // 只读取请求的Page
+    Fixed(u32),     // 每次读取固定数量的Page
+    Adaptive(u32),  // 顺序访问时窗口逐次翻倍，随机访问时回落到1，参数为窗口上限
+}
+
+// 读缓存为LRU，写回缓存按物理地址有序保存脏页，flush时按地址顺序以Block为单位写入磁盘
 pub struct BufCache {
     pub capacity: usize,
-    pub cache: lru_cache::LRUCache<Buf>,
+    pub cache: Box<dyn cache::Cache<u32, Buf>>,
+    pub cache_policy: cache::CachePolicy,
+    pub dirty_capacity: usize,
+    pub dirty: BTreeMap<u32, [u8; 4096]>,
     pub disk_manager: disk_manager::DiskManager,
+    pub read_ahead: ReadAhead,
+    pub window: u32,
+    pub last_address: Option<u32>,
+    pub metrics: metrics::MetricsLink,
 }
 
 impl BufCache {
     pub fn new() -> BufCache {
+        BufCache::new_with_disk(disk_manager::DiskManager::new(true))
+    }
+
+    // size以Page为单位
+    pub fn new_with_size(size: u32) -> BufCache {
+        BufCache::new_with_disk(disk_manager::DiskManager::new_with_size(true, size))
+    }
+
+    pub fn new_with_disk(disk_manager: disk_manager::DiskManager) -> BufCache {
         let capacity = 1024;
+        let metrics = disk_manager.metrics.clone();
         BufCache {
             capacity: capacity as usize,
-            cache: lru_cache::LRUCache::new(capacity as usize),
-            disk_manager: disk_manager::DiskManager::new(true),
+            cache: cache::new_cache(cache::CachePolicy::LRU, capacity as usize),
+            cache_policy: cache::CachePolicy::LRU,
+            dirty_capacity: 256,
+            dirty: BTreeMap::new(),
+            disk_manager,
+            read_ahead: ReadAhead::Adaptive(32),
+            window: 1,
+            last_address: None,
+            metrics,
         }
     }
 
+    // 切换替换策略会丢弃读缓存中的数据，脏页不受影响
+    pub fn set_cache_policy(&mut self, policy: cache::CachePolicy) {
+        self.cache = cache::new_cache(policy, self.capacity);
+        self.cache_policy = policy;
+    }
+
+    pub fn set_read_ahead(&mut self, read_ahead: ReadAhead) {
+        self.read_ahead = read_ahead;
+        self.window = 1;
+    }
+
     pub fn read(&mut self, dev: u8, address: u32) -> [u8; 4096] {
+        let sequential = self.last_address.is_some() && self.last_address.unwrap() + 1 == address;
+        self.last_address = Some(address);
         let data = self.get_data(address);
+        self.metrics.borrow_mut().record_cache(data.is_some());
         if data.is_some() {
             return data.unwrap();
         }
-        let block_no = address / 128;
-        let data = self.disk_manager.read(block_no);
-        for (index, page) in data.iter().enumerate() {
-            self.put_data(address + index as u32, *page);
+        let size = self.get_read_ahead_size(address, sequential);
+        for i in address + 1..address + size {
+            if self.cache.contains_key(&i) || self.dirty.contains_key(&i) {
+                continue;
+            }
+            let page = self.disk_manager.read_page(i);
+            self.put_data(i, page);
         }
-        self.get_data(address).unwrap()
+        let data = self.disk_manager.read_page(address);
+        self.put_data(address, data);
+        data
     }
 
+    // 只写入写回缓存，写回缓存满时先将已有脏页写入磁盘
     pub fn write(&mut self, dev: u8, address: u32, data: [u8; 4096]) {
-        self.put_data(address, data);
-        self.disk_manager.disk_write(address, data);
+        if !self.dirty.contains_key(&address) && self.dirty.len() >= self.dirty_capacity {
+            self.flush();
+        }
+        self.remove_data(address);
+        self.dirty.insert(address, data);
     }
 
+    // 擦除前的脏页随Block一起丢弃
     pub fn erase(&mut self, dev: u8, block_no: u32) {
         let start_address = block_no * 128;
         let end_address = (block_no + 1) * 128;
         for address in start_address..end_address {
             self.remove_data(address);
+            self.dirty.remove(&address);
         }
         self.disk_manager.disk_erase(block_no);
     }
+
+    // 将所有脏页按物理地址顺序写入磁盘
+    pub fn flush(&mut self) {
+        while let Some((address, _)) = self.dirty.iter().next() {
+            let block_no = address / 128;
+            self.flush_block(block_no);
+        }
+    }
+
+    pub fn flush_block(&mut self, block_no: u32) {
+        let start_address = block_no * 128;
+        let end_address = (block_no + 1) * 128;
+        let addresses: Vec<u32> = self.dirty.range(start_address..end_address).map(|(address, _)| *address).collect();
+        for address in addresses {
+            let data = self.dirty.remove(&address).unwrap();
+            self.disk_manager.disk_write(address, data);
+            self.put_data(address, data);
+        }
+    }
+
+    // 写回所有脏页并等待磁盘写入完成
+    pub fn sync(&mut self) {
+        self.flush();
+        self.disk_manager.sync();
+    }
+
+    pub fn get_dirty_size(&self) -> usize {
+        self.dirty.len()
+    }
 }
 
 impl BufCache {
+    // 计算本次缺页需要读取的Page数量，包含请求的Page
+    pub fn get_read_ahead_size(&mut self, address: u32, sequential: bool) -> u32 {
+        let size = match self.read_ahead {
+            ReadAhead::None => 1,
+            ReadAhead::Fixed(size) => size,
+            ReadAhead::Adaptive(max_size) => {
+                if sequential {
+                    self.window = min(self.window * 2, max_size);
+                } else {
+                    self.window = 1;
+                }
+                self.window
+            }
+        };
+        let remain = 128 - address % 128;
+        min(remain, size).max(1)
+    }
+
     pub fn get_data(&mut self, address: u32) -> Option<[u8; 4096]> {
-        let data = self.cache.get(address);
+        if let Some(data) = self.dirty.get(&address) {
+            return Some(*data);
+        }
+        let data = self.cache.get(&address);
         if data.is_some() {
-            return Some(data.as_deref().unwrap().data);
+            return Some(data.unwrap().data);
         }
         None
     }
@@ -75,7 +186,7 @@ impl BufCache {
     }
 
     pub fn remove_data(&mut self, address: u32) {
-        self.cache.remove(address);
+        self.cache.remove(&address);
     }
 }
 
@@ -101,4 +212,84 @@ mod test {
         let data = cache.read(0, 100);
         assert_eq!(data, [0; 4096]);
     }
+
+    #[test]
+    fn write_back() {
+        let mut cache = BufCache::new();
+        cache.dirty_capacity = 4;
+
+        cache.write(0, 130, [1; 4096]);
+        cache.write(0, 129, [2; 4096]);
+        cache.write(0, 3, [3; 4096]);
+        assert_eq!(cache.get_dirty_size(), 3);
+        assert_eq!(cache.read(0, 130), [1; 4096]);
+        assert_eq!(cache.disk_manager.read_page(130), [0; 4096]);
+
+        cache.flush_block(1);
+        assert_eq!(cache.get_dirty_size(), 1);
+        assert_eq!(cache.disk_manager.read_page(129), [2; 4096]);
+
+        cache.write(0, 4, [4; 4096]);
+        cache.write(0, 5, [5; 4096]);
+        cache.write(0, 6, [6; 4096]);
+        cache.write(0, 7, [7; 4096]);
+        assert_eq!(cache.get_dirty_size(), 1);
+        assert_eq!(cache.disk_manager.read_page(6), [6; 4096]);
+
+        cache.erase(0, 0);
+        assert_eq!(cache.get_dirty_size(), 0);
+        assert_eq!(cache.read(0, 7), [0; 4096]);
+
+        cache.write(0, 256, [8; 4096]);
+        cache.sync();
+        assert_eq!(cache.get_dirty_size(), 0);
+        assert_eq!(cache.disk_manager.disk_read_page(256), [8; 4096]);
+        assert_eq!(cache.disk_manager.disk_read_page(130), [1; 4096]);
+    }
+
+    #[test]
+    fn read_ahead() {
+        let mut cache = BufCache::new();
+        cache.disk_manager.disk_write(200, [5; 4096]);
+
+        cache.set_read_ahead(ReadAhead::None);
+        cache.read(0, 130);
+        assert_eq!(cache.cache.get_size(), 1);
+        assert_eq!(cache.disk_manager.get_clock().unwrap().read_num, 1);
+
+        cache.set_read_ahead(ReadAhead::Fixed(128));
+        cache.read(0, 140);
+        assert_eq!(cache.cache.get_size(), 117);
+        assert_eq!(cache.get_data(200).unwrap(), [5; 4096]);
+        assert_eq!(cache.get_data(255).unwrap(), [0; 4096]);
+        assert_eq!(cache.get_data(256), None);
+        assert_eq!(cache.get_data(139), None);
+    }
+
+    #[test]
+    fn policy() {
+        let mut cache = BufCache::new();
+        cache.set_read_ahead(ReadAhead::None);
+        cache.set_cache_policy(cache::CachePolicy::ARC);
+        cache.read(0, 10);
+        cache.read(0, 10);
+        cache.read(0, 11);
+        assert_eq!(cache.cache.get_stat(), cache::CacheStat { hit: 1, miss: 2 });
+    }
+
+    #[test]
+    fn adaptive() {
+        let mut cache = BufCache::new();
+        cache.set_read_ahead(ReadAhead::Adaptive(8));
+        for address in 0..16 {
+            cache.read(0, address);
+        }
+        // 依次在0, 1, 3, 7, 15处缺页，窗口为1, 2, 4, 8, 8
+        assert_eq!(cache.window, 8);
+        assert_eq!(cache.get_data(22).unwrap(), [0; 4096]);
+        assert_eq!(cache.get_data(23), None);
+        cache.read(0, 1000);
+        assert_eq!(cache.window, 1);
+        assert_eq!(cache.get_data(1001), None);
+    }
 }
\ No newline at end of file
@@ -45,7 +45,7 @@ pub fn dir_link(inode: &mut inode_manager::InodeLink, ino: u32, name: String) ->
         ino,
     };
     let buf = DirectoryParser::encode(&entry).unwrap();
-    inode.borrow_mut().write((index * per_size) as u32, per_size as u32, &buf)
+    inode.borrow_mut().write((index * per_size) as u64, per_size as u32, &buf).is_ok()
 }
 
 // Delete a directory entry (name, ino) into the directory inode.
@@ -70,7 +70,7 @@ pub fn dir_unlink(inode:&mut inode_manager::InodeLink, ino: u32, name: String) -
     if index == len {
         return false;
     }
-    inode.borrow_mut().truncate((index * per_size) as u32, per_size as u32)
+    inode.borrow_mut().truncate((index * per_size) as u64, per_size as u64).is_ok()
 }
 
 #[derive(PartialEq, Debug)]
@@ -162,7 +162,7 @@ mod test {
     #[test]
     fn test_dirlookup() {
         let mut inode_manager = inode_manager::InodeManager::new();
-        inode_manager.core_manager.borrow_mut().mount();
+        inode_manager.core_manager.borrow_mut().mount().unwrap();
         let mut link = inode_manager.i_alloc();
         let stat = inode::InodeStat {
             file_type: inode::InodeFileType::Directory,
@@ -172,6 +172,12 @@ mod test {
             gid: 44,
             ref_cnt: 0,
             n_link: 1,
+            generation: 0,
+            atime: 0,
+            mtime: 0,
+            ctime: 0,
+            btime: 0,
+            mode: 0o755,
         };
         link.as_ref().unwrap().borrow_mut().modify_stat(stat);
         dir_link(link.as_mut().unwrap(), 10, "test1.txt".to_string());
@@ -186,7 +192,7 @@ mod test {
     #[test]
     fn test_dirlink() {
         let mut inode_manager = inode_manager::InodeManager::new();
-        inode_manager.core_manager.borrow_mut().mount();
+        inode_manager.core_manager.borrow_mut().mount().unwrap();
         let mut link = inode_manager.i_alloc();
         let stat = inode::InodeStat {
             file_type: inode::InodeFileType::Directory,
@@ -196,6 +202,12 @@ mod test {
             gid: 44,
             ref_cnt: 0,
             n_link: 1,
+            generation: 0,
+            atime: 0,
+            mtime: 0,
+            ctime: 0,
+            btime: 0,
+            mode: 0o755,
         };
         link.as_ref().unwrap().borrow_mut().modify_stat(stat);
         dir_link(link.as_mut().unwrap(), 10, "test.txt".to_string());
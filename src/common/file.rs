@@ -1,6 +1,16 @@
 use crate::inode::inode;
-use crate::inode::inode::InodeStat;
+use crate::inode::inode::{InodeStat, SetTime};
 use crate::inode::inode_manager;
+use crate::core::error::FsError;
+use crate::inode::mode;
+use crate::fake_proc::Cred;
+
+// file_seek的whence，与lseek一致
+pub const SEEK_SET: u32 = 0;
+pub const SEEK_CUR: u32 = 1;
+pub const SEEK_END: u32 = 2;
+pub const SEEK_DATA: u32 = 3;
+pub const SEEK_HOLE: u32 = 4;
 
 #[derive(PartialEq)]
 pub enum FileDescriptorType {
@@ -21,11 +31,17 @@ pub struct FileStat {
     pub ino: u32,
     pub file_type: FileType,
     pub n_link: u8,
-    pub size: u32,
+    pub size: u64,
+    pub generation: u32,   // 与ino一起唯一确定一个文件，ino被复用后generation不同
+    pub atime: u64,        // 以纳秒为单位
+    pub mtime: u64,
+    pub ctime: u64,
+    pub btime: u64,
+    pub mode: u16,
 }
 
 pub struct File {
-    pub off: u32,
+    pub off: u64,
     pub ref_cnt: u8,
     pub read_able: u8,
     pub writeable: u8,
@@ -34,6 +50,26 @@ pub struct File {
 }
 
 impl File {
+    // Open inode for file f, cred must have the requested access.
+    pub fn file_open(&mut self, inode: inode_manager::InodeLink, cred: &Cred, read_able: bool, writeable: bool) -> i32 {
+        let mut mask = 0;
+        if read_able {
+            mask |= mode::R_OK;
+        }
+        if writeable {
+            mask |= mode::W_OK;
+        }
+        if !inode.borrow().permission(cred, mask) {
+            return -FsError::PermissionDenied.errno();
+        }
+        self.off = 0;
+        self.read_able = read_able as u8;
+        self.writeable = writeable as u8;
+        self.fd_type = FileDescriptorType::INODE;
+        self.inode = Some(inode);
+        0
+    }
+
     // Get metadata about file f.
     pub fn file_stat(&self) -> Option<FileStat> {
         if self.fd_type == FileDescriptorType::INODE || self.fd_type == FileDescriptorType::DEVICE {
@@ -53,7 +89,7 @@ impl File {
         if self.fd_type == FileDescriptorType::INODE {
             count = self.inode.as_ref().unwrap().borrow_mut().read(self.off, len, buf);
             if count > 0 {
-                self.off += count as u32;
+                self.off += count as u64;
             }
         }
         count
@@ -66,16 +102,111 @@ impl File {
             return -1;
         }
         if self.fd_type == FileDescriptorType::INODE {
-            let res = self.inode.as_ref().unwrap().borrow_mut().write(self.off, len, &buf);
-            if res {
-                self.off += len;
-                ret = len as i32;
-            } else {
-                ret = -1;
+            match self.inode.as_ref().unwrap().borrow_mut().write(self.off, len, &buf) {
+                Ok(()) => {
+                    self.off += len as u64;
+                    ret = len as i32;
+                }
+                Err(err) => ret = -err.errno(),
             }
         }
         ret
     }
+
+    // Reposition the offset of file f, return the new offset.
+    pub fn file_seek(&mut self, offset: i64, whence: u32) -> i64 {
+        if self.fd_type != FileDescriptorType::INODE {
+            return -1;
+        }
+        let inode = self.inode.as_ref().unwrap().borrow();
+        let pos = match whence {
+            SEEK_SET => Ok(offset),
+            SEEK_CUR => Ok(self.off as i64 + offset),
+            SEEK_END => Ok(inode.size as i64 + offset),
+            SEEK_DATA | SEEK_HOLE if offset < 0 => Err(FsError::NoSuchAddress),
+            SEEK_DATA => inode.seek_data(offset as u64).map(|pos| pos as i64).ok_or(FsError::NoSuchAddress),
+            SEEK_HOLE => inode.seek_hole(offset as u64).map(|pos| pos as i64).ok_or(FsError::NoSuchAddress),
+            _ => Err(FsError::InvalidArgument),
+        };
+        match pos {
+            Ok(pos) if pos < 0 => -FsError::InvalidArgument.errno() as i64,
+            Ok(pos) => {
+                drop(inode);
+                self.off = pos as u64;
+                pos
+            }
+            Err(err) => -err.errno() as i64,
+        }
+    }
+
+    // Set the access and modification time of file f.
+    pub fn file_utimens(&mut self, cred: &Cred, atime: SetTime, mtime: SetTime) -> i32 {
+        if self.fd_type != FileDescriptorType::INODE {
+            return -1;
+        }
+        match self.inode.as_ref().unwrap().borrow_mut().utimens(cred, atime, mtime) {
+            Ok(()) => 0,
+            Err(err) => -err.errno(),
+        }
+    }
+
+    // Read the extended attribute name of file f into buf, return its length.
+    pub fn file_get_xattr(&mut self, cred: &Cred, name: &str, buf: &mut Vec<u8>) -> i32 {
+        if self.fd_type != FileDescriptorType::INODE {
+            return -1;
+        }
+        match self.inode.as_ref().unwrap().borrow_mut().get_xattr(cred, name) {
+            Ok(value) => {
+                *buf = value;
+                buf.len() as i32
+            }
+            Err(err) => -err.errno(),
+        }
+    }
+
+    // Set the extended attribute name of file f.
+    pub fn file_set_xattr(&mut self, cred: &Cred, name: &str, value: &[u8], flags: u32) -> i32 {
+        if self.fd_type != FileDescriptorType::INODE || self.writeable == 0 {
+            return -1;
+        }
+        match self.inode.as_ref().unwrap().borrow_mut().set_xattr(cred, name, value, flags) {
+            Ok(()) => 0,
+            Err(err) => -err.errno(),
+        }
+    }
+
+    // List the extended attribute names of file f into buf, each ends with a NUL byte.
+    pub fn file_list_xattr(&mut self, cred: &Cred, buf: &mut Vec<u8>) -> i32 {
+        if self.fd_type != FileDescriptorType::INODE {
+            return -1;
+        }
+        buf.clear();
+        for name in self.inode.as_ref().unwrap().borrow_mut().list_xattr(cred) {
+            buf.extend_from_slice(name.as_bytes());
+            buf.push(0);
+        }
+        buf.len() as i32
+    }
+
+    // Remove the extended attribute name of file f.
+    pub fn file_remove_xattr(&mut self, cred: &Cred, name: &str) -> i32 {
+        if self.fd_type != FileDescriptorType::INODE || self.writeable == 0 {
+            return -1;
+        }
+        match self.inode.as_ref().unwrap().borrow_mut().remove_xattr(cred, name) {
+            Ok(()) => 0,
+            Err(err) => -err.errno(),
+        }
+    }
+
+    // Flush file f to disk.
+    pub fn file_sync(&mut self) -> i32 {
+        if self.fd_type != FileDescriptorType::INODE {
+            return -1;
+        }
+        self.inode.as_ref().unwrap().borrow_mut().sync();
+        0
+    }
 }
 
 impl File {
@@ -97,6 +228,12 @@ impl File {
             file_type: FileType::TFILE,
             n_link: inode_stat.n_link,
             size: inode_stat.size,
+            generation: inode_stat.generation,
+            atime: inode_stat.atime,
+            mtime: inode_stat.mtime,
+            ctime: inode_stat.ctime,
+            btime: inode_stat.btime,
+            mode: inode_stat.mode,
         }
     }
 
@@ -109,6 +246,12 @@ impl File {
             gid: 0,
             ref_cnt: 0,
             n_link: stat.n_link,
+            generation: stat.generation,
+            atime: stat.atime,
+            mtime: stat.mtime,
+            ctime: stat.ctime,
+            btime: stat.btime,
+            mode: stat.mode,
         }
     }
 }
\ No newline at end of file
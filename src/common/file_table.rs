@@ -68,7 +68,7 @@ mod test {
     #[test]
     fn basics() {
         let mut table = FileTable::new();
-        table.inode_manager.core_manager.borrow_mut().mount();
+        table.inode_manager.core_manager.borrow_mut().mount().unwrap();
         let link = table.file_alloc().unwrap();
         let _ = table.file_dup(&link);
         table.file_close(link);
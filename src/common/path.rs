@@ -1,6 +1,8 @@
 use crate::inode::inode;
 use crate::inode::inode_manager;
 use crate::common::directory;
+use crate::inode::mode;
+use crate::fake_proc::Cred;
 
 // Copy the next element from path into name.
 // Return (path, name).
@@ -38,7 +40,8 @@ pub fn skip_elem(path: String) -> Option<(String, String)> {
 }
 
 // Look up and return the inode for a path name.
-pub fn name_x(i_manager: &mut inode_manager::InodeManager, path: String, name: &mut String, name_i_parent: bool) -> Option<inode_manager::InodeLink> {
+// Every directory on the way must be searchable by cred.
+pub fn name_x(i_manager: &mut inode_manager::InodeManager, cred: &Cred, path: String, name: &mut String, name_i_parent: bool) -> Option<inode_manager::InodeLink> {
     let path = &mut path.clone();
     let mut ip;
     let mut next;
@@ -60,6 +63,9 @@ pub fn name_x(i_manager: &mut inode_manager::InodeManager, path: String, name: &
         if ip.borrow().file_type != inode::InodeFileType::Directory {
             return None;
         }
+        if !ip.borrow().permission(cred, mode::X_OK) {
+            return None;
+        }
         if name_i_parent && path == "" {
             return Some(ip);
         }
@@ -76,13 +82,13 @@ pub fn name_x(i_manager: &mut inode_manager::InodeManager, path: String, name: &
     return Some(ip);
 }
 
-pub fn name_i(i_manager: &mut inode_manager::InodeManager, path: String) -> Option<inode_manager::InodeLink> {
+pub fn name_i(i_manager: &mut inode_manager::InodeManager, cred: &Cred, path: String) -> Option<inode_manager::InodeLink> {
     let mut name = "".to_string();
-    name_x(i_manager, path, &mut name, false)
+    name_x(i_manager, cred, path, &mut name, false)
 }
 
-pub fn name_i_parent(i_manager: &mut inode_manager::InodeManager, path: String, name: &mut String) -> Option<inode_manager::InodeLink> {
-    name_x(i_manager, path, name, true)
+pub fn name_i_parent(i_manager: &mut inode_manager::InodeManager, cred: &Cred, path: String, name: &mut String) -> Option<inode_manager::InodeLink> {
+    name_x(i_manager, cred, path, name, true)
 }
 
 
@@ -107,7 +113,7 @@ mod test {
     #[test]
     fn test_name_x() {
         let mut inode_manager = inode_manager::InodeManager::new();
-        inode_manager.core_manager.borrow_mut().mount();
+        inode_manager.core_manager.borrow_mut().mount().unwrap();
         let mut link = inode_manager.i_alloc();
         inode_manager.i_alloc();
         inode_manager.i_alloc();
@@ -124,6 +130,12 @@ mod test {
             gid: 44,
             ref_cnt: 0,
             n_link: 1,
+            generation: 0,
+            atime: 0,
+            mtime: 0,
+            ctime: 0,
+            btime: 0,
+            mode: 0o755,
         };
         link.as_ref().unwrap().borrow_mut().modify_stat(stat);
         directory::dir_link(link.as_mut().unwrap(), 2, "test1.txt".to_string());
@@ -138,6 +150,12 @@ mod test {
             gid: 44,
             ref_cnt: 0,
             n_link: 1,
+            generation: 0,
+            atime: 0,
+            mtime: 0,
+            ctime: 0,
+            btime: 0,
+            mode: 0o755,
         };
         link.as_ref().unwrap().borrow_mut().modify_stat(stat);
         directory::dir_link(link.as_mut().unwrap(), 5, "test4.txt".to_string());
@@ -145,11 +163,22 @@ mod test {
         directory::dir_link(link.as_mut().unwrap(), 7, "test5.txt".to_string());
         directory::dir_link(link.as_mut().unwrap(), 8, "test6.txt".to_string());
         let mut name = "".to_string();
-        let link = name_x(&mut inode_manager, "/home/a.rs".to_string(), &mut name, false);
+        let root = Cred::root();
+        let link = name_x(&mut inode_manager, &root, "/home/a.rs".to_string(), &mut name, false);
         assert_eq!(name, "".to_string());
         assert_eq!(link.as_ref().unwrap().borrow().ino, 6);
-        let link = name_x(&mut inode_manager, "/home/a.rs".to_string(), &mut name, true);
+        let link = name_x(&mut inode_manager, &root, "/home/a.rs".to_string(), &mut name, true);
         assert_eq!(name, "a.rs".to_string());
         assert_eq!(link.as_ref().unwrap().borrow().ino, 3);
+
+        // 经过的目录都需要搜索权限
+        let mut cred = Cred::new(200, 200);
+        assert!(name_i(&mut inode_manager, &cred, "/home/a.rs".to_string()).is_some());
+        let home = inode_manager.i_get(3).unwrap();
+        home.borrow_mut().chmod(&root, 0o750).unwrap();
+        assert!(name_i(&mut inode_manager, &cred, "/home/a.rs".to_string()).is_none());
+        assert!(name_i_parent(&mut inode_manager, &cred, "/home/a.rs".to_string(), &mut name).is_none());
+        cred.groups.push(44);
+        assert!(name_i(&mut inode_manager, &cred, "/home/a.rs".to_string()).is_some());
     }
 }
\ No newline at end of file
@@ -1,43 +1,62 @@
-use std::collections::HashMap;
-use crate::util::array;
+use std::collections::BTreeSet;
+use crate::core::meta_log;
 
+// 每个表页记录的Page数量
+pub const PAGE_BIT_NUM: u32 = meta_log::PAYLOAD_SIZE as u32 * 8;
+
+// 按表页保存在堆上，未使用的表页不分配内存
 pub struct BIT {
-    pub table: HashMap<u32, bool>, // true: dirty/used false: clean
-    pub sync: bool,                // true 需要持久化到磁盘中
+    pub size: u32,
+    pub table: Vec<Vec<u8>>,       // bit 1: dirty/used 0: clean
+    pub dirty: BTreeSet<u32>,      // 需要持久化到磁盘中的表页
     pub is_op: bool,               // true 等调用end_op才持久化到磁盘中
 }
 
 impl BIT {
-    pub fn new() -> BIT {
+    // size为管理的Page数量
+    pub fn new(size: u32) -> BIT {
+        let page_num = (size + PAGE_BIT_NUM - 1) / PAGE_BIT_NUM;
         BIT {
-            table: HashMap::new(),
-            sync: false,
+            size,
+            table: vec![vec![]; page_num as usize],
+            dirty: BTreeSet::new(),
             is_op: false,
         }
     }
 
-    pub fn init_page(&mut self, address: u32, status: bool) {
-        if self.table.contains_key(&address) {
-            panic!("BIT: init page has exist");
-        }
-        self.table.insert(address, status);
+    pub fn get_page_num(&self) -> u32 {
+        self.table.len() as u32
     }
 
     pub fn get_page(&self, address: u32) -> bool {
-        if !self.table.contains_key(&address) {
+        if address >= self.size {
             panic!("BIT: get not that page");
         }
-        self.table.get(&address).unwrap().clone()
+        let page = &self.table[(address / PAGE_BIT_NUM) as usize];
+        if page.is_empty() {
+            return false;
+        }
+        let index = address % PAGE_BIT_NUM;
+        page[(index / 8) as usize] >> (index % 8) & 1 == 1
     }
 
     pub fn set_page(&mut self, address: u32, status: bool) {
-        if !self.table.contains_key(&address) {
+        if address >= self.size {
             panic!("BIT: set not that page");
         }
-        *self.table.get_mut(&address).unwrap() = status;
-        self.sync = true;
+        let page_no = address / PAGE_BIT_NUM;
+        let page = &mut self.table[page_no as usize];
+        if page.is_empty() {
+            page.resize(meta_log::PAYLOAD_SIZE, 0);
+        }
+        let index = address % PAGE_BIT_NUM;
+        match status {
+            true => page[(index / 8) as usize] |= 1 << (index % 8),
+            false => page[(index / 8) as usize] &= !(1 << (index % 8)),
+        }
+        self.dirty.insert(page_no);
     }
-    
+
     pub fn get_block(&self, block_no: u32) -> Option<[bool; 128]> {
         let mut res = [false; 128];
         let start_index = block_no * 128;
@@ -56,35 +75,44 @@ impl BIT {
         }
     }
 
-    pub fn encode(&self) -> array::Array2<u8> {
-        let mut res = array::Array1::<u8>::new(128 * 4096);
-        res.init(0);
-        for (key, value) in &self.table {
-            let index = key / 8;
-            let off = key % 8;
-            if *value {
-                res.set(index, res.get(index) | 1 << off);
-            }
+    pub fn encode_page(&self, page_no: u32) -> Vec<u8> {
+        let page = &self.table[page_no as usize];
+        if page.is_empty() {
+            return vec![0; meta_log::PAYLOAD_SIZE];
+        }
+        page.clone()
+    }
+
+    // 从磁盘读入一个表页，返回其中已使用的Page
+    pub fn load_page(&mut self, page_no: u32, payload: &[u8]) -> Vec<u32> {
+        if payload.len() != meta_log::PAYLOAD_SIZE {
+            panic!("BIT: load page not matched size");
         }
-        let mut data = array::Array2::<u8>::new(128, 4096);
-        data.init(0);
-        for (index, temp) in res.iter().enumerate() {
-            let i = index / 4096;
-            let j = index % 4096;
-            data.set(i as u32, j as u32, temp);
+        let mut res = vec![];
+        for (i, byte) in payload.iter().enumerate() {
+            for k in 0..8 {
+                if byte >> k & 1 == 1 {
+                    res.push(page_no * PAGE_BIT_NUM + i as u32 * 8 + k);
+                }
+            }
         }
-        data
+        self.table[page_no as usize] = payload.to_vec();
+        res
+    }
+
+    pub fn get_dirty_pages(&self) -> Vec<u32> {
+        self.dirty.iter().cloned().collect()
     }
 
     pub fn need_sync(&self) -> bool {
         if self.is_op {
             return false;
         }
-        self.sync
+        !self.dirty.is_empty()
     }
 
     pub fn sync(&mut self) {
-        self.sync = false;
+        self.dirty.clear();
     }
 
     pub fn begin_op(&mut self) {
@@ -99,34 +127,25 @@ impl BIT {
 #[cfg(test)]
 mod test {
     use super::*;
-    
+
     #[test]
     fn basics() {
-        let mut bit = BIT::new();
-        let mut data = array::Array2::<u8>::new(128, 4096);
-        data.init(0);
-        data.set(100, 3, 3);
-        data.set(10, 2, 68);
-        for (i, byte) in data.iter().enumerate() {
-            let mut byte = byte;
-            for k in 0..8 {
-                let index = i * 8 + k;
-                if byte & 1 == 1 {
-                    bit.init_page(index as u32, true);
-                } else {
-                    bit.init_page(index as u32, false);
-                }
-                byte = byte >> 1;
-            }
-        }
-        assert_eq!(bit.encode(), data);
+        let mut bit = BIT::new(PAGE_BIT_NUM * 2);
+        assert_eq!(bit.get_page_num(), 2);
+        let mut data = vec![0; meta_log::PAYLOAD_SIZE];
+        data[3] = 3;
+        data[2] = 68;
+        let pages = bit.load_page(1, &data);
+        assert_eq!(pages, vec![PAGE_BIT_NUM + 18, PAGE_BIT_NUM + 22, PAGE_BIT_NUM + 24, PAGE_BIT_NUM + 25]);
+        assert_eq!(bit.encode_page(1), data);
+        assert_eq!(bit.encode_page(0), vec![0; meta_log::PAYLOAD_SIZE]);
         assert_eq!(bit.need_sync(), false);
         bit.set_page(200, true);
         assert_eq!(bit.get_page(200), true);
         assert_eq!(bit.need_sync(), true);
+        assert_eq!(bit.get_dirty_pages(), vec![0]);
         let data = [true; 128];
         bit.set_block(10, data);
         assert_eq!(bit.get_block(10).unwrap(), data);
     }
 }
-
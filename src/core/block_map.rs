@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+
+// Block 0与Block 1中依次追加写入的映射表快照，Page头部为标识、序列号与映射项数量
+pub const MAGIC: u32 = 0x5346_424d;
+// 保存快照的Block数量，一个写满后擦除另一个继续写入
+pub const BLOCK_NUM: u32 = 2;
+// 单个Page能保存的映射项数量，超过后不再交换
+pub const MAX_ENTRY_NUM: usize = (4096 - 12) / 8;
+
+// 逻辑Block到物理Block的映射，只记录被交换过的Block
+// Metadata Region与Main Region都通过逻辑Block访问磁盘，交换后可以让Metadata Region轮换到磨损少的Block
+// 保存快照的Block固定不参与交换
+pub struct BlockMap {
+    map: BTreeMap<u32, u32>,
+    sequence: u32,
+    head: u32,
+}
+
+impl BlockMap {
+    pub fn new() -> BlockMap {
+        BlockMap {
+            map: BTreeMap::new(),
+            sequence: 0,
+            head: 0,
+        }
+    }
+
+    pub fn get(&self, block_no: u32) -> u32 {
+        match self.map.get(&block_no) {
+            Some(physical) => *physical,
+            None => block_no,
+        }
+    }
+
+    pub fn get_entry_num(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn can_swap(&self) -> bool {
+        self.map.len() + 2 <= MAX_ENTRY_NUM
+    }
+
+    // 交换两个逻辑Block对应的物理Block
+    pub fn swap(&mut self, block_a: u32, block_b: u32) {
+        if block_a < BLOCK_NUM || block_b < BLOCK_NUM {
+            panic!("BlockMap: swap super block");
+        }
+        let physical_a = self.get(block_a);
+        let physical_b = self.get(block_b);
+        for (block_no, physical) in [(block_a, physical_b), (block_b, physical_a)] {
+            if block_no == physical {
+                self.map.remove(&block_no);
+            } else {
+                self.map.insert(block_no, physical);
+            }
+        }
+    }
+
+    // 返回快照写入的位置与内容，位置在两个Block中循环，进入一个Block时需要先擦除
+    pub fn encode(&mut self) -> (u32, [u8; 4096]) {
+        let mut data = [0; 4096];
+        data[0..4].copy_from_slice(&MAGIC.to_be_bytes());
+        data[4..8].copy_from_slice(&self.sequence.to_be_bytes());
+        data[8..12].copy_from_slice(&(self.map.len() as u32).to_be_bytes());
+        for (i, (block_no, physical)) in self.map.iter().enumerate() {
+            data[12 + i * 8..16 + i * 8].copy_from_slice(&block_no.to_be_bytes());
+            data[16 + i * 8..20 + i * 8].copy_from_slice(&physical.to_be_bytes());
+        }
+        let offset = self.head;
+        self.head = (self.head + 1) % (BLOCK_NUM * 128);
+        self.sequence += 1;
+        (offset, data)
+    }
+
+    // 挂载时依次传入每个Block中的Page，返回false表示该Block之后没有快照
+    pub fn load(&mut self, offset: u32, data: &[u8; 4096]) -> bool {
+        let read_u32 = |index: usize| u32::from_be_bytes([data[index], data[index + 1], data[index + 2], data[index + 3]]);
+        if read_u32(0) != MAGIC {
+            return false;
+        }
+        let sequence = read_u32(4);
+        if sequence < self.sequence {
+            return true;
+        }
+        self.map.clear();
+        for i in 0..read_u32(8) as usize {
+            self.map.insert(read_u32(12 + i * 8), read_u32(16 + i * 8));
+        }
+        self.sequence = sequence + 1;
+        self.head = (offset + 1) % (BLOCK_NUM * 128);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        let mut map = BlockMap::new();
+        map.swap(2, 10);
+        map.swap(2, 20);
+        assert_eq!((map.get(2), map.get(10), map.get(20)), (20, 2, 10));
+        map.swap(10, 20);
+        assert_eq!((map.get(2), map.get(10), map.get(20)), (20, 10, 2));
+        assert_eq!(map.get_entry_num(), 2);
+        let (offset, data_1) = map.encode();
+        assert_eq!(offset, 0);
+        map.swap(2, 20);
+        let (offset, data_2) = map.encode();
+        assert_eq!(offset, 1);
+
+        let mut map = BlockMap::new();
+        assert!(map.load(0, &data_1));
+        assert!(map.load(1, &data_2));
+        assert!(!map.load(2, &[0; 4096]));
+        assert_eq!(map.get_entry_num(), 0);
+        assert_eq!(map.encode().0, 2);
+
+        // Block 0写满后写入Block 1，之后回到Block 0时Block 1中保留最新的快照
+        let mut map = BlockMap::new();
+        map.swap(3, 4);
+        let mut snapshots = vec![];
+        for _ in 0..BLOCK_NUM * 128 + 1 {
+            snapshots.push(map.encode());
+        }
+        assert_eq!((snapshots[128].0, snapshots[256].0), (128, 0));
+        let mut map = BlockMap::new();
+        for (offset, data) in snapshots[129..256].iter() {
+            map.load(*offset, data);
+        }
+        assert_eq!((map.get(3), map.encode().0), (4, 0));
+    }
+}
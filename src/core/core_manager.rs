@@ -1,35 +1,97 @@
 use std::sync::Mutex;
+use std::cmp::min;
+use std::collections::HashMap;
 use crate::buf;
 use crate::core::bit;
 use crate::core::pit;
 use crate::core::vam;
+use crate::core::wear;
+use crate::core::block_map;
+use crate::core::error::FsError;
+use crate::core::meta_log;
+use crate::core::options;
+use crate::metrics;
 use crate::util::array;
 use crate::inode::inode;
 use crate::inode::inode_event;
+use crate::inode::xattr;
+use crate::kv::kv::{self, KV};
 use crate::kv::fake_kv;
 use crate::kv::raw_inode;
 use crate::gc::gc_manager;
 use crate::gc::gc_event;
+use crate::gc::background_gc;
+use crate::gc::temperature::{self, Temperature};
 use crate::gc::gc_manager::PageUsedStatus;
 
-pub struct CoreManager {
+// 默认使用内存中的FakeKV保存Inode，也可以通过new_with_kv指定其他实现
+pub struct CoreManager<T: KV = fake_kv::FakeKV> {
     bit: bit::BIT,
     pit: pit::PIT,
     vam: vam::VAM,
-    kv: fake_kv::FakeKV,
+    meta: meta_log::MetaLog,
+    wear: wear::WearTable,
+    block_map: block_map::BlockMap,
+    wear_threshold: Option<u32>,   // None表示关闭磨损均衡
+    atime_policy: options::AtimePolicy,
+    kv: T,
     gc: gc_manager::GCManager,
+    bg_gc: background_gc::BackgroundGC,
+    temperature: temperature::TemperatureTracker,
     buf_cache: buf::BufCache,
+    main_offset: u32,
+    metrics: metrics::MetricsLink,
 }
 
 impl CoreManager {
     pub fn new() -> CoreManager {
+        CoreManager::new_with_cache(buf::BufCache::new())
+    }
+
+    // size以Page为单位，Block 0与Block 1保留给Super Block与BlockMap，之后依次为Metadata Region与Main Region
+    pub fn new_with_size(size: u32) -> CoreManager {
+        CoreManager::new_with_cache(buf::BufCache::new_with_size(size))
+    }
+
+    pub fn new_with_cache(buf_cache: buf::BufCache) -> CoreManager {
+        CoreManager::new_with_kv(buf_cache, fake_kv::FakeKV::new())
+    }
+}
+
+impl<T: KV> CoreManager<T> {
+    pub fn new_with_kv(buf_cache: buf::BufCache, kv: T) -> CoreManager<T> {
+        let block_num = buf_cache.disk_manager.get_block_num();
+        if block_num <= block_map::BLOCK_NUM {
+            panic!("CoreManager: new with too small disk");
+        }
+        // 各表与虚拟地址空间按Main Region的最大Page数量分配
+        let size = (block_num - block_map::BLOCK_NUM) * 128;
+        let bit = bit::BIT::new(size);
+        let pit = pit::PIT::new(size);
+        let vam = vam::VAM::new(size);
+        let wear = wear::WearTable::new(block_num);
+        let meta = meta_log::MetaLog::new(bit.get_page_num() + pit.get_page_num() + vam.get_tpage_num() + wear.get_page_num());
+        let main_offset = block_map::BLOCK_NUM + meta.get_size();
+        if block_num <= main_offset {
+            panic!("CoreManager: new with too small disk");
+        }
+        let metrics = buf_cache.metrics.clone();
         CoreManager {
-            bit: bit::BIT::new(),
-            pit: pit::PIT::new(),
-            vam: vam::VAM::new(),
-            kv: fake_kv::FakeKV::new(),
-            gc: gc_manager::GCManager::new(),
-            buf_cache: buf::BufCache::new(),
+            bit,
+            pit,
+            vam,
+            meta,
+            wear,
+            block_map: block_map::BlockMap::new(),
+            wear_threshold: None,
+            atime_policy: options::AtimePolicy::Relatime,
+            kv,
+            gc: gc_manager::GCManager::new(block_num - main_offset),
+            bg_gc: background_gc::BackgroundGC::new(0, 0, 0),
+            temperature: temperature::TemperatureTracker::new(),
+            buf_cache,
+            main_offset,
+            metrics,
         }
     }
 
@@ -37,36 +99,69 @@ impl CoreManager {
         todo!()
     }
 
-    pub fn mount(&mut self) {
-        self.read_bit();
-        self.read_pit();
+    pub fn mount(&mut self) -> Result<(), FsError> {
+        self.mount_with_options(options::MountOptions::new())
+    }
+
+    // Metadata Region的格式版本不符时返回InvalidArgument
+    pub fn mount_with_options(&mut self, options: options::MountOptions) -> Result<(), FsError> {
+        self.buf_cache.set_cache_policy(options.buf_cache_policy);
+        self.buf_cache.set_read_ahead(options.read_ahead);
+        self.gc.set_victim_policy(options.gc_victim_policy);
+        self.gc.set_step_size(options.gc_step_size);
+        // 至少保留两个Block，保证GC总有搬移的目标
+        let block_num = self.gc.get_block_num();
+        let reserve = (block_num * options.over_provision + 99) / 100;
+        self.gc.set_reserve(reserve.max(2).min(block_num - 1));
+        self.wear_threshold = match options.wear_leveling {
+            true => Some(options.wear_threshold),
+            false => None,
+        };
+        self.atime_policy = options.atime_policy;
+        self.read_block_map();
+        self.read_meta()?;
+        for block_no in 0..self.gc.get_block_num() {
+            let count = self.wear.get(self.block_map.get(self.main_offset + block_no));
+            self.gc.set_erase_count(block_no, count);
+        }
+        self.bg_gc = background_gc::BackgroundGC::new(options.gc_low_watermark, options.gc_high_watermark, options.gc_budget);
+        if options.background_gc {
+            self.bg_gc.start();
+        }
+        Ok(())
+    }
+
+    pub fn unmount(&mut self) {
+        self.bg_gc.stop();
+        self.sync();
+    }
+
+    pub fn get_atime_policy(&self) -> options::AtimePolicy {
+        self.atime_policy
+    }
+
+    // 将缓存的映射表与BufCache中的脏页全部写入磁盘
+    pub fn sync(&mut self) {
+        self.sync_vam();
+        self.sync_wear();
+        self.buf_cache.sync();
     }
 }
 
 // KV Module
-impl CoreManager {
+impl<T: KV> CoreManager<T> {
     pub fn allocate_inode(&mut self) -> inode::Inode {
         let raw_inode = self.kv.allocate_inode();
         CoreManager::transfer_raw_inode_to_inode(&raw_inode)
     }
 
+    // RawInode中保存的是虚拟地址，GC移动数据时不需要修改
     pub fn get_inode(&mut self, ino: u32) -> inode::Inode {
-        let mut raw_inode = self.kv.get_inode(ino);
-        for entry in raw_inode.data.iter_mut() {
-            let address = entry.address;
-            entry.address = self.vam.get_available_address(entry.size);
-            for i in 0..entry.size {
-                self.vam.insert_map(address+i, entry.address+i);
-            }
-        }
+        let raw_inode = self.kv.get_inode(ino);
         CoreManager::transfer_raw_inode_to_inode(&raw_inode)
     }
 
     pub fn update_inode(&mut self, inode: inode::Inode) {
-        let mut inode = inode;
-        for entry in inode.data.iter_mut() {
-            entry.address = self.vam.get_physic_address(entry.address).unwrap()
-        }
         let raw_inode = CoreManager::transfer_inode_to_raw_inode(&inode);
         self.kv.update_inode(raw_inode);
     }
@@ -82,44 +177,132 @@ impl CoreManager {
     pub fn update_raw_inode(&mut self, raw_inode: raw_inode::RawInode) {
         self.kv.update_inode(raw_inode);
     }
+
+    // 多个Inode的修改与删除一起生效，例如删除文件时同时修改父目录
+    pub fn write_inode_batch(&mut self, inodes: Vec<inode::Inode>, deletes: Vec<u32>) {
+        let mut batch = kv::WriteBatch::new();
+        for inode in inodes.iter() {
+            batch.put(CoreManager::transfer_inode_to_raw_inode(inode));
+        }
+        for ino in deletes {
+            batch.delete(ino);
+        }
+        self.kv.write_batch(batch);
+    }
+
+    // 按ino从小到大遍历所有Inode，供fsck与清理孤立Inode使用
+    pub fn iter_inode(&self) -> impl Iterator<Item = inode::Inode> + '_ {
+        self.kv.iter_inode().map(|raw_inode| CoreManager::transfer_raw_inode_to_inode(&raw_inode))
+    }
 }
 
 // GC Module
-impl CoreManager {
-    pub fn find_next_pos_to_write(&mut self, size: u32) -> u32 {
-        let mut res;
+impl<T: KV> CoreManager<T> {
+    // 空间不足时在前台逐步进行GC，没有可回收的Block时返回NoSpace
+    pub fn find_next_pos_to_write(&mut self, size: u32, temperature: Temperature) -> Result<u32, FsError> {
         loop {
-            res = self.gc.find_next_pos_to_write(size);
-            if res.is_some() {
-                break;
+            if let Some(address) = self.gc.find_next_pos_to_write(size, temperature) {
+                return Ok(address);
+            }
+            if !self.forward_gc() {
+                return Err(FsError::NoSpace);
             }
-            self.forward_gc();
         }
-        res.unwrap()
     }
 
-    pub fn forward_gc(&mut self) {
-        let gc_group = self.gc.generate_gc_event();
-        self.dispose_gc_group(gc_group);
+    // 返回写入位置与长度，长度不超过size与Block中剩余的空间
+    pub fn find_next_range_to_write(&mut self, size: u32, temperature: Temperature) -> Result<(u32, u32), FsError> {
+        loop {
+            if let Some(range) = self.gc.find_next_range_to_write(size, temperature) {
+                return Ok(range);
+            }
+            if !self.forward_gc() {
+                return Err(FsError::NoSpace);
+            }
+        }
     }
 
-    pub fn background_gc(&mut self) {
-        let flag = false;
-        loop {
-            if flag {
-                let gc_group = self.gc.generate_gc_event();
+    // 执行一步增量GC，每步最多搬移step_size个Page
+    pub fn forward_gc(&mut self) -> bool {
+        match self.gc.generate_gc_step() {
+            Some(gc_group) => {
                 self.dispose_gc_group(gc_group);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get_space_stat(&self) -> gc_manager::SpaceStat {
+        self.gc.get_space_stat()
+    }
+
+    // 由上层在没有前台IO时调用，不在写入路径中执行，空闲Block低于低水位时回收，每次最多执行budget步增量GC
+    // 静态磨损均衡只在后台GC运行时进行，与普通回收共用每次的配额
+    pub fn background_gc(&mut self) {
+        let mut free_num = self.gc.get_free_block_num();
+        if !self.bg_gc.wake(free_num) {
+            return;
+        }
+        if self.wear_leveling() {
+            self.bg_gc.record_wear_leveling();
+        }
+        for _ in 0..self.bg_gc.get_budget() {
+            let gc_group = match self.gc.generate_gc_step() {
+                Some(gc_group) => gc_group,
+                None => {
+                    self.bg_gc.sleep();
+                    return;
+                }
+            };
+            let (move_num, erase_num) = self.dispose_gc_group(gc_group);
+            self.bg_gc.record_step(move_num);
+            if erase_num > 0 {
+                self.bg_gc.record_round();
+            }
+            free_num = self.gc.get_free_block_num();
+            if self.bg_gc.is_satisfied(free_num) {
+                self.bg_gc.sleep();
+                return;
             }
         }
+        self.bg_gc.record_yield();
+    }
+
+    // 擦除次数差距过大时把擦除次数最少的Block作为回收对象，其中的冷数据由之后的增量GC搬走
+    pub fn wear_leveling(&mut self) -> bool {
+        match self.wear_threshold {
+            Some(threshold) => self.gc.set_wear_victim(threshold),
+            None => false,
+        }
+    }
+
+    // 指定文件之后写入数据的温度
+    pub fn set_temperature_hint(&mut self, ino: u32, temperature: Temperature) {
+        self.temperature.set_hint(ino, temperature);
+    }
+
+    pub fn get_background_gc_stat(&self) -> background_gc::BackgroundGCStat {
+        self.bg_gc.get_stat()
     }
 
     pub fn set_main_table_page(&mut self, address: u32, status: PageUsedStatus) {
         self.gc.set_table(address, status);
     }
 
-    pub fn dispose_gc_group(&mut self, gc_group: gc_event::GCEventGroup) {
+    // 返回搬移的Page数量与擦除的Block数量
+    pub fn dispose_gc_group(&mut self, gc_group: gc_event::GCEventGroup) -> (u32, u32) {
         let mut gc_group = gc_group;
         CoreManager::sort_gc_event(&mut gc_group);
+        let (mut move_num, mut erase_num) = (0, 0);
+        for event in gc_group.events.iter() {
+            match event {
+                gc_event::GCEvent::Move(event) => move_num += event.size,
+                gc_event::GCEvent::Erase(_) => erase_num += 1,
+                _ => (),
+            }
+        }
+        self.metrics.borrow_mut().record_gc(move_num, erase_num);
         for event in gc_group.events {
             match event {
                 gc_event::GCEvent::Erase(event) => {
@@ -134,77 +317,149 @@ impl CoreManager {
                     self.bit.end_op();
                     self.pit.end_op();
                     self.erase_block(event.block_no, true);
+                    self.gc.erase_block(event.block_no);
                 }
                 gc_event::GCEvent::Move(event) => {
                     let o_address = event.o_address;
                     let d_address = event.d_address;
                     let size = event.size;
-                    let ino = event.ino;
                     let mut data = vec![];
+                    let mut owners = vec![];
                     for i in o_address..o_address + size {
                         data.push(self.read_page(i, true));
-                        let v_address = self.vam.get_virtual_address(i);
-                        if v_address.is_some() {
-                            self.vam.update_map(d_address + i, v_address.unwrap());
+                        let mut owner = self.pit.get_page(i);
+                        if let Some(v_address) = self.get_virtual_address(i) {
+                            self.update_map(d_address + i - o_address, v_address);
                         }
                         self.dirty_pit(i);
+                        owner.version = self.pit.next_version();
+                        owners.push(owner);
                     }
                     for i in d_address..d_address + size {
                         self.update_bit(i, true);
-                        self.update_pit(i, ino);
+                        self.update_pit(i, owners[(i - d_address) as usize]);
                         self.write_page(i, data[(i - d_address) as usize], true);
                     }
-                    let mut raw_inode = self.get_raw_inode(ino);
-                    for entry in raw_inode.data.iter_mut() {
-                        if entry.address == o_address {
-                            entry.address = d_address;
-                            break;
-                        }
-                    }
-                    self.update_raw_inode(raw_inode);
                 }
                 _ => ()
             }
         }
+        (move_num, erase_num)
     }
 }
 
-// 管理BIT Region
-impl CoreManager {
-    pub fn read_bit(&mut self) {
-        let mut data_1 = self.read_block(1, false);
-        let data_2 = self.read_block(2, false);
-        let mut flag = false;
-        for page in data_2.iter() {
-            for byte in page.iter() {
-                if byte & 0b1111_1111 != 0 {
-                    flag = true;
-                    break;
-                }
+// Metrics Module
+impl<T: KV> CoreManager<T> {
+    pub fn record_host_write(&mut self, len: u32) {
+        self.metrics.borrow_mut().record_host_write(len);
+    }
+
+    pub fn get_metrics(&self) -> metrics::MetricsSnapshot {
+        let erase_count = (0..self.gc.get_block_num())
+            .map(|block_no| self.gc.get_block_info(block_no).erase_count)
+            .collect();
+        metrics::MetricsSnapshot {
+            metrics: *self.metrics.borrow(),
+            erase_count,
+        }
+    }
+
+    // 只清零计数，擦除次数是磁盘的状态，不受影响
+    pub fn reset_metrics(&mut self) {
+        self.metrics.borrow_mut().reset();
+    }
+}
+
+// 管理Metadata Region，BIT、PIT、VAM与擦除次数的表页依次编号
+impl<T: KV> CoreManager<T> {
+    // 扫描Metadata Region，读入每个表页的最新副本
+    pub fn read_meta(&mut self) -> Result<(), FsError> {
+        let mut pages = vec![];
+        for offset in 0..self.meta.get_size() * 128 {
+            let data = self.read_page(block_map::BLOCK_NUM * 128 + offset, false);
+            if let Some((page_no, sequence)) = meta_log::decode_page(&data)? {
+                pages.push((page_no, sequence, offset));
             }
         }
-        if flag {
-            self.erase_block(1, false);
-            self.write_block(1, data_2.dup(), false);
-            self.erase_block(2, false);
-            data_1 = data_2;
+        self.meta.recover(pages);
+        // 搬移下一个Block的过程中断时继续完成
+        if self.meta.get_head() % 128 != 0 {
+            self.relocate_meta_block((self.meta.get_head() / 128 + 1) % self.meta.get_size());
+        }
+        let bit_num = self.bit.get_page_num();
+        let pit_num = self.pit.get_page_num();
+        let vam_num = self.vam.get_tpage_num();
+        for page_no in 0..self.meta.get_page_num() {
+            // Translation Page在访问时才读入
+            if page_no >= bit_num + pit_num && page_no < bit_num + pit_num + vam_num {
+                continue;
+            }
+            let payload = match self.read_meta_page(page_no) {
+                Some(payload) => payload,
+                None => continue,
+            };
+            if page_no < bit_num {
+                self.set_bit(page_no, &payload);
+            } else if page_no < bit_num + pit_num {
+                self.set_pit(page_no - bit_num, &payload);
+            } else {
+                self.wear.load_page(page_no - bit_num - pit_num - vam_num, &payload);
+            }
         }
-        self.set_bit(CoreManager::truncate_array_1_to_array_2(data_1));
+        self.recover_vam();
+        self.resolve_pit();
+        Ok(())
     }
 
-    pub fn set_bit(&mut self, data: array::Array2::<u8>) {
-        for (i, byte) in data.iter().enumerate() {
-            let mut byte = byte;
-            for k in 0..8 {
-                let index = i * 8 + k;
-                if byte & 1 == 1 {
-                    self.bit.init_page(index as u32, true);
-                    self.set_main_table_page(index as u32, PageUsedStatus::Dirty);
-                } else {
-                    self.bit.init_page(index as u32, false);
-                    self.set_main_table_page(index as u32, PageUsedStatus::Clean);
-                }
-                byte = byte >> 1;
+    pub fn read_meta_page(&mut self, page_no: u32) -> Option<Vec<u8>> {
+        let offset = self.meta.get_location(page_no)?;
+        let data = self.read_page(block_map::BLOCK_NUM * 128 + offset, false);
+        Some(data[meta_log::HEADER_SIZE..].to_vec())
+    }
+
+    // 写入位置到达新的Block时，其中的表页已经在进入上一个Block时搬走，可以直接擦除
+    // 擦除后先把下一个Block中有效的表页搬过来，新的副本写入后旧的副本才会在下一次擦除时丢弃
+    pub fn write_meta_page(&mut self, page_no: u32, payload: &[u8]) {
+        while self.meta.get_head() % 128 == 0 {
+            let block_no = self.meta.get_head() / 128;
+            if self.meta.get_block_valid(block_no) != 0 {
+                panic!("MetaLog: erase block with valid pages");
+            }
+            self.rotate_meta_block(block_map::BLOCK_NUM + block_no);
+            self.erase_block(block_map::BLOCK_NUM + block_no, false);
+            if self.relocate_meta_block((block_no + 1) % self.meta.get_size()) < 128 {
+                break;
+            }
+        }
+        self.append_meta_page(page_no, payload);
+    }
+
+    // 把Block中有效的表页追加到写入位置，返回搬移的数量
+    pub fn relocate_meta_block(&mut self, block_no: u32) -> u32 {
+        let mut pages = vec![];
+        for (page_no, offset) in self.meta.get_block_pages(block_no) {
+            let data = self.read_page(block_map::BLOCK_NUM * 128 + offset, false);
+            pages.push((page_no, data[meta_log::HEADER_SIZE..].to_vec()));
+        }
+        for (page_no, payload) in pages.iter() {
+            self.append_meta_page(*page_no, payload);
+        }
+        pages.len() as u32
+    }
+
+    fn append_meta_page(&mut self, page_no: u32, payload: &[u8]) {
+        let (offset, sequence) = self.meta.append(page_no);
+        self.write_page(block_map::BLOCK_NUM * 128 + offset, meta_log::encode_page(page_no, sequence, payload), false);
+    }
+}
+
+// 管理BIT
+impl<T: KV> CoreManager<T> {
+    pub fn set_bit(&mut self, page_no: u32, payload: &[u8]) {
+        let page_num = self.gc.get_block_num() * 128;
+        for address in self.bit.load_page(page_no, payload) {
+            if address < page_num {
+                self.set_main_table_page(address, PageUsedStatus::Dirty);
             }
         }
     }
@@ -218,14 +473,13 @@ impl CoreManager {
         self.sync_bit();
     }
 
+    // 只写回修改过的表页
     pub fn sync_bit(&mut self) {
         if self.bit.need_sync() {
-            let data = self.bit.encode();
-            let data = CoreManager::truncate_array_2_to_array_1(data);
-            self.write_block(2, data.dup(), false);
-            self.erase_block(1, false);
-            self.write_block(1, data, false);
-            self.erase_block(2, false);
+            for page_no in self.bit.get_dirty_pages() {
+                let payload = self.bit.encode_page(page_no);
+                self.write_meta_page(page_no, &payload);
+            }
             self.bit.sync();
         }
     }
@@ -240,43 +494,185 @@ impl CoreManager {
     }
 }
 
-// 管理PIT Region
-impl CoreManager {
-    // 因为会爆栈，暂时改成1个Block
-    pub fn read_pit(&mut self) {
-        let mut data_1 = self.read_block(3, false);
-        let data_2 = self.read_block(4, false);
-        let mut flag = false;
-        for page in data_2.iter() {
-            for byte in page.iter() {
-                if byte & 0b1111_1111 != 0 {
-                    flag = true;
-                    break;
+// 管理PIT
+impl<T: KV> CoreManager<T> {
+    pub fn set_pit(&mut self, page_no: u32, payload: &[u8]) {
+        for (address, owner) in self.pit.load_page(page_no, payload) {
+            self.set_main_table_page(address, PageUsedStatus::Busy(owner.ino));
+        }
+    }
+
+    // 写入过程中断时PIT中可能留有未提交的新Page，或搬移、覆盖前的旧副本
+    // 只保留Inode中的Extent引用其虚拟地址的Page，同一虚拟地址有多个副本时保留version最大的，并让映射指向它
+    pub fn resolve_pit(&mut self) {
+        let mut pages: HashMap<u32, Vec<(u32, pit::PageOwner)>> = HashMap::new();
+        for (address, owner) in self.pit.table.iter() {
+            pages.entry(owner.ino).or_default().push((*address, *owner));
+        }
+        let mut latest: HashMap<u32, (u32, u32)> = HashMap::new();
+        let mut stale = vec![];
+        for (ino, pages) in pages {
+            let raw_inode = match self.kv.has_inode(ino) {
+                true => Some(self.kv.get_inode(ino)),
+                false => None,
+            };
+            for (address, owner) in pages {
+                let committed = raw_inode.as_ref().map_or(false, |raw_inode| {
+                    raw_inode.data.iter().chain(raw_inode.xattr_entry.iter())
+                        .any(|entry| owner.v_address >= entry.address && owner.v_address < entry.address + entry.size)
+                });
+                if !committed {
+                    stale.push(address);
+                    continue;
+                }
+                match latest.get(&owner.v_address) {
+                    Some(&(version, o_address)) if (version, o_address) > (owner.version, address) => stale.push(address),
+                    Some(&(_, o_address)) => {
+                        stale.push(o_address);
+                        latest.insert(owner.v_address, (owner.version, address));
+                    }
+                    None => {
+                        latest.insert(owner.v_address, (owner.version, address));
+                    }
+                }
+            }
+        }
+        for (v_address, (_, address)) in latest {
+            if self.get_physic_address(v_address) != Some(address) {
+                self.insert_map(address, v_address);
+            }
+        }
+        for address in stale {
+            self.dirty_pit(address);
+        }
+    }
+
+    // 反向映射不常驻内存，PIT中记录了写入时的虚拟地址，确认映射确实指向该Page
+    pub fn get_virtual_address(&mut self, address: u32) -> Option<u32> {
+        let v_address = self.pit.table.get(&address)?.v_address;
+        match self.get_physic_address(v_address) == Some(address) {
+            true => Some(v_address),
+            false => None,
+        }
+    }
+
+    // 根据所有者在Inode中找到包含该Page的Extent，返回对应的虚拟地址
+    pub fn locate_page(&mut self, owner: pit::PageOwner) -> Option<u32> {
+        if !self.kv.has_inode(owner.ino) {
+            return None;
+        }
+        let raw_inode = self.kv.get_inode(owner.ino);
+        if owner.offset >= xattr::EXTENT_OFFSET {
+            let entry = raw_inode.xattr_entry?;
+            let index = owner.offset - xattr::EXTENT_OFFSET;
+            if index % 4096 == 0 && index < entry.size as u64 * 4096 {
+                return Some(entry.address + (index / 4096) as u32);
+            }
+            return None;
+        }
+        for entry in raw_inode.data.iter() {
+            if owner.offset >= entry.offset && owner.offset < entry.offset + entry.size as u64 * 4096 {
+                let index = owner.offset - entry.offset;
+                if index % 4096 == 0 {
+                    return Some(entry.address + (index / 4096) as u32);
                 }
             }
         }
-        if flag {
-            self.erase_block(1, false);
-            self.write_block(1, data_2.dup(), false);
-            self.erase_block(2, false);
-            data_1 = data_2;
+        None
+    }
+
+    // Extent的偏移改变后更新其中每个Page的所有者
+    pub fn restamp_entry(&mut self, ino: u32, entry: &inode::InodeEntry) {
+        for i in 0..entry.size {
+            let address = self.get_physic_address(entry.address + i).unwrap();
+            let mut owner = self.pit.get_page(address);
+            if owner.ino != ino || owner.offset != entry.offset + i as u64 * 4096 {
+                owner.ino = ino;
+                owner.offset = entry.offset + i as u64 * 4096;
+                self.update_pit(address, owner);
+            }
+        }
+    }
+
+    // 释放Extent占用的所有Page
+    pub fn release_entry(&mut self, entry: &inode::InodeEntry) {
+        for v_address in entry.address..entry.address + entry.size {
+            self.release_page(v_address);
+        }
+    }
+
+    pub fn release_page(&mut self, v_address: u32) {
+        let address = self.get_physic_address(v_address).unwrap();
+        self.dirty_pit(address);
+        self.delete_map(address, v_address);
+    }
+
+    // 将Page写入新分配的位置，所有者偏移从offset开始，返回起始虚拟地址
+    // 虚拟地址连续，物理位置按Block中剩余的空间分段分配，失败时释放已写入的Page
+    pub fn write_extent(&mut self, ino: u32, offset: u64, pages: &[[u8; 4096]], temperature: Temperature) -> Result<u32, FsError> {
+        let size = pages.len() as u32;
+        let start = self.vam.get_available_address(size);
+        let mut index = 0;
+        while index < size {
+            let (mut address, len) = match self.find_next_range_to_write(size - index, temperature) {
+                Ok(range) => range,
+                Err(err) => {
+                    for v_address in start..start + index {
+                        self.release_page(v_address);
+                    }
+                    self.vam.free_address(start + index, size - index);
+                    return Err(err);
+                }
+            };
+            self.metrics.borrow_mut().record_data_write(len);
+            for i in index..index + len {
+                self.write_page(address, pages[i as usize], true);
+                self.update_bit(address, true);
+                let owner = pit::PageOwner {
+                    ino,
+                    offset: offset + i as u64 * 4096,
+                    version: self.pit.next_version(),
+                    v_address: start + i,
+                };
+                self.update_pit(address, owner);
+                self.insert_map(address, start + i);
+                address += 1;
+            }
+            index += len;
         }
-        self.set_pit(CoreManager::truncate_array_1_to_array_2(data_1))
+        Ok(start)
     }
 
-    pub fn set_pit(&mut self, data: array::Array2::<u8>) {
-        let iter = pit::DataRegion::new(&data);
-        for (index, ino) in iter.enumerate() {
-            if ino != 0 {
-                self.pit.init_page(index as u32, ino);
-                self.set_main_table_page(index as u32, PageUsedStatus::Busy(ino));
+    // 依次写入各事件的新数据，返回每个事件对应的Entry，失败时释放本次已写入的Page
+    pub fn write_extents(&mut self, ino: u32, extents: Vec<Vec<(u64, u64, Vec<[u8; 4096]>)>>, temperature: Temperature) -> Result<Vec<Vec<inode::InodeEntry>>, FsError> {
+        let mut res: Vec<Vec<inode::InodeEntry>> = vec![];
+        for event_extents in extents {
+            let mut entries = vec![];
+            for (offset, len, pages) in event_extents {
+                match self.write_extent(ino, offset, &pages, temperature) {
+                    Ok(address) => entries.push(inode::InodeEntry {
+                        offset,
+                        len,
+                        size: pages.len() as u32,
+                        valid: true,
+                        address,
+                    }),
+                    Err(err) => {
+                        for entry in res.iter().flatten().chain(entries.iter()) {
+                            self.release_entry(entry);
+                        }
+                        return Err(err);
+                    }
+                }
             }
+            res.push(entries);
         }
+        Ok(res)
     }
 
-    pub fn update_pit(&mut self, address: u32, status: u32) {
+    pub fn update_pit(&mut self, address: u32, status: pit::PageOwner) {
         self.pit.set_page(address, status);
-        self.set_main_table_page(address, PageUsedStatus::Busy(status));
+        self.set_main_table_page(address, PageUsedStatus::Busy(status.ino));
         self.sync_pit();
     }
 
@@ -291,15 +687,14 @@ impl CoreManager {
         self.set_main_table_page(address, PageUsedStatus::Clean);
         self.sync_pit();
     }
-    
+
     pub fn sync_pit(&mut self) {
         if self.pit.need_sync() {
-            let data = self.pit.encode();
-            let data = CoreManager::truncate_array_2_to_array_1(data);
-            self.write_block(4, data.dup(), false);
-            self.erase_block(3, false);
-            self.write_block(3, data, false);
-            self.erase_block(4, false);
+            let base = self.bit.get_page_num();
+            for page_no in self.pit.get_dirty_pages() {
+                let payload = self.pit.encode_page(page_no);
+                self.write_meta_page(base + page_no, &payload);
+            }
             self.pit.sync();
         }
     }
@@ -314,14 +709,139 @@ impl CoreManager {
     }
 }
 
+// 管理磨损均衡
+impl<T: KV> CoreManager<T> {
+    // 每个Block中的快照从Page 0开始依次写入，序列号最大的即为最新的映射表
+    pub fn read_block_map(&mut self) {
+        for block_no in 0..block_map::BLOCK_NUM {
+            for offset in block_no * 128..(block_no + 1) * 128 {
+                let data = self.read_page(offset, false);
+                if !self.block_map.load(offset, &data) {
+                    break;
+                }
+            }
+        }
+    }
+
+    // 进入另一个Block时才擦除它，擦除与写入之间中断时原来Block中的快照仍然完整
+    pub fn write_block_map(&mut self) {
+        let (offset, data) = self.block_map.encode();
+        if offset % 128 == 0 {
+            self.erase_block(offset / 128, false);
+        }
+        self.write_page(offset, data, false);
+    }
+
+    // Metadata Region的Block回收时，如果比最少擦除的空闲Block多擦除threshold次以上，就与其交换物理Block
+    pub fn rotate_meta_block(&mut self, block_no: u32) {
+        let threshold = match self.wear_threshold {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        let free_block = match self.gc.find_free_block() {
+            Some(free_block) => free_block,
+            None => return,
+        };
+        let meta_count = self.wear.get(self.block_map.get(block_no));
+        let free_count = self.wear.get(self.block_map.get(self.main_offset + free_block));
+        if meta_count <= free_count + threshold || !self.block_map.can_swap() {
+            return;
+        }
+        self.block_map.swap(block_no, self.main_offset + free_block);
+        self.write_block_map();
+        // 换到Main Region的物理Block中还有旧的表页
+        self.erase_block(free_block, true);
+        self.gc.erase_block(free_block);
+        let count = self.wear.get(self.block_map.get(self.main_offset + free_block));
+        self.gc.set_erase_count(free_block, count);
+    }
+
+    pub fn sync_wear(&mut self) {
+        let base = self.bit.get_page_num() + self.pit.get_page_num() + self.vam.get_tpage_num();
+        while self.wear.need_sync() {
+            for page_no in self.wear.take_dirty_pages() {
+                let payload = self.wear.encode_page(page_no);
+                self.write_meta_page(base + page_no, &payload);
+            }
+        }
+    }
+}
+
+// 管理VAM
+impl<T: KV> CoreManager<T> {
+    pub fn get_physic_address(&mut self, v_address: u32) -> Option<u32> {
+        self.load_vam_page(v_address / vam::ENTRY_NUM);
+        self.vam.get_physic_address(v_address)
+    }
+
+    pub fn insert_map(&mut self, address: u32, v_address: u32) {
+        self.load_vam_page(v_address / vam::ENTRY_NUM);
+        self.vam.insert_map(address, v_address);
+    }
+
+    pub fn update_map(&mut self, address: u32, v_address: u32) {
+        self.load_vam_page(v_address / vam::ENTRY_NUM);
+        self.vam.update_map(address, v_address);
+    }
+
+    pub fn delete_map(&mut self, address: u32, v_address: u32) {
+        self.load_vam_page(v_address / vam::ENTRY_NUM);
+        self.vam.delete_map(address, v_address);
+    }
+
+    // 挂载时根据Inode中的Extent恢复已使用的虚拟地址，其余地址上残留的映射在读入Translation Page时清除
+    pub fn recover_vam(&mut self) {
+        let mut extents = vec![];
+        for raw_inode in self.kv.iter_inode() {
+            for entry in raw_inode.data.iter().chain(raw_inode.xattr_entry.iter()) {
+                if entry.size > 0 {
+                    extents.push((entry.address, entry.size));
+                }
+            }
+        }
+        for (v_address, size) in extents {
+            self.vam.reserve_address(v_address, size);
+        }
+    }
+
+    // 将缓存中修改过的Translation Page写回
+    pub fn sync_vam(&mut self) {
+        let base = self.bit.get_page_num() + self.pit.get_page_num();
+        for (tpage_no, page) in self.vam.take_dirty_pages() {
+            self.write_meta_page(base + tpage_no, &page.encode());
+        }
+    }
+
+    // 缓存未命中时读入Translation Page，替换出的脏页需要写回
+    pub fn load_vam_page(&mut self, tpage_no: u32) {
+        if self.vam.is_cached(tpage_no) {
+            return;
+        }
+        let base = self.bit.get_page_num() + self.pit.get_page_num();
+        let page = match self.read_meta_page(base + tpage_no) {
+            Some(payload) => vam::TranslationPage::decode(&payload),
+            None => vam::TranslationPage::new(),
+        };
+        if let Some((tpage_no, page)) = self.vam.cache_page(tpage_no, page) {
+            self.write_meta_page(base + tpage_no, &page.encode());
+        }
+    }
+}
+
 // 调用下层的接口，对上不可见
-impl CoreManager {
+impl<T: KV> CoreManager<T> {
+    // 逻辑地址经过BlockMap转换为磁盘上的物理地址
+    pub fn get_device_address(&self, address: u32, is_main: bool) -> u32 {
+        let address = match is_main {
+            true => address + self.main_offset * 128,
+            false => address,
+        };
+        self.block_map.get(address / 128) * 128 + address % 128
+    }
+
     pub fn read_page(&mut self, address: u32, is_main: bool) -> [u8; 4096] {
-        if is_main {
-            self.buf_cache.read(0, address + 5 * 128)
-        } else {
-            self.buf_cache.read(0, address)
-        }
+        let address = self.get_device_address(address, is_main);
+        self.buf_cache.read(0, address)
     }
 
     pub fn read_block(&mut self, block_no: u32, is_main: bool) -> array::Array1::<[u8; 4096]> {
@@ -342,11 +862,8 @@ impl CoreManager {
     }
 
     pub fn write_page(&mut self, address: u32, data: [u8; 4096], is_main: bool) {
-        if is_main  {
-            self.buf_cache.write(0, address + 5 * 128, data);
-        } else {
-            self.buf_cache.write(0, address, data);
-        }
+        let address = self.get_device_address(address, is_main);
+        self.buf_cache.write(0, address, data);
     }
 
     pub fn write_block(&mut self, block_no: u32, data: array::Array1::<[u8; 4096]>, is_main: bool) {
@@ -360,100 +877,84 @@ impl CoreManager {
     }
 
     pub fn erase_block(&mut self, block_no: u32, is_main: bool) {
-        if is_main {
-            self.buf_cache.erase(0, block_no + 5);
-        } else {
-            self.buf_cache.erase(0, block_no);
-        }
+        let block_no = self.get_device_address(block_no * 128, is_main) / 128;
+        self.buf_cache.erase(0, block_no);
+        self.wear.increase(block_no);
     }
 }
 
 // 对上层提供的读写接口
-impl CoreManager {
+impl<T: KV> CoreManager<T> {
     pub fn read_data(&mut self, v_address: u32) -> [u8; 4096] {
-        let address = self.vam.get_physic_address(v_address).unwrap();
+        let address = self.get_physic_address(v_address).unwrap();
         self.read_page(address, true)
     }
 
-    pub fn dispose_event_group(&mut self, event_group: inode_event::InodeEventGroup) -> Option<inode::Inode> {
+    // 先写入全部新数据，成功后才释放或修改Inode原有的Page
+    // 写入前检查空间，不足或写入失败时不做任何修改，返回NoSpace
+    pub fn dispose_event_group(&mut self, event_group: inode_event::InodeEventGroup) -> Result<Option<inode::Inode>, FsError> {
         let mut inode = event_group.dup().inode;
         let mut event_group = event_group;
         CoreManager::sort_inode_event(&mut event_group);
         event_group.debug();
         if event_group.need_delete {
-            for entry in inode.data.iter() {
-                let address = self.vam.get_physic_address(entry.address).unwrap();
-                for i in 0..entry.size {
-                    self.dirty_pit(address + i);
-                    let v_address = self.vam.get_virtual_address(address + i).unwrap();
-                    self.vam.delete_map(address, v_address);
-                }
+            for entry in inode.data.iter().chain(inode.xattr_entry.iter()) {
+                self.release_entry(entry);
             }
             self.kv.delete_inode(inode.ino);
-            None
+            self.temperature.remove(inode.ino);
+            Ok(None)
         } else {
+            // 旧的Page在新数据写入之后才释放，不能用来容纳新数据
+            let extents: Vec<Vec<(u64, u64, Vec<[u8; 4096]>)>> = event_group.events.iter()
+                .map(|event| CoreManager::split_event(event))
+                .collect();
+            let required: u32 = extents.iter().flatten().map(|extent| extent.2.len() as u32).sum();
+            if required > self.gc.get_space_stat().available {
+                return Err(FsError::NoSpace);
+            }
             for entry in inode.data.iter_mut() {
                 if entry.valid == false {
                     entry.valid = true;
                 }
             }
-            for event in event_group.events {
+            let temperature = self.temperature.classify(inode.ino, inode.file_type == inode::InodeFileType::Directory);
+            let entries = self.write_extents(inode.ino, extents, temperature)?;
+            if !event_group.events.is_empty() {
+                self.temperature.record_update(inode.ino);
+            }
+            // AddContent被拆成多个Entry或不产生Entry，之后事件的index随之偏移
+            let mut shift: i32 = 0;
+            for (event, entries) in event_group.events.into_iter().zip(entries) {
                 match event {
                     inode_event::InodeEvent::AddContent(event) => {
-                        let mut address = self.find_next_pos_to_write(event.size);
-                        let mut v_address = self.vam.get_available_address(event.size);
-                        let entry = inode::InodeEntry {
-                            offset: event.offset,
-                            len: event.len,
-                            size: event.size,
-                            valid: true,
-                            address: v_address,
-                        };
-                        for i in 0..event.size {
-                            let mut page = [0; 4096];
-                            for j in 0..4096 {
-                                let byte = event.content.get((i * 4096 + j) as usize);
-                                if byte.is_some() {
-                                    page[j as usize] = *byte.unwrap();
-                                } else {
-                                    page[j as usize] = 0;
-                                }
-                            }
-                            self.write_page(address, page, true);
-                            self.update_bit(address, true);
-                            self.update_pit(address, inode.ino);
-                            self.vam.insert_map(address, v_address);
-                            address += 1;
-                            v_address += 1;
+                        let index = (event.index as i32 + shift) as usize;
+                        shift += entries.len() as i32 - 1;
+                        for (k, entry) in entries.into_iter().enumerate() {
+                            inode.data.insert(index + k, entry);
                         }
-                        inode.data.insert(event.index as usize, entry);
-
                     }
                     inode_event::InodeEvent::TruncateContent(event) => {
-                        let mut entry = inode.data.get_mut(event.index as usize).unwrap();
+                        let mut entry = inode.data.get_mut((event.index as i32 + shift) as usize).unwrap();
                         entry.len = event.len;
                         entry.size = event.size;
                         entry.offset = event.offset;
-                        let address = self.vam.get_physic_address(event.v_address).unwrap();
-                        for i in event.size..event.o_size {
-                            self.dirty_pit(address + i);
-                            let v_address = self.vam.get_virtual_address(address + i).unwrap();
-                            self.vam.delete_map(address, v_address);
+                        for v_address in event.v_address + event.size..event.v_address + event.o_size {
+                            self.release_page(v_address);
                         }
+                        self.restamp_entry(inode.ino, entry);
 
                     }
                     inode_event::InodeEvent::ChangeContent(event) => {
-                        let mut entry = inode.data.get_mut(event.index as usize).unwrap();
+                        let mut entry = inode.data.get_mut((event.index as i32 + shift) as usize).unwrap();
                         entry.offset = event.offset;
                         entry.address = event.v_address;
+                        self.restamp_entry(inode.ino, entry);
                     }
                     inode_event::InodeEvent::DeleteContent(event) => {
-                        let mut entry = inode.data.get_mut(event.index as usize).unwrap();
-                        let address = self.vam.get_physic_address(event.v_address).unwrap();
-                        for i in 0..event.size {
-                            self.dirty_pit(address + i);
-                            let v_address = self.vam.get_virtual_address(address + i).unwrap();
-                            self.vam.delete_map(address, v_address);
+                        let mut entry = inode.data.get_mut((event.index as i32 + shift) as usize).unwrap();
+                        for v_address in event.v_address..event.v_address + event.size {
+                            self.release_page(v_address);
                         }
                         entry.valid = false;
                     }
@@ -464,6 +965,21 @@ impl CoreManager {
                         inode.uid = event.uid;
                         inode.gid = event.gid;
                         inode.n_link = event.n_link;
+                        inode.mode = event.mode;
+                        inode.atime = event.atime;
+                        inode.mtime = event.mtime;
+                        inode.ctime = event.ctime;
+                    }
+                    inode_event::InodeEvent::SetXattr(event) => {
+                        if let Some(entry) = inode.xattr_entry.take() {
+                            self.release_entry(&entry);
+                        }
+                        inode.xattr = vec![];
+                        if event.content.len() <= xattr::INLINE_SIZE {
+                            inode.xattr = event.content;
+                        } else {
+                            inode.xattr_entry = entries.into_iter().next();
+                        }
                     }
                     _ => ()
                 }
@@ -477,17 +993,13 @@ impl CoreManager {
             for index in remove_indexs.into_iter().rev() {
                 inode.data.remove(index);
             }
-            let mut size = 0;
+            // 文件中可以有空洞，大小由上层给出，至少覆盖最后一个Entry
             for entry in inode.data.iter() {
-                size += entry.len;
-            }
-            inode.size = size;
-            let mut raw_inode = CoreManager::transfer_inode_to_raw_inode(&inode);
-            for entry in raw_inode.data.iter_mut() {
-                entry.address = self.vam.get_physic_address(entry.address).unwrap();
+                inode.size = inode.size.max(entry.offset + entry.len);
             }
+            let raw_inode = CoreManager::transfer_inode_to_raw_inode(&inode);
             self.kv.update_inode(raw_inode);
-            Some(inode)
+            Ok(Some(inode))
         }
     }
 }
@@ -521,10 +1033,25 @@ impl CoreManager {
             gid: raw_inode.gid,
             ref_cnt: raw_inode.ref_cnt,
             n_link: raw_inode.n_link,
+            generation: raw_inode.generation,
+            mode: raw_inode.mode,
+            atime: raw_inode.atime,
+            mtime: raw_inode.mtime,
+            ctime: raw_inode.ctime,
+            btime: raw_inode.btime,
             lock: Mutex::new(false),
+            dirty: false,
             core: None,
             file_type,
             data,
+            xattr: raw_inode.xattr.clone(),
+            xattr_entry: raw_inode.xattr_entry.map(|entry| inode::InodeEntry {
+                len: entry.len,
+                size: entry.size,
+                offset: entry.offset,
+                address: entry.address,
+                valid: true,
+            }),
         }
     }
     
@@ -555,8 +1082,63 @@ impl CoreManager {
             n_link: inode.n_link,
             ref_cnt: inode.ref_cnt,
             file_type,
+            generation: inode.generation,
+            mode: inode.mode,
+            atime: inode.atime,
+            mtime: inode.mtime,
+            ctime: inode.ctime,
+            btime: inode.btime,
             data,
+            xattr: inode.xattr.clone(),
+            xattr_entry: inode.xattr_entry.map(|entry| raw_inode::RawEntry {
+                len: entry.len,
+                size: entry.size,
+                offset: entry.offset,
+                address: entry.address,
+            }),
+        }
+    }
+
+    // 事件中需要写入的新数据，返回每段的(offset, len, pages)
+    // 文件数据中全零的Page不写入，不能内联的扩展属性整体写入一个Extent
+    pub fn split_event(event: &inode_event::InodeEvent) -> Vec<(u64, u64, Vec<[u8; 4096]>)> {
+        let event = match event {
+            inode_event::InodeEvent::AddContent(event) => event,
+            inode_event::InodeEvent::SetXattr(event) if event.content.len() > xattr::INLINE_SIZE => {
+                let mut pages = vec![];
+                for chunk in event.content.chunks(4096) {
+                    let mut page = [0; 4096];
+                    page[..chunk.len()].copy_from_slice(chunk);
+                    pages.push(page);
+                }
+                return vec![(xattr::EXTENT_OFFSET, event.content.len() as u64, pages)];
+            }
+            _ => return vec![],
+        };
+        let mut pages = vec![];
+        for i in 0..event.size as usize {
+            let mut page = [0; 4096];
+            let start = min(i * 4096, event.content.len());
+            let end = min(start + 4096, event.content.len());
+            page[..end - start].copy_from_slice(&event.content[start..end]);
+            pages.push(page);
+        }
+        let mut res = vec![];
+        let mut start = None;
+        for i in 0..=event.size {
+            let is_zero = i == event.size || pages[i as usize].iter().all(|byte| *byte == 0);
+            match (start, is_zero) {
+                (None, false) => start = Some(i),
+                (Some(begin), true) => {
+                    let offset = event.offset + begin as u64 * 4096;
+                    let len = min(event.len, i as u64 * 4096) - begin as u64 * 4096;
+                    res.push((offset, len, pages[begin as usize..i as usize].to_vec()));
+                    start = None;
+                }
+                _ => (),
+            }
         }
+        res
     }
 
     pub fn sort_gc_event(event_group: &mut gc_event::GCEventGroup) {
@@ -623,10 +1205,12 @@ impl CoreManager {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::util::cache;
+    use crate::kv::file_kv;
 
     fn init_test() -> CoreManager {
         let mut manager = CoreManager::new();
-        manager.mount();
+        manager.mount().unwrap();
         manager
     }
 
@@ -641,66 +1225,384 @@ mod test {
         manager.update_bit(100, true);
         manager.update_bit(200, true);
         assert_eq!(manager.bit.need_sync(), false);
-        let block1 = manager.read_block(1, false);
-        let block2 = manager.read_block(2, false);
-        for page in block2.iter() {
-            for byte in page.iter() {
-                assert_eq!(*byte, 0);
-            }
-        }
-        assert_eq!(block1.get(0)[12]>>4 & 1, 1);
-        assert_eq!(block1.get(0)[25] & 1, 1);
+        let page = manager.read_meta_page(0).unwrap();
+        assert_eq!(page[12]>>4 & 1, 1);
+        assert_eq!(page[25] & 1, 1);
+        // 重新挂载后从Metadata Region恢复
+        manager.sync();
+        let mut manager = CoreManager::new_with_cache(manager.buf_cache);
+        manager.mount().unwrap();
+        assert!(manager.bit.get_page(100));
+        assert_eq!(manager.gc.get_table(200), PageUsedStatus::Dirty);
     }
 
     #[test]
     fn pit() {
+        let read_u32 = |page: &Vec<u8>, index: usize| {
+            let byte1 = (page[index] as u32) << 24;
+            let byte2 = (page[index + 1] as u32) << 16;
+            let byte3 = (page[index + 2] as u32) << 8;
+            let byte4 = page[index + 3] as u32;
+            byte1 + byte2 + byte3 + byte4
+        };
+        let owner = |ino: u32, offset: u64, version: u32| pit::PageOwner { ino, offset, version, v_address: 0 };
         let mut manager = init_test();
-        manager.update_pit(100, 67);
-        manager.update_pit(200, 223);
+        let base = manager.bit.get_page_num();
+        // 挂载时只保留Inode引用的Page
+        let mut inode = manager.allocate_inode();
+        inode.data.push(inode::InodeEntry { valid: true, offset: 4096, len: 4096, size: 1, address: 0 });
+        manager.update_inode(inode);
+        manager.update_pit(100, owner(67, 4096, 1));
+        manager.update_pit(200, owner(223, 0, 2));
         assert_eq!(manager.pit.need_sync(), false);
-        let block1 = manager.read_block(3, false);
-        let block2 = manager.read_block(4, false);
-        for page in block2.iter() {
-            for byte in page.iter() {
-                assert_eq!(*byte, 0);
-            }
-        }
-        let byte1 = (block1.get(0)[400] as u32) << 24;
-        let byte2 = (block1.get(0)[401] as u32) << 16;
-        let byte3 = (block1.get(0)[402] as u32) << 8;
-        let byte4 = block1.get(0)[403] as u32;
-        let temp = byte1 + byte2 + byte3 + byte4;
-        assert_eq!(temp, 67);
-        let byte1 = (block1.get(0)[800] as u32) << 24;
-        let byte2 = (block1.get(0)[801] as u32) << 16;
-        let byte3 = (block1.get(0)[802] as u32) << 8;
-        let byte4 = block1.get(0)[803] as u32;
-        let temp = byte1 + byte2 + byte3 + byte4;
-        assert_eq!(temp, 223);
-        manager.update_pit(1024, 2349);
-        let block = manager.read_block(3, false);
-        let byte1 = (block.get(1)[0] as u32) << 24;
-        let byte2 = (block.get(1)[1] as u32) << 16;
-        let byte3 = (block.get(1)[2] as u32) << 8;
-        let byte4 = block.get(1)[3] as u32;
-        let temp = byte1 + byte2 + byte3 + byte4;
-        assert_eq!(temp, 2349);
+        let page = manager.read_meta_page(base).unwrap();
+        assert_eq!(read_u32(&page, 2000), 67);
+        assert_eq!(read_u32(&page, 2008), 4096);
+        assert_eq!(read_u32(&page, 2012), 1);
+        assert_eq!(read_u32(&page, 4000), 223);
+        // 其他表页单独写回，第一个表页的位置不变
+        let location = manager.meta.get_location(base);
+        manager.update_pit(1024, owner(2349, 0, 3));
+        assert_eq!(manager.meta.get_location(base), location);
+        let page = manager.read_meta_page(base + 5).unwrap();
+        assert_eq!(read_u32(&page, 80), 2349);
         manager.dirty_pit(1024);
-        let block = manager.read_block(3, false);
-        let byte1 = (block.get(1)[0] as u32) << 24;
-        let byte2 = (block.get(1)[1] as u32) << 16;
-        let byte3 = (block.get(1)[2] as u32) << 8;
-        let byte4 = block.get(1)[3] as u32;
-        let temp = byte1 + byte2 + byte3 + byte4;
-        assert_eq!(temp, 0);
+        let page = manager.read_meta_page(base + 5).unwrap();
+        assert_eq!(read_u32(&page, 80), 0);
         manager.clean_pit(200);
-        let block = manager.read_block(3, false);
-        let byte1 = (block.get(0)[800] as u32) << 24;
-        let byte2 = (block.get(0)[801] as u32) << 16;
-        let byte3 = (block.get(0)[802] as u32) << 8;
-        let byte4 = block.get(0)[803] as u32;
-        let temp = byte1 + byte2 + byte3 + byte4;
-        assert_eq!(temp, 0);
+        let page = manager.read_meta_page(base).unwrap();
+        assert_eq!(read_u32(&page, 4000), 0);
+        manager.update_pit(100, owner(1, 4096, 1));
+        manager.sync();
+        let mut manager = CoreManager::new_with_kv(manager.buf_cache, manager.kv);
+        manager.mount().unwrap();
+        assert_eq!(manager.pit.get_page(100), owner(1, 4096, 1));
+        assert_eq!(manager.pit.next_version(), 2);
+        assert_eq!(manager.gc.get_table(100), PageUsedStatus::Busy(1));
+    }
+
+    #[test]
+    fn reverse_map() {
+        let mut manager = init_test();
+        let mut inode = manager.allocate_inode();
+        let v_address = manager.vam.get_available_address(2);
+        inode.data.push(inode::InodeEntry { valid: true, offset: 100, len: 5000, size: 2, address: v_address });
+        manager.update_inode(inode);
+        for i in 0..2 {
+            let owner = pit::PageOwner { ino: 1, offset: 100 + i as u64 * 4096, version: manager.pit.next_version(), v_address: v_address + i };
+            manager.update_bit(i, true);
+            manager.update_pit(i, owner);
+            manager.insert_map(i, v_address + i);
+        }
+        assert_eq!(manager.locate_page(manager.pit.get_page(1)), Some(v_address + 1));
+
+        let gc_group = manager.gc.generate_gc_event().unwrap();
+        manager.dispose_gc_group(gc_group);
+        manager.forward_gc();
+        assert_eq!(manager.get_physic_address(v_address + 1), Some(129));
+        let owner = manager.pit.get_page(129);
+        assert_eq!((owner.ino, owner.offset, owner.version), (1, 4196, 4));
+
+        // 模拟中断：旧副本与新副本同时存在，挂载时保留版本号大的Page
+        manager.update_pit(200, pit::PageOwner { ino: 1, offset: 4196, version: 2, v_address: v_address + 1 });
+        manager.sync();
+        let mut manager = CoreManager::new_with_kv(manager.buf_cache, manager.kv);
+        manager.mount().unwrap();
+        assert_eq!(manager.gc.get_table(200), PageUsedStatus::Dirty);
+        assert_eq!(manager.gc.get_table(129), PageUsedStatus::Busy(1));
+    }
+
+    #[test]
+    fn crash() {
+        let mut manager = init_test();
+        let ino = manager.allocate_inode().ino;
+        let overwrite = |manager: &mut CoreManager, byte: u8| {
+            let mut event_group = inode_event::InodeEventGroup::new();
+            event_group.inode = manager.get_inode(ino);
+            if let Some(entry) = event_group.inode.data.first() {
+                event_group.events.push(inode_event::InodeEvent::DeleteContent(inode_event::DeleteContentInodeEvent {
+                    index: 0, size: entry.size, v_address: entry.address,
+                }));
+            }
+            event_group.events.push(inode_event::InodeEvent::AddContent(inode_event::AddContentInodeEvent {
+                index: 0, offset: 0, len: 2 * 4096, size: 2, content: vec![byte; 2 * 4096],
+            }));
+            manager.dispose_event_group(event_group).unwrap();
+        };
+        overwrite(&mut manager, 1);
+        manager.sync();
+
+        // 新数据已写入但Inode还未更新时中断，挂载后仍然读到原有数据，新写入的Page失效
+        let entries = manager.write_extents(ino, vec![vec![(0, 2 * 4096, vec![[2; 4096]; 2])]], Temperature::Warm).unwrap();
+        let address = manager.get_physic_address(entries[0][0].address).unwrap();
+        manager.sync_vam();
+        let mut manager = CoreManager::new_with_kv(manager.buf_cache, manager.kv);
+        manager.mount().unwrap();
+        let inode = manager.get_inode(ino);
+        assert_eq!(manager.read_data(inode.data[0].address), [1; 4096]);
+        assert_eq!(manager.read_data(inode.data[0].address + 1), [1; 4096]);
+        assert_eq!(manager.gc.get_table(address), PageUsedStatus::Dirty);
+        assert_eq!(manager.get_space_stat().valid, 2);
+        // 未提交的虚拟地址重新可分配，残留的映射被清除
+        manager.sync();
+        assert_eq!(manager.get_physic_address(entries[0][0].address), None);
+
+        // Inode更新后、映射写回前中断，挂载后映射指向新数据
+        overwrite(&mut manager, 3);
+        let mut manager = CoreManager::new_with_kv(manager.buf_cache, manager.kv);
+        manager.mount().unwrap();
+        let inode = manager.get_inode(ino);
+        assert_eq!(manager.read_data(inode.data[0].address), [3; 4096]);
+        assert_eq!(manager.read_data(inode.data[0].address + 1), [3; 4096]);
+        assert_eq!(manager.get_space_stat().valid, 2);
+    }
+
+    #[test]
+    fn background_gc() {
+        let mut manager = CoreManager::new();
+        let mut options = options::MountOptions::new();
+        options.gc_low_watermark = manager.gc.get_block_num() - 1;
+        options.gc_high_watermark = manager.gc.get_block_num() - 1;
+        manager.mount_with_options(options).unwrap();
+        // Block 0与Block 1写满，各自只有28个有效Page
+        manager.bit_begin_op();
+        manager.pit_begin_op();
+        for address in 0..256 {
+            manager.update_bit(address, true);
+            manager.update_pit(address, pit::PageOwner { ino: 1, offset: address as u64 * 4096, version: 1, v_address: address });
+            if address % 128 >= 28 {
+                manager.dirty_pit(address);
+            }
+        }
+        manager.bit_end_op();
+        manager.pit_end_op();
+        manager.sync_bit();
+        manager.sync_pit();
+        assert_eq!(manager.gc.get_free_block_num(), manager.gc.get_block_num() - 2);
+
+        // 每次只回收一个Block，未到高水位时让出
+        manager.background_gc();
+        let stat = manager.get_background_gc_stat();
+        assert_eq!((stat.wake_num, stat.round_num, stat.move_num, stat.yield_num), (1, 1, 28, 1));
+        assert_eq!(manager.gc.get_table(256), PageUsedStatus::Busy(1));
+        manager.background_gc();
+        let stat = manager.get_background_gc_stat();
+        assert_eq!((stat.round_num, stat.move_num, stat.yield_num), (2, 56, 1));
+        // 两个Block的有效Page合并到Block 2
+        assert_eq!(manager.gc.get_table(256 + 55), PageUsedStatus::Busy(1));
+        assert_eq!(manager.bg_gc.get_state(), background_gc::BackgroundGCState::Idle);
+        assert_eq!(manager.gc.get_block_info(0).erase_count, 1);
+        assert_eq!(manager.gc.get_page_stat().0, 56);
+        manager.background_gc();
+        assert_eq!(manager.get_background_gc_stat().round_num, 2);
+
+        manager.unmount();
+        assert_eq!(manager.bg_gc.get_state(), background_gc::BackgroundGCState::Stopped);
+    }
+
+    #[test]
+    fn incremental_gc() {
+        let mut manager = CoreManager::new();
+        let mut options = options::MountOptions::new();
+        options.gc_low_watermark = manager.gc.get_block_num();
+        options.gc_high_watermark = manager.gc.get_block_num();
+        options.gc_step_size = 16;
+        manager.mount_with_options(options).unwrap();
+        manager.bit_begin_op();
+        manager.pit_begin_op();
+        for address in 0..128 {
+            manager.update_bit(address, true);
+            manager.update_pit(address, pit::PageOwner { ino: 1, offset: address as u64 * 4096, version: 1, v_address: address });
+            if address >= 28 {
+                manager.dirty_pit(address);
+            }
+        }
+        manager.bit_end_op();
+        manager.pit_end_op();
+
+        // 每次只搬移16个Page，Block 0保持部分搬移的状态
+        manager.background_gc();
+        let stat = manager.get_background_gc_stat();
+        assert_eq!((stat.round_num, stat.step_num, stat.move_num), (0, 1, 16));
+        assert_eq!(manager.gc.get_table(15), PageUsedStatus::Dirty);
+        assert_eq!(manager.gc.get_table(16), PageUsedStatus::Busy(1));
+        assert_eq!(manager.gc.get_gc_victim(), 0);
+        manager.background_gc();
+        let stat = manager.get_background_gc_stat();
+        assert_eq!((stat.round_num, stat.step_num, stat.move_num), (1, 2, 28));
+        assert_eq!(manager.gc.get_block_info(0).erase_count, 1);
+        assert_eq!(manager.gc.get_page_stat().0, 28);
+    }
+
+    #[test]
+    fn temperature() {
+        let mut manager = init_test();
+        let write = |manager: &mut CoreManager, ino: u32, file_type: inode::InodeFileType| {
+            let mut event_group = inode_event::InodeEventGroup::new();
+            event_group.inode = manager.get_inode(ino);
+            event_group.inode.file_type = file_type;
+            let index = event_group.inode.data.len() as u32;
+            let offset = index as u64 * 4096;
+            event_group.events.push(inode_event::InodeEvent::AddContent(inode_event::AddContentInodeEvent {
+                index, offset, len: 4096, size: 1, content: vec![1; 4096],
+            }));
+            let inode = manager.dispose_event_group(event_group).unwrap().unwrap();
+            let v_address = inode.data.last().unwrap().address;
+            manager.get_physic_address(v_address).unwrap() / 128
+        };
+        let dir = manager.allocate_inode().ino;
+        let file = manager.allocate_inode().ino;
+        let cold = manager.allocate_inode().ino;
+        manager.set_temperature_hint(cold, Temperature::Cold);
+        // 目录、普通文件与冷文件写入不同的Block
+        assert_eq!(write(&mut manager, dir, inode::InodeFileType::Directory), 0);
+        assert_eq!(write(&mut manager, file, inode::InodeFileType::File), 1);
+        assert_eq!(write(&mut manager, cold, inode::InodeFileType::File), 2);
+        assert_eq!(write(&mut manager, dir, inode::InodeFileType::Directory), 0);
+        // 频繁更新的文件变为热数据
+        for _ in 1..temperature::HOT_THRESHOLD {
+            assert_eq!(write(&mut manager, file, inode::InodeFileType::File), 1);
+        }
+        assert_eq!(write(&mut manager, file, inode::InodeFileType::File), 0);
+    }
+
+    #[test]
+    fn wear_leveling() {
+        let mut manager = CoreManager::new();
+        let mut options = options::MountOptions::new();
+        options.gc_step_size = 1;
+        manager.mount_with_options(options).unwrap();
+        manager.bit_begin_op();
+        manager.pit_begin_op();
+        for address in 0..2 {
+            manager.update_bit(address, true);
+            manager.update_pit(address, pit::PageOwner { ino: 1, offset: address as u64 * 4096, version: 1, v_address: address });
+        }
+        manager.bit_end_op();
+        manager.pit_end_op();
+        manager.gc.set_erase_count(1, 3);
+        manager.gc.set_erase_count(5, 20);
+        // 后台GC没有被唤醒时不进行磨损均衡
+        manager.background_gc();
+        assert_eq!(manager.get_background_gc_stat().wear_num, 0);
+        assert_eq!(manager.gc.get_table(0), PageUsedStatus::Busy(1));
+
+        // 唤醒后Block 0中的冷数据按每次的配额逐步搬到擦除次数少的空闲Block
+        manager.bg_gc = background_gc::BackgroundGC::new(manager.gc.get_block_num(), manager.gc.get_block_num(), 1);
+        manager.bg_gc.start();
+        manager.background_gc();
+        assert_eq!(manager.get_background_gc_stat().wear_num, 1);
+        assert_eq!((manager.gc.get_table(0), manager.gc.get_table(1)), (PageUsedStatus::Dirty, PageUsedStatus::Busy(1)));
+        assert_eq!(manager.gc.get_table(256), PageUsedStatus::Busy(1));
+        manager.background_gc();
+        assert_eq!(manager.gc.get_table(257), PageUsedStatus::Busy(1));
+        assert_eq!(manager.gc.get_block_info(0).erase_count, 1);
+        assert_eq!(manager.wear.get(manager.main_offset), 1);
+        assert_eq!(manager.wear_leveling(), false);
+        // 新分配优先使用擦除次数少的空闲Block
+        assert_eq!(manager.find_next_pos_to_write(1, Temperature::Hot), Ok(3 * 128));
+
+        // Metadata Region反复回收后与Main Region交换物理Block
+        let mut manager = CoreManager::new();
+        let mut options = options::MountOptions::new();
+        options.wear_threshold = 1;
+        manager.mount_with_options(options).unwrap();
+        let v_address = manager.vam.get_available_address(1);
+        manager.insert_map(200, v_address);
+        let mut inode = manager.allocate_inode();
+        inode.data.push(inode::InodeEntry { valid: true, offset: 0, len: 4096, size: 1, address: v_address });
+        manager.update_inode(inode);
+        for i in 0..1000 {
+            manager.update_map(200 + (i + 1) % 2, v_address);
+            manager.sync_vam();
+        }
+        assert!(manager.block_map.get_entry_num() > 0);
+        assert!((block_map::BLOCK_NUM..manager.main_offset).any(|block_no| manager.block_map.get(block_no) >= manager.main_offset));
+        manager.unmount();
+        let count = manager.wear.get(block_map::BLOCK_NUM);
+
+        let mut manager = CoreManager::new_with_kv(manager.buf_cache, manager.kv);
+        manager.mount().unwrap();
+        assert_eq!(manager.get_physic_address(v_address), Some(200));
+        assert_eq!(manager.wear.get(block_map::BLOCK_NUM), count);
+        assert!(manager.block_map.get_entry_num() > 0);
+    }
+
+    #[test]
+    fn no_space() {
+        let mut manager = CoreManager::new_with_size(16 * 128);
+        manager.mount().unwrap();
+        let block_num = manager.gc.get_block_num();
+        let stat = manager.get_space_stat();
+        assert_eq!((stat.total, stat.reserved, stat.available), (block_num * 128, 2 * 128, (block_num - 2) * 128));
+        let ino = manager.allocate_inode().ino;
+        let write = |manager: &mut CoreManager| {
+            let mut event_group = inode_event::InodeEventGroup::new();
+            event_group.inode = manager.get_inode(ino);
+            let index = event_group.inode.data.len() as u32;
+            event_group.events.push(inode_event::InodeEvent::AddContent(inode_event::AddContentInodeEvent {
+                index, offset: index as u64 * 64 * 4096, len: 64 * 4096, size: 64, content: vec![1; 64 * 4096],
+            }));
+            manager.dispose_event_group(event_group).map(|inode| inode.unwrap().data.len())
+        };
+        // 写满可用空间后返回NoSpace，保留空间不被占用
+        let mut num = 0;
+        while write(&mut manager).is_ok() {
+            num += 1;
+        }
+        assert_eq!(num, (block_num - 2) * 2);
+        assert_eq!(write(&mut manager), Err(FsError::NoSpace));
+        let stat = manager.get_space_stat();
+        assert_eq!((stat.valid, stat.available), ((block_num - 2) * 128, 0));
+        assert_eq!(manager.get_inode(ino).data.len() as u32, num);
+
+        // 空间不足时覆盖写失败，原有数据保持不变
+        let mut event_group = inode_event::InodeEventGroup::new();
+        event_group.inode = manager.get_inode(ino);
+        let entry = event_group.inode.data[0];
+        event_group.events.push(inode_event::InodeEvent::DeleteContent(inode_event::DeleteContentInodeEvent {
+            index: 0, size: entry.size, v_address: entry.address,
+        }));
+        event_group.events.push(inode_event::InodeEvent::AddContent(inode_event::AddContentInodeEvent {
+            index: 0, offset: 0, len: 64 * 4096, size: 64, content: vec![2; 64 * 4096],
+        }));
+        assert_eq!(manager.dispose_event_group(event_group).err(), Some(FsError::NoSpace));
+        let inode = manager.get_inode(ino);
+        assert_eq!(inode.data[0].address, entry.address);
+        assert_eq!(manager.read_data(entry.address), [1; 4096]);
+        assert_eq!(manager.get_space_stat().valid, (block_num - 2) * 128);
+
+        // 删除文件后可以继续写入
+        let mut event_group = inode_event::InodeEventGroup::new();
+        event_group.inode = manager.get_inode(ino);
+        event_group.need_delete = true;
+        assert!(manager.dispose_event_group(event_group).unwrap().is_none());
+        let ino = manager.allocate_inode().ino;
+        let mut event_group = inode_event::InodeEventGroup::new();
+        event_group.inode = manager.get_inode(ino);
+        event_group.events.push(inode_event::InodeEvent::AddContent(inode_event::AddContentInodeEvent {
+            index: 0, offset: 0, len: 64 * 4096, size: 64, content: vec![1; 64 * 4096],
+        }));
+        assert!(manager.dispose_event_group(event_group).is_ok());
+        assert_eq!(manager.get_space_stat().valid, 64);
+    }
+
+    #[test]
+    fn large_extent() {
+        let mut manager = init_test();
+        let mut event_group = inode_event::InodeEventGroup::new();
+        event_group.inode = manager.allocate_inode();
+        let content = (0..300 * 4096).map(|i| (i / 4096 % 251 + 1) as u8).collect();
+        event_group.events.push(inode_event::InodeEvent::AddContent(inode_event::AddContentInodeEvent {
+            index: 0, offset: 0, len: 300 * 4096, size: 300, content,
+        }));
+        // 超过一个Block的Extent分段写入不同的Block，虚拟地址仍然连续
+        let inode = manager.dispose_event_group(event_group).unwrap().unwrap();
+        assert_eq!((inode.data.len(), inode.data[0].size), (1, 300));
+        for i in [0, 127, 128, 299] {
+            assert_eq!(manager.read_data(inode.data[0].address + i), [(i % 251 + 1) as u8; 4096]);
+        }
+        assert_eq!(manager.get_physic_address(inode.data[0].address + 299).unwrap() / 128, 2);
     }
 
     #[test]
@@ -714,6 +1616,9 @@ mod test {
         let inode = manager.get_inode(3);
         assert_eq!(inode.n_link, 3);
         manager.delete_inode(3);
+        // 复用被删除的ino，generation加一
+        let inode = manager.allocate_inode();
+        assert_eq!((inode.ino, inode.get_stat().generation), (3, 1));
         let mut raw_inode = manager.get_raw_inode(2);
         raw_inode.n_link = 100;
         manager.update_raw_inode(raw_inode);
@@ -721,23 +1626,148 @@ mod test {
         assert_eq!(inode.n_link, 100);
     }
 
+    #[test]
+    fn inode_batch() {
+        let mut manager = init_test();
+        let mut dir = manager.allocate_inode();
+        let file = manager.allocate_inode();
+        let other = manager.allocate_inode();
+        dir.size = 64;
+        manager.write_inode_batch(vec![dir], vec![file.ino]);
+        let inodes: Vec<inode::Inode> = manager.iter_inode().collect();
+        assert_eq!(inodes.iter().map(|inode| inode.ino).collect::<Vec<u32>>(), vec![1, other.ino]);
+        assert_eq!(inodes[0].size, 64);
+    }
+
+    #[test]
+    fn file_kv() {
+        let path = std::env::temp_dir().join(format!("sffs_core_kv_{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let mut manager = CoreManager::new_with_kv(buf::BufCache::new(), file_kv::FileKV::open(path));
+        manager.mount().unwrap();
+        let mut event_group = inode_event::InodeEventGroup::new();
+        event_group.inode = manager.allocate_inode();
+        let ino = event_group.inode.ino;
+        event_group.events.push(inode_event::InodeEvent::AddContent(inode_event::AddContentInodeEvent {
+            index: 0, offset: 0, len: 4096, size: 1, content: vec![7; 4096],
+        }));
+        manager.dispose_event_group(event_group).unwrap();
+        manager.unmount();
+
+        // Inode保存在文件中，重新挂载后仍然可以读到数据
+        let mut manager = CoreManager::new_with_kv(manager.buf_cache, file_kv::FileKV::open(path));
+        manager.mount().unwrap();
+        let inode = manager.get_inode(ino);
+        assert_eq!(inode.size, 4096);
+        assert_eq!(manager.read_data(inode.data[0].address), [7; 4096]);
+        std::fs::remove_file(path).unwrap();
+    }
+
     #[test]
     fn gc() {
         let mut manager = init_test();
-        assert_eq!(manager.find_next_pos_to_write(10), 0);
+        assert_eq!(manager.find_next_pos_to_write(10, Temperature::Warm), Ok(0));
         manager.update_bit(0, true);
-        manager.update_pit(0, 1);
+        manager.update_pit(0, pit::PageOwner { ino: 1, offset: 0, version: 1, v_address: 0 });
         manager.update_bit(1, true);
-        manager.update_pit(1, 1);
-        assert_eq!(manager.find_next_pos_to_write(10), 2);
-        assert_eq!(manager.gc.find_next_pos_to_write_except(10, 0).unwrap(), 128);
-        let gc_group = manager.gc.generate_gc_event();
+        manager.update_pit(1, pit::PageOwner { ino: 1, offset: 4096, version: 2, v_address: 1 });
+        assert_eq!(manager.find_next_pos_to_write(10, Temperature::Warm), Ok(2));
+        assert_eq!(manager.gc.find_next_pos_to_write_except(10, Temperature::Cold, 0).unwrap(), 128);
+        let gc_group = manager.gc.generate_gc_event().unwrap();
         assert_eq!(gc_group.events[0], gc_event::GCEvent::Move(gc_event::MoveGCEvent{ index: 0, ino: 1, size: 2, o_address: 0, d_address: 128 }));
         assert_eq!(gc_group.events[1], gc_event::GCEvent::Erase(gc_event::EraseGCEvent{ index: 1, block_no: 0 }));
         manager.allocate_inode();
         manager.forward_gc();
     }
 
+    #[test]
+    fn mount() {
+        let mut manager = CoreManager::new();
+        let mut options = options::MountOptions::new();
+        options.read_ahead = buf::ReadAhead::None;
+        options.buf_cache_policy = cache::CachePolicy::TwoQ;
+        manager.mount_with_options(options).unwrap();
+        assert_eq!(manager.buf_cache.read_ahead, buf::ReadAhead::None);
+        assert_eq!(manager.buf_cache.cache_policy, cache::CachePolicy::TwoQ);
+        // 先读Block 0与Block 1中的BlockMap，Metadata Region共三个Block，逐页读取
+        assert_eq!(manager.main_offset, 5);
+        assert_eq!(manager.buf_cache.disk_manager.get_clock().unwrap().read_num, 3 * 128 + 2);
+
+        // 表页格式版本不符时挂载失败
+        let mut manager = CoreManager::new();
+        let mut data = meta_log::encode_page(0, 0, &[0; meta_log::PAYLOAD_SIZE]);
+        data[8..12].copy_from_slice(&(meta_log::FORMAT_VERSION - 1).to_be_bytes());
+        manager.buf_cache.disk_manager.disk_write(block_map::BLOCK_NUM * 128, data);
+        assert_eq!(manager.mount(), Err(FsError::InvalidArgument));
+    }
+
+    #[test]
+    fn sync() {
+        let mut manager = init_test();
+        manager.write_page(3, [9; 4096], true);
+        assert_eq!(manager.buf_cache.get_dirty_size(), 1);
+        manager.sync();
+        assert_eq!(manager.buf_cache.get_dirty_size(), 0);
+        assert_eq!(manager.buf_cache.disk_manager.disk_read_page(3 + manager.main_offset * 128), [9; 4096]);
+    }
+
+    #[test]
+    fn sparse() {
+        // 8GB Disk
+        let mut manager = CoreManager::new_with_size(2 * 1024 * 1024);
+        manager.mount().unwrap();
+        assert_eq!(manager.gc.get_block_num(), 16 * 1024 - block_map::BLOCK_NUM - manager.meta.get_size());
+        let address = manager.gc.get_block_num() * 128 - 1;
+        manager.write_page(address, [7; 4096], true);
+        assert_eq!(manager.read_page(address, true), [7; 4096]);
+        assert_eq!(manager.gc.get_table(address), PageUsedStatus::Clean);
+        assert_eq!(manager.gc.find_next_pos_to_write(128, Temperature::Warm), Some(0));
+    }
+
+    #[test]
+    fn vam() {
+        let mut manager = init_test();
+        let v_address = manager.vam.get_available_address(10);
+        for i in 0..10 {
+            manager.insert_map(i, v_address + i);
+        }
+        let mut inode = manager.allocate_inode();
+        inode.data.push(inode::InodeEntry { valid: true, offset: 0, len: 9 * 4096, size: 9, address: 1 });
+        manager.update_inode(inode);
+        manager.update_map(100, 5);
+        // 反复写回使VAM Region写满一轮，触发Block回收
+        let v_address = manager.vam.get_available_address(1);
+        manager.insert_map(200, v_address);
+        for i in 0..500 {
+            manager.update_map(200 + (i + 1) % 2, v_address);
+            manager.sync_vam();
+            // 写入位置之后的Block中的表页已经搬走，擦除时不会丢失
+            let next = (manager.meta.get_head() / 128 + 1) % manager.meta.get_size();
+            assert!(manager.meta.get_head() % 128 == 0 || manager.meta.get_block_valid(next) == 0);
+        }
+        manager.delete_map(0, 0);
+        manager.unmount();
+        assert_eq!(manager.vam.get_dirty_size(), 0);
+
+        let mut manager = CoreManager::new_with_kv(manager.buf_cache, manager.kv);
+        manager.mount().unwrap();
+        // 挂载时不读入Translation Page，访问时才读入
+        assert_eq!(manager.vam.is_cached(0), false);
+        assert_eq!(manager.get_physic_address(0), None);
+        assert_eq!(manager.get_physic_address(1), Some(1));
+        assert_eq!(manager.get_physic_address(5), Some(100));
+        // 不被Inode引用的虚拟地址上残留的映射在读入时清除
+        assert_eq!(manager.get_physic_address(10), None);
+        // 反向映射由PIT中的所有者与Inode推导
+        manager.update_pit(9, pit::PageOwner { ino: 1, offset: 8 * 4096, version: 1, v_address: 9 });
+        assert_eq!(manager.get_virtual_address(9), Some(9));
+        assert_eq!(manager.get_virtual_address(100), None);
+        // 只有Inode引用的虚拟地址在重新挂载后保持已分配
+        assert_eq!(manager.vam.get_available_address(1), 0);
+        assert_eq!(manager.vam.get_available_address(2), 10);
+    }
+
     #[test]
     fn underlay() {
         let mut manager = init_test();
@@ -796,10 +1826,19 @@ mod test {
             n_link: 2,
             ref_cnt: 3,
             file_type: 1,
+            generation: 0,
+            atime: 0,
+            mtime: 0,
+            ctime: 0,
+            btime: 0,
+            mode: 0o100644,
             data: vec![],
+            xattr: vec![],
+            xattr_entry: Some(raw_inode::RawEntry { len: 5000, size: 2, offset: xattr::EXTENT_OFFSET, address: 7 }),
         };
         let inode = CoreManager::transfer_raw_inode_to_inode(&raw_inode);
         assert_eq!(inode.file_type, inode::InodeFileType::Directory);
+        assert_eq!(inode.xattr_entry.map(|entry| (entry.len, entry.address)), Some((5000, 7)));
         
         let mut inode = inode::Inode::new();
         inode.ino = 12;
@@ -0,0 +1,33 @@
+// 文件系统各层共用的错误，通过文件接口返回时转换为负的errno
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum FsError {
+    NoSpace,
+    InvalidArgument,
+    NoSuchAddress,
+    PermissionDenied,
+    NotPermitted,
+    NoData,
+    Exists,
+    OutOfRange,
+    TooBig,
+    NotSupported,
+    FileTooBig,
+}
+
+impl FsError {
+    pub fn errno(&self) -> i32 {
+        match self {
+            FsError::NoSpace => 28,          // ENOSPC
+            FsError::InvalidArgument => 22,  // EINVAL
+            FsError::NoSuchAddress => 6,     // ENXIO
+            FsError::PermissionDenied => 13, // EACCES
+            FsError::NotPermitted => 1,      // EPERM
+            FsError::NoData => 61,           // ENODATA
+            FsError::Exists => 17,           // EEXIST
+            FsError::OutOfRange => 34,       // ERANGE
+            FsError::TooBig => 7,            // E2BIG
+            FsError::NotSupported => 95,     // EOPNOTSUPP
+            FsError::FileTooBig => 27,       // EFBIG
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+// 以空闲区间管理地址空间，by_start按起始地址索引用于合并，by_len按长度索引用于Best Fit
+pub struct ExtentAllocator {
+    size: u32,
+    free_size: u32,
+    by_start: BTreeMap<u32, u32>,
+    by_len: BTreeSet<(u32, u32)>,
+}
+
+impl ExtentAllocator {
+    pub fn new(size: u32) -> ExtentAllocator {
+        let mut allocator = ExtentAllocator {
+            size,
+            free_size: 0,
+            by_start: BTreeMap::new(),
+            by_len: BTreeSet::new(),
+        };
+        if size > 0 {
+            allocator.insert_extent(0, size);
+        }
+        allocator
+    }
+
+    pub fn get_size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn get_free_size(&self) -> u32 {
+        self.free_size
+    }
+
+    pub fn get_extent_num(&self) -> usize {
+        self.by_start.len()
+    }
+
+    // 分配连续的size个地址，选择能容纳的最短空闲区间
+    pub fn allocate(&mut self, size: u32) -> Option<u32> {
+        if size == 0 {
+            panic!("ExtentAllocator: allocate zero size");
+        }
+        let (len, start) = *self.by_len.range((size, 0)..).next()?;
+        self.remove_extent(start, len);
+        if len > size {
+            self.insert_extent(start + size, len - size);
+        }
+        Some(start)
+    }
+
+    // 将指定区间标记为已使用，挂载恢复时使用
+    pub fn reserve(&mut self, start: u32, size: u32) {
+        let (e_start, e_len) = match self.find_extent(start) {
+            Some(extent) if start + size <= extent.0 + extent.1 => extent,
+            _ => panic!("ExtentAllocator: reserve not free range"),
+        };
+        self.remove_extent(e_start, e_len);
+        if start > e_start {
+            self.insert_extent(e_start, start - e_start);
+        }
+        if e_start + e_len > start + size {
+            self.insert_extent(start + size, e_start + e_len - start - size);
+        }
+    }
+
+    // 释放区间并与相邻的空闲区间合并
+    pub fn free(&mut self, start: u32, size: u32) {
+        if size == 0 || start + size > self.size {
+            panic!("ExtentAllocator: free out of range");
+        }
+        let mut start = start;
+        let mut size = size;
+        if let Some((&p_start, &p_len)) = self.by_start.range(..=start).next_back() {
+            if p_start + p_len > start {
+                panic!("ExtentAllocator: free has free");
+            }
+            if p_start + p_len == start {
+                self.remove_extent(p_start, p_len);
+                start = p_start;
+                size += p_len;
+            }
+        }
+        if let Some((&n_start, &n_len)) = self.by_start.range(start + 1..).next() {
+            if n_start < start + size {
+                panic!("ExtentAllocator: free has free");
+            }
+            if n_start == start + size {
+                self.remove_extent(n_start, n_len);
+                size += n_len;
+            }
+        }
+        self.insert_extent(start, size);
+    }
+
+    pub fn is_free(&self, address: u32) -> bool {
+        self.find_extent(address).is_some()
+    }
+
+    fn find_extent(&self, address: u32) -> Option<(u32, u32)> {
+        let (&start, &len) = self.by_start.range(..=address).next_back()?;
+        if start + len > address {
+            Some((start, len))
+        } else {
+            None
+        }
+    }
+
+    fn insert_extent(&mut self, start: u32, len: u32) {
+        self.by_start.insert(start, len);
+        self.by_len.insert((len, start));
+        self.free_size += len;
+    }
+
+    fn remove_extent(&mut self, start: u32, len: u32) {
+        self.by_start.remove(&start);
+        self.by_len.remove(&(len, start));
+        self.free_size -= len;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        let mut allocator = ExtentAllocator::new(100);
+        assert_eq!(allocator.allocate(10), Some(0));
+        assert_eq!(allocator.allocate(20), Some(10));
+        assert_eq!(allocator.allocate(30), Some(30));
+        allocator.free(10, 20);
+        assert_eq!(allocator.get_extent_num(), 2);
+        // Best Fit优先使用释放出的较短区间
+        assert_eq!(allocator.allocate(5), Some(10));
+        assert_eq!(allocator.allocate(40), Some(60));
+        assert_eq!(allocator.allocate(16), None);
+        allocator.free(0, 10);
+        allocator.free(30, 30);
+        assert_eq!(allocator.get_extent_num(), 2);
+        assert_eq!(allocator.get_free_size(), 55);
+        assert!(!allocator.is_free(10));
+        allocator.free(10, 5);
+        allocator.free(60, 40);
+        assert_eq!(allocator.get_extent_num(), 1);
+        assert_eq!(allocator.get_free_size(), 100);
+
+        allocator.reserve(50, 1);
+        allocator.reserve(0, 1);
+        assert_eq!(allocator.get_extent_num(), 2);
+        assert!(!allocator.is_free(50));
+        assert_eq!(allocator.allocate(49), Some(1));
+    }
+}
@@ -0,0 +1,176 @@
+use crate::core::error::FsError;
+
+// Metadata Region以日志方式保存BIT、PIT与VAM的表页，每个表页可以单独读入和写回
+// 表页头部12字节为表页号+1、序列号与格式版本，其余为表页内容
+pub const HEADER_SIZE: usize = 12;
+pub const PAYLOAD_SIZE: usize = 4096 - HEADER_SIZE;
+// 表页格式的版本，PIT中的文件偏移改为64位后为2，BlockMap占用两个Block后为3
+// PIT记录虚拟地址后为4，挂载时遇到其他版本返回错误
+pub const FORMAT_VERSION: u32 = 4;
+
+pub fn encode_page(page_no: u32, sequence: u32, payload: &[u8]) -> [u8; 4096] {
+    if payload.len() != PAYLOAD_SIZE {
+        panic!("MetaLog: encode not matched size");
+    }
+    let mut data = [0; 4096];
+    data[0..4].copy_from_slice(&(page_no + 1).to_be_bytes());
+    data[4..8].copy_from_slice(&sequence.to_be_bytes());
+    data[8..12].copy_from_slice(&FORMAT_VERSION.to_be_bytes());
+    data[HEADER_SIZE..].copy_from_slice(payload);
+    data
+}
+
+// 返回表页号与序列号，未写入的Page返回None，版本不符时返回InvalidArgument
+pub fn decode_page(data: &[u8; 4096]) -> Result<Option<(u32, u32)>, FsError> {
+    let page_no = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+    if page_no == 0 {
+        return Ok(None);
+    }
+    let sequence = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+    let version = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+    if version != FORMAT_VERSION {
+        return Err(FsError::InvalidArgument);
+    }
+    Ok(Some((page_no - 1, sequence)))
+}
+
+// 只记录日志状态，实际读写由CoreManager完成
+// directory记录每个表页最新副本在Region中的偏移
+// 写入位置进入一个Block时，先把下一个Block中有效的表页搬到当前Block，之后擦除下一个Block时其中没有有效的表页
+pub struct MetaLog {
+    size: u32,
+    head: u32,
+    sequence: u32,
+    directory: Vec<Option<u32>>,
+    block_valid: Vec<u32>,
+}
+
+impl MetaLog {
+    // page_num为表页数量，预留两个Block保证回收时总能腾出空间
+    pub fn new(page_num: u32) -> MetaLog {
+        let size = (page_num + 127) / 128 + 2;
+        MetaLog {
+            size,
+            head: 0,
+            sequence: 0,
+            directory: vec![None; page_num as usize],
+            block_valid: vec![0; size as usize],
+        }
+    }
+
+    // Region占用的Block数量
+    pub fn get_size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn get_page_num(&self) -> u32 {
+        self.directory.len() as u32
+    }
+
+    pub fn get_location(&self, page_no: u32) -> Option<u32> {
+        if page_no >= self.get_page_num() {
+            panic!("MetaLog: page out of range");
+        }
+        self.directory[page_no as usize]
+    }
+
+    pub fn get_head(&self) -> u32 {
+        self.head
+    }
+
+    // 记录一次追加写入，返回写入位置与序列号
+    pub fn append(&mut self, page_no: u32) -> (u32, u32) {
+        if page_no >= self.get_page_num() {
+            panic!("MetaLog: page out of range");
+        }
+        let offset = self.head;
+        let sequence = self.sequence;
+        if let Some(o_offset) = self.directory[page_no as usize] {
+            self.block_valid[(o_offset / 128) as usize] -= 1;
+        }
+        self.directory[page_no as usize] = Some(offset);
+        self.block_valid[(offset / 128) as usize] += 1;
+        self.head = (self.head + 1) % (self.size * 128);
+        self.sequence += 1;
+        (offset, sequence)
+    }
+
+    // 返回Block中仍然有效的(表页号, 偏移)
+    pub fn get_block_pages(&self, block_no: u32) -> Vec<(u32, u32)> {
+        let mut res = vec![];
+        if self.block_valid[block_no as usize] != 0 {
+            for (page_no, location) in self.directory.iter().enumerate() {
+                if let Some(offset) = *location {
+                    if offset / 128 == block_no {
+                        res.push((page_no as u32, offset));
+                    }
+                }
+            }
+        }
+        res
+    }
+
+    pub fn get_block_valid(&self, block_no: u32) -> u32 {
+        self.block_valid[block_no as usize]
+    }
+
+    // 挂载时根据扫描到的(表页号, 序列号, 位置)恢复，同一表页保留序列号最大的副本
+    pub fn recover(&mut self, pages: Vec<(u32, u32, u32)>) {
+        let mut sequences: Vec<Option<u32>> = vec![None; self.directory.len()];
+        let mut max_sequence = None;
+        for (page_no, sequence, offset) in pages {
+            if page_no >= self.get_page_num() {
+                panic!("MetaLog: recover page out of range");
+            }
+            if sequences[page_no as usize].map_or(true, |o_sequence| sequence > o_sequence) {
+                if let Some(o_offset) = self.directory[page_no as usize] {
+                    self.block_valid[(o_offset / 128) as usize] -= 1;
+                }
+                sequences[page_no as usize] = Some(sequence);
+                self.directory[page_no as usize] = Some(offset);
+                self.block_valid[(offset / 128) as usize] += 1;
+            }
+            if max_sequence.map_or(true, |(max, _)| sequence > max) {
+                max_sequence = Some((sequence, offset));
+            }
+        }
+        if let Some((sequence, offset)) = max_sequence {
+            self.sequence = sequence + 1;
+            self.head = (offset + 1) % (self.size * 128);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        let payload = vec![5; PAYLOAD_SIZE];
+        let data = encode_page(7, 99, &payload);
+        assert_eq!(decode_page(&data), Ok(Some((7, 99))));
+        assert_eq!(&data[HEADER_SIZE..], &payload[..]);
+        assert_eq!(decode_page(&[0; 4096]), Ok(None));
+        let mut data = data;
+        data[11] = 1;
+        assert_eq!(decode_page(&data), Err(FsError::InvalidArgument));
+
+        let mut log = MetaLog::new(9);
+        assert_eq!(log.get_size(), 3);
+        assert_eq!(log.append(0), (0, 0));
+        assert_eq!(log.append(1), (1, 1));
+        assert_eq!(log.append(0), (2, 2));
+        assert_eq!(log.get_location(0), Some(2));
+        assert_eq!(log.get_block_pages(0), vec![(0, 2), (1, 1)]);
+        assert_eq!(log.get_block_valid(0), 2);
+        assert_eq!(log.get_block_pages(1), vec![]);
+
+        let mut log = MetaLog::new(9);
+        log.recover(vec![(2, 7, 130), (0, 3, 5), (2, 4, 6)]);
+        assert_eq!(log.get_location(2), Some(130));
+        assert_eq!(log.get_location(0), Some(5));
+        assert_eq!(log.get_head(), 131);
+        assert_eq!(log.append(1), (131, 8));
+    }
+}
@@ -1,4 +1,10 @@
 pub mod bit;
 pub mod pit;
 pub mod vam;
+pub mod wear;
+pub mod error;
+pub mod block_map;
+pub mod meta_log;
+pub mod extent_allocator;
+pub mod options;
 pub mod core_manager;
\ No newline at end of file
@@ -0,0 +1,49 @@
+use crate::buf;
+use crate::util::cache;
+use crate::gc::victim;
+
+// 读取文件时atime的更新方式
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AtimePolicy {
+    Strict,     // 每次读取都更新
+    Relatime,   // atime不晚于mtime或ctime，或距上次更新超过一天时才更新
+    NoAtime,    // 不更新
+}
+
+// 挂载时可配置的选项
+#[derive(Copy, Clone)]
+pub struct MountOptions {
+    pub read_ahead: buf::ReadAhead,
+    pub buf_cache_policy: cache::CachePolicy,
+    pub inode_cache_policy: cache::CachePolicy,
+    pub background_gc: bool,
+    pub gc_low_watermark: u32,   // 以空闲Block数量计
+    pub gc_high_watermark: u32,
+    pub gc_budget: u32,          // 每次后台回收最多执行的步数
+    pub gc_step_size: u32,       // 增量GC每步最多搬移的Page数量
+    pub gc_victim_policy: victim::VictimPolicy,
+    pub wear_leveling: bool,
+    pub wear_threshold: u32,     // 允许的最大擦除次数差距
+    pub over_provision: u32,     // 为GC预留的Main Region百分比，不计入可用空间
+    pub atime_policy: AtimePolicy,
+}
+
+impl MountOptions {
+    pub fn new() -> MountOptions {
+        MountOptions {
+            read_ahead: buf::ReadAhead::Adaptive(32),
+            buf_cache_policy: cache::CachePolicy::LRU,
+            inode_cache_policy: cache::CachePolicy::LRU,
+            background_gc: true,
+            gc_low_watermark: 4,
+            gc_high_watermark: 8,
+            gc_budget: 1,
+            gc_step_size: 32,
+            gc_victim_policy: victim::VictimPolicy::Greedy,
+            wear_leveling: true,
+            wear_threshold: 16,
+            over_provision: 5,
+            atime_policy: AtimePolicy::Relatime,
+        }
+    }
+}
@@ -1,43 +1,74 @@
 use std::collections::HashMap;
-use crate::util::array::{self, Array2};
+use std::collections::BTreeSet;
+use crate::core::meta_log;
+
+// 每项依次为ino、文件偏移、版本号与虚拟地址
+pub const ENTRY_SIZE: usize = 20;
+// 每个表页记录的Page数量
+pub const PAGE_ENTRY_NUM: u32 = (meta_log::PAYLOAD_SIZE / ENTRY_SIZE) as u32;
+
+// Page的所有者，Extent以Byte为粒度，offset为Page第一个Byte在文件中的偏移
+// version在每次写入Page时递增，同一虚拟地址存在多个副本时以version最大的为准
+// v_address为写入时映射到的虚拟地址，挂载时据此判断Page是否已被Inode引用
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PageOwner {
+    pub ino: u32,
+    pub offset: u64,
+    pub version: u32,
+    pub v_address: u32,
+}
 
 pub struct PIT {
-    pub table: HashMap<u32, u32>,  // page -> ino
-    pub sync: bool,                // true 需要持久化到磁盘中
+    pub size: u32,
+    pub version: u32,
+    pub table: HashMap<u32, PageOwner>,  // page -> owner
+    pub dirty: BTreeSet<u32>,      // 需要持久化到磁盘中的表页
     pub is_op: bool,               // true 等调用end_op才持久化到磁盘中
 }
 
 impl PIT {
-    pub fn new() -> PIT {
+    // size为管理的Page数量
+    pub fn new(size: u32) -> PIT {
         PIT {
+            size,
+            version: 1,
             table: HashMap::new(),
-            sync: false,
+            dirty: BTreeSet::new(),
             is_op: false,
         }
     }
 
-    pub fn init_page(&mut self, address: u32, status: u32) {
+    pub fn get_page_num(&self) -> u32 {
+        (self.size + PAGE_ENTRY_NUM - 1) / PAGE_ENTRY_NUM
+    }
+
+    // 分配一个新的版本号
+    pub fn next_version(&mut self) -> u32 {
+        let version = self.version;
+        self.version += 1;
+        version
+    }
+
+    pub fn init_page(&mut self, address: u32, status: PageOwner) {
         if self.table.contains_key(&address) {
             panic!("PIT: init page has exist");
         }
         self.table.insert(address, status);
     }
 
-    pub fn get_page(&self, address: u32) -> u32 {
+    pub fn get_page(&self, address: u32) -> PageOwner {
         if !self.table.contains_key(&address) {
             panic!("PIT: get not that page");
         }
         self.table.get(&address).unwrap().clone()
     }
 
-    pub fn set_page(&mut self, address: u32, status: u32) {
-        if !self.table.contains_key(&address) {
-            self.table.insert(address, status);
-            self.sync = true;
-            return;
+    pub fn set_page(&mut self, address: u32, status: PageOwner) {
+        if address >= self.size {
+            panic!("PIT: set not that page");
         }
-        *self.table.get_mut(&address).unwrap() = status;
-        self.sync = true;
+        self.table.insert(address, status);
+        self.dirty.insert(address / PAGE_ENTRY_NUM);
     }
 
     pub fn delete_page(&mut self, address: u32) {
@@ -45,54 +76,70 @@ impl PIT {
             panic!("PIT: delete not that page");
         }
         self.table.remove(&address).unwrap();
-        self.sync = true;
+        self.dirty.insert(address / PAGE_ENTRY_NUM);
     }
 
     pub fn clean_page(&mut self, address: u32) {
         if self.table.contains_key(&address) {
             self.table.remove(&address).unwrap();
-            self.sync = true;
+            self.dirty.insert(address / PAGE_ENTRY_NUM);
         }
     }
 
-    pub fn encode(&self) -> Array2<u8> {
-        let mut res = array::Array1::<u32>::new(128 * 4096 / 4);
-        res.init(0);
-        for (key, value) in &self.table {
-            res.set(*key, *value);
+    pub fn encode_page(&self, page_no: u32) -> Vec<u8> {
+        let mut data = vec![0; meta_log::PAYLOAD_SIZE];
+        let start_index = page_no * PAGE_ENTRY_NUM;
+        for i in 0..PAGE_ENTRY_NUM {
+            if let Some(owner) = self.table.get(&(start_index + i)) {
+                let index = i as usize * ENTRY_SIZE;
+                data[index..index + 4].copy_from_slice(&owner.ino.to_be_bytes());
+                data[index + 4..index + 12].copy_from_slice(&owner.offset.to_be_bytes());
+                data[index + 12..index + 16].copy_from_slice(&owner.version.to_be_bytes());
+                data[index + 16..index + 20].copy_from_slice(&owner.v_address.to_be_bytes());
+            }
         }
-        let mut temp = array::Array1::<u8>::new(128 * 4096);
-        temp.init(0);
-        for (index, value) in res.iter().enumerate() {
-            let byte_1 = (value >> 24) as u8;
-            let byte_2 = (value >> 16) as u8;
-            let byte_3 = (value >> 8) as u8;
-            let byte_4 = value as u8;
-            let start_index = index * 4;
-            temp.set(start_index as u32, byte_1);
-            temp.set((start_index + 1) as u32, byte_2);
-            temp.set((start_index + 2) as u32, byte_3);
-            temp.set((start_index + 3) as u32, byte_4);
+        data
+    }
+
+    // 从磁盘读入一个表页，返回其中的(Page, 所有者)
+    pub fn load_page(&mut self, page_no: u32, payload: &[u8]) -> Vec<(u32, PageOwner)> {
+        if payload.len() != meta_log::PAYLOAD_SIZE {
+            panic!("PIT: load page not matched size");
         }
-        let mut data = array::Array2::<u8>::new(128, 4096);
-        data.init(0);
-        for (index, value) in temp.iter().enumerate() {
-            let i = index / 4096;
-            let j = index % 4096;
-            data.set(i as u32, j as u32, value);
+        let read_u32 = |index: usize| u32::from_be_bytes(payload[index..index + 4].try_into().unwrap());
+        let read_u64 = |index: usize| u64::from_be_bytes(payload[index..index + 8].try_into().unwrap());
+        let mut res = vec![];
+        for i in 0..PAGE_ENTRY_NUM {
+            let index = i as usize * ENTRY_SIZE;
+            let owner = PageOwner {
+                ino: read_u32(index),
+                offset: read_u64(index + 4),
+                version: read_u32(index + 12),
+                v_address: read_u32(index + 16),
+            };
+            if owner.ino != 0 {
+                let address = page_no * PAGE_ENTRY_NUM + i;
+                self.table.insert(address, owner);
+                self.version = self.version.max(owner.version + 1);
+                res.push((address, owner));
+            }
         }
-        data
+        res
+    }
+
+    pub fn get_dirty_pages(&self) -> Vec<u32> {
+        self.dirty.iter().cloned().collect()
     }
 
     pub fn need_sync(&self) -> bool {
         if self.is_op {
             return false;
         }
-        self.sync
+        !self.dirty.is_empty()
     }
 
     pub fn sync(&mut self) {
-        self.sync = false;
+        self.dirty.clear();
     }
 
     pub fn begin_op(&mut self) {
@@ -105,65 +152,36 @@ impl PIT {
 
 }
 
-pub struct DataRegion {
-    count: u32,
-    data: array::Array1<u8>,
-}
-
-impl DataRegion {
-    pub fn new(data: &array::Array2<u8>) -> DataRegion {
-        if data.size() != [128, 4096] {
-            panic!("TestRegion: new not matched size");
-        }
-        let mut arr = array::Array1::<u8>::new(data.len());
-        for (index, byte) in data.iter().enumerate() {
-            arr.set(index as u32, byte);
-        }
-        DataRegion {
-            count: 0,
-            data: arr,
-        }
-    }
-}
-
-impl Iterator for DataRegion {
-    type Item = u32;
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.count < self.data.len() {
-            let byte_1 = (self.data.get(self.count) as u32) << 24;
-            let byte_2 = (self.data.get(self.count+1) as u32) << 16;
-            let byte_3 = (self.data.get(self.count+2) as u32) << 8;
-            let byte_4 = self.data.get(self.count+3) as u32;
-            self.count += 4;
-            Some(byte_1 + byte_2 + byte_3 + byte_4)
-        } else {
-            None
-        }
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
 
     #[test]
     fn basics() {
-        let mut pit = PIT::new();
-        let mut data = array::Array2::<u8>::new(128, 4096);
-        data.init(0);
-        data.set(100, 312, 234);
-        data.set(11, 232, 67);
-        data.set(121, 2332, 123);
-        let iter = DataRegion::new(&data);
-        for (index, ino) in iter.enumerate() {
-            if ino != 0 {
-                pit.init_page(index as u32, ino);
-            }
-        }
-        assert_eq!(pit.encode(), data);
+        let mut pit = PIT::new(PAGE_ENTRY_NUM * 3);
+        assert_eq!(pit.get_page_num(), 3);
+        let mut data = vec![0; meta_log::PAYLOAD_SIZE];
+        data[3] = 67;
+        data[11] = 1;
+        data[15] = 9;
+        data[19] = 4;
+        data[ENTRY_SIZE * 100] = 2;
+        data[ENTRY_SIZE * 100 + 7] = 5;
+        data[ENTRY_SIZE * 100 + 10] = 16;
+        data[ENTRY_SIZE * 100 + 15] = 3;
+        data[ENTRY_SIZE * 100 + 17] = 1;
+        let pages = pit.load_page(2, &data);
+        assert_eq!(pages, vec![
+            (PAGE_ENTRY_NUM * 2, PageOwner { ino: 67, offset: 1, version: 9, v_address: 4 }),
+            (PAGE_ENTRY_NUM * 2 + 100, PageOwner { ino: 2 << 24, offset: (5 << 32) + 4096, version: 3, v_address: 1 << 16 }),
+        ]);
+        assert_eq!(pit.encode_page(2), data);
+        assert_eq!(pit.next_version(), 10);
         assert_eq!(pit.need_sync(), false);
-        pit.set_page(200, 100);
-        assert_eq!(pit.get_page(200), 100);
+        let owner = PageOwner { ino: 100, offset: 0, version: pit.next_version(), v_address: 7 };
+        pit.set_page(200, owner);
+        assert_eq!(pit.get_page(200), owner);
         assert_eq!(pit.need_sync(), true);
+        assert_eq!(pit.get_dirty_pages(), vec![0]);
     }
 }
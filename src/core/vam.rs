@@ -1,95 +1,277 @@
-use std::collections::HashMap;
+use std::collections::BTreeSet;
+use crate::util::cache;
+use crate::core::meta_log;
+use crate::core::extent_allocator;
 
+// 每个Translation Page保存的映射项数量
+pub const ENTRY_NUM: u32 = meta_log::PAYLOAD_SIZE as u32 / 4;
+// 默认缓存的Translation Page数量
+pub const CACHE_SIZE: usize = 64;
+
+// 一段虚拟地址到物理地址的映射，映射项保存物理地址+1，0表示未映射
+#[derive(Clone, PartialEq, Debug)]
+pub struct TranslationPage {
+    pub entries: Vec<u32>,
+}
+
+impl TranslationPage {
+    pub fn new() -> TranslationPage {
+        TranslationPage {
+            entries: vec![0; ENTRY_NUM as usize],
+        }
+    }
+
+    pub fn get(&self, index: u32) -> Option<u32> {
+        match self.entries[index as usize] {
+            0 => None,
+            address => Some(address - 1),
+        }
+    }
+
+    pub fn set(&mut self, index: u32, address: Option<u32>) {
+        self.entries[index as usize] = match address {
+            Some(address) => address + 1,
+            None => 0,
+        };
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = vec![0; meta_log::PAYLOAD_SIZE];
+        for (i, entry) in self.entries.iter().enumerate() {
+            data[i * 4..i * 4 + 4].copy_from_slice(&entry.to_be_bytes());
+        }
+        data
+    }
+
+    pub fn decode(payload: &[u8]) -> TranslationPage {
+        if payload.len() != meta_log::PAYLOAD_SIZE {
+            panic!("TranslationPage: decode not matched size");
+        }
+        let mut page = TranslationPage::new();
+        for i in 0..ENTRY_NUM as usize {
+            page.entries[i] = u32::from_be_bytes([payload[i * 4], payload[i * 4 + 1], payload[i * 4 + 2], payload[i * 4 + 3]]);
+        }
+        page
+    }
+}
+
+// 以Page为粒度的FTL，映射表按Translation Page保存在Metadata Region中
+// 仿照DFTL只缓存热点Translation Page，脏页在被替换或sync时写回，其余的按需读入
+// 不保存物理地址到虚拟地址的反向映射，由CoreManager根据PIT中的所有者在Inode中查找
+// 虚拟地址在解除映射时归还给allocator，挂载时根据Inode中的Extent恢复，不单独持久化
+// 中断前未提交的Extent会在Translation Page中留下映射，读入时清除allocator中空闲地址上的映射
 pub struct VAM {
-    count: u32,
-    physical_address_table: HashMap<u32, u32>, // physical -> virtual
-    virtual_address_table: HashMap<u32, u32>,  // virtual -> physical
+    allocator: extent_allocator::ExtentAllocator,
+    size: u32,
+    cache: Box<dyn cache::Cache<u32, TranslationPage>>,
+    dirty: BTreeSet<u32>,
 }
 
 impl VAM {
-    pub fn new() -> VAM {
+    // size为虚拟地址空间大小，以Page为单位
+    pub fn new(size: u32) -> VAM {
+        VAM::new_with_cache(size, CACHE_SIZE)
+    }
+
+    pub fn new_with_cache(size: u32, capacity: usize) -> VAM {
         VAM {
-            count: 0,
-            physical_address_table: HashMap::default(),
-            virtual_address_table: HashMap::default(),
+            allocator: extent_allocator::ExtentAllocator::new(size),
+            size,
+            cache: cache::new_cache(cache::CachePolicy::LRU, capacity),
+            dirty: BTreeSet::new(),
         }
     }
 
-    pub fn get_available_address(&mut self, size: u32) -> u32 {
-        let res = self.count;
-        self.count += size;
-        res
+    pub fn get_size(&self) -> u32 {
+        self.size
     }
 
-    pub fn get_virtual_address(&self, address: u32) -> Option<u32> {
-        let address = self.physical_address_table.get(&address);
-        match address {
-            Some(address) => {
-                Some(address.clone())
-            }
-            None => None
-        }
+    pub fn get_tpage_num(&self) -> u32 {
+        (self.size + ENTRY_NUM - 1) / ENTRY_NUM
     }
 
-    pub fn get_physic_address(&self, v_address: u32) -> Option<u32> {
-        let address = self.virtual_address_table.get(&v_address);
-        match address {
-            Some(address) => {
-                Some(address.clone())
-            }
-            None => None
+    // 分配连续的size个虚拟地址
+    pub fn get_available_address(&mut self, size: u32) -> u32 {
+        match self.allocator.allocate(size) {
+            Some(address) => address,
+            None => panic!("VAM: no available address"),
         }
     }
 
+    pub fn get_free_size(&self) -> u32 {
+        self.allocator.get_free_size()
+    }
+
+    // 挂载时标记Inode中Extent使用的虚拟地址
+    pub fn reserve_address(&mut self, v_address: u32, size: u32) {
+        self.allocator.reserve(v_address, size);
+    }
+
+    // 归还分配后没有建立映射的虚拟地址
+    pub fn free_address(&mut self, v_address: u32, size: u32) {
+        self.allocator.free(v_address, size);
+    }
+
+    // 以下映射操作要求对应的Translation Page已在缓存中
+    pub fn get_physic_address(&mut self, v_address: u32) -> Option<u32> {
+        let page = self.get_cached_page(v_address / ENTRY_NUM);
+        page.get(v_address % ENTRY_NUM)
+    }
+
+    // 挂载后分配到的虚拟地址可能还留有中断前未被Inode引用的映射，直接覆盖
     pub fn insert_map(&mut self, address: u32, v_address: u32) {
-        if self.physical_address_table.contains_key(&address) {
-            panic!("VAM: insert map has exist");
-        }
-        self.physical_address_table.insert(address, v_address);
-        self.virtual_address_table.insert(v_address, address);
+        self.set_entry(v_address, Some(address));
     }
 
     pub fn update_map(&mut self, address: u32, v_address: u32) {
-        let o_address = self.get_physic_address(v_address);
-        if o_address.is_none() {
+        if self.get_physic_address(v_address).is_none() {
             panic!("VAM: update no that map");
         }
-        self.delete_map(o_address.unwrap(), v_address);
-        self.insert_map(address, v_address);
+        self.set_entry(v_address, Some(address));
     }
 
+    // 解除映射后虚拟地址可以被重新分配
     pub fn delete_map(&mut self, address: u32, v_address: u32) {
-        if !self.physical_address_table.contains_key(&address) {
+        if self.get_physic_address(v_address) != Some(address) {
             panic!("VAM: delete no that map");
         }
-        self.physical_address_table.remove(&address);
-        self.virtual_address_table.remove(&v_address);
+        self.set_entry(v_address, None);
+        self.allocator.free(v_address, 1);
+    }
+
+    fn set_entry(&mut self, v_address: u32, address: Option<u32>) {
+        let tpage_no = v_address / ENTRY_NUM;
+        self.get_cached_page(tpage_no).set(v_address % ENTRY_NUM, address);
+        self.dirty.insert(tpage_no);
+    }
+
+    fn get_cached_page(&mut self, tpage_no: u32) -> &mut TranslationPage {
+        if tpage_no >= self.get_tpage_num() {
+            panic!("VAM: virtual address out of range");
+        }
+        match self.cache.get_mut(&tpage_no) {
+            Some(page) => page,
+            None => panic!("VAM: translation page not cached"),
+        }
+    }
+}
+
+// 缓存管理，实际读写由CoreManager完成
+impl VAM {
+    pub fn is_cached(&self, tpage_no: u32) -> bool {
+        self.cache.contains_key(&tpage_no)
+    }
+
+    // 返回被替换出且需要写回的Translation Page，清除过残留映射的表页标记为脏页
+    pub fn cache_page(&mut self, tpage_no: u32, mut page: TranslationPage) -> Option<(u32, TranslationPage)> {
+        let mut cleared = false;
+        for index in 0..ENTRY_NUM {
+            let v_address = tpage_no * ENTRY_NUM + index;
+            if page.get(index).is_some() && v_address < self.size && self.allocator.is_free(v_address) {
+                page.set(index, None);
+                cleared = true;
+            }
+        }
+        let res = match self.cache.put(tpage_no, page) {
+            Some((tpage_no, page)) if self.dirty.remove(&tpage_no) => Some((tpage_no, page)),
+            _ => None,
+        };
+        if cleared {
+            self.dirty.insert(tpage_no);
+        }
+        res
+    }
+
+    // 取出缓存中的脏页用于写回
+    pub fn take_dirty_pages(&mut self) -> Vec<(u32, TranslationPage)> {
+        let mut pages = vec![];
+        let dirty = std::mem::take(&mut self.dirty);
+        for tpage_no in dirty {
+            let page = self.cache.get(&tpage_no).unwrap().clone();
+            pages.push((tpage_no, page));
+        }
+        pages
+    }
+
+    pub fn get_dirty_size(&self) -> usize {
+        self.dirty.len()
+    }
+
+    pub fn get_cache_stat(&self) -> cache::CacheStat {
+        self.cache.get_stat()
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    
+
     #[test]
     fn basics() {
-        let mut vam = VAM::new();
+        let mut vam = VAM::new(4096);
+        assert_eq!(vam.get_tpage_num(), 5);
 
         assert_eq!(vam.get_available_address(10), 0);
         assert_eq!(vam.get_available_address(10), 10);
 
+        assert_eq!(vam.cache_page(0, TranslationPage::new()), None);
         for i in 0..10 {
             vam.insert_map(i, 10 + i);
         }
         assert_eq!(vam.get_physic_address(14).unwrap(), 4);
-        assert_eq!(vam.get_virtual_address(2).unwrap(), 12);
 
         vam.update_map(100, 13);
         assert_eq!(vam.get_physic_address(13).unwrap(), 100);
-        assert_eq!(vam.get_virtual_address(100).unwrap(), 13);
 
         vam.delete_map(100, 13);
         assert_eq!(vam.get_physic_address(13), None);
-        assert_eq!(vam.get_virtual_address(100), None);
+        assert_eq!(vam.get_dirty_size(), 1);
+        // 释放的虚拟地址被重新分配
+        assert_eq!(vam.get_free_size(), 4096 - 19);
+        assert_eq!(vam.get_available_address(1), 13);
+        assert_eq!(vam.get_available_address(2), 20);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn page() {
+        let mut page = TranslationPage::new();
+        page.set(0, Some(0));
+        page.set(ENTRY_NUM - 1, Some(12345));
+        let data = page.encode();
+        assert_eq!(data.len(), meta_log::PAYLOAD_SIZE);
+        assert_eq!(TranslationPage::decode(&data), page);
+        assert_eq!(TranslationPage::decode(&[0; meta_log::PAYLOAD_SIZE]), TranslationPage::new());
+    }
+
+    #[test]
+    fn cache() {
+        let mut vam = VAM::new_with_cache(4096, 3);
+        for tpage_no in 0..3 {
+            vam.cache_page(tpage_no, TranslationPage::new());
+            vam.insert_map(tpage_no, tpage_no * ENTRY_NUM);
+        }
+        // 缓存只有三个位置，最早的脏页被替换出来需要写回
+        let (tpage_no, page) = vam.cache_page(3, TranslationPage::new()).unwrap();
+        assert_eq!(tpage_no, 0);
+        assert_eq!(page.get(0), Some(0));
+        assert_eq!(vam.take_dirty_pages().len(), 2);
+        assert_eq!(vam.get_dirty_size(), 0);
+        assert_eq!(vam.cache_page(4, TranslationPage::new()), None);
+
+        // 挂载时只恢复Inode使用的虚拟地址，Translation Page之后按需读入
+        let mut vam = VAM::new(4096);
+        vam.reserve_address(2 * ENTRY_NUM + 5, 3);
+        assert_eq!(vam.get_free_size(), 4096 - 3);
+        assert_eq!(vam.get_available_address(2 * ENTRY_NUM + 5), 0);
+        assert_eq!(vam.get_available_address(1), 2 * ENTRY_NUM + 8);
+        assert_eq!(vam.is_cached(2), false);
+        // 读入时清除空闲虚拟地址上残留的映射
+        let mut page = TranslationPage::new();
+        page.set(5, Some(100));
+        page.set(9, Some(200));
+        assert_eq!(vam.cache_page(2, page), None);
+        assert_eq!(vam.get_physic_address(2 * ENTRY_NUM + 5), Some(100));
+        assert_eq!(vam.get_physic_address(2 * ENTRY_NUM + 9), None);
+        assert_eq!(vam.get_dirty_size(), 1);
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::BTreeSet;
+use crate::core::meta_log;
+
+// 每个表页记录的Block数量
+pub const PAGE_ENTRY_NUM: u32 = meta_log::PAYLOAD_SIZE as u32 / 4;
+
+// 记录每个物理Block的擦除次数，按表页保存在Metadata Region中
+// 擦除次数只用于磨损均衡，修改后不立即写回，sync时统一持久化
+pub struct WearTable {
+    erase_count: Vec<u32>,
+    dirty: BTreeSet<u32>,
+}
+
+impl WearTable {
+    // size为磁盘的Block数量
+    pub fn new(size: u32) -> WearTable {
+        WearTable {
+            erase_count: vec![0; size as usize],
+            dirty: BTreeSet::new(),
+        }
+    }
+
+    pub fn get_page_num(&self) -> u32 {
+        (self.erase_count.len() as u32 + PAGE_ENTRY_NUM - 1) / PAGE_ENTRY_NUM
+    }
+
+    pub fn get(&self, block_no: u32) -> u32 {
+        self.erase_count[block_no as usize]
+    }
+
+    pub fn increase(&mut self, block_no: u32) {
+        self.erase_count[block_no as usize] += 1;
+        self.dirty.insert(block_no / PAGE_ENTRY_NUM);
+    }
+
+    pub fn encode_page(&self, page_no: u32) -> Vec<u8> {
+        let mut data = vec![0; meta_log::PAYLOAD_SIZE];
+        let start_index = page_no * PAGE_ENTRY_NUM;
+        for i in 0..PAGE_ENTRY_NUM {
+            if let Some(count) = self.erase_count.get((start_index + i) as usize) {
+                data[i as usize * 4..i as usize * 4 + 4].copy_from_slice(&count.to_be_bytes());
+            }
+        }
+        data
+    }
+
+    pub fn load_page(&mut self, page_no: u32, payload: &[u8]) {
+        if payload.len() != meta_log::PAYLOAD_SIZE {
+            panic!("WearTable: load page not matched size");
+        }
+        let start_index = page_no * PAGE_ENTRY_NUM;
+        for i in 0..PAGE_ENTRY_NUM as usize {
+            let block_no = start_index as usize + i;
+            if block_no >= self.erase_count.len() {
+                break;
+            }
+            self.erase_count[block_no] = u32::from_be_bytes([payload[i * 4], payload[i * 4 + 1], payload[i * 4 + 2], payload[i * 4 + 3]]);
+        }
+    }
+
+    pub fn take_dirty_pages(&mut self) -> Vec<u32> {
+        std::mem::take(&mut self.dirty).into_iter().collect()
+    }
+
+    pub fn need_sync(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        let mut table = WearTable::new(PAGE_ENTRY_NUM + 10);
+        assert_eq!(table.get_page_num(), 2);
+        table.increase(PAGE_ENTRY_NUM + 3);
+        table.increase(PAGE_ENTRY_NUM + 3);
+        assert_eq!(table.take_dirty_pages(), vec![1]);
+        assert_eq!(table.need_sync(), false);
+        let data = table.encode_page(1);
+        let mut table = WearTable::new(PAGE_ENTRY_NUM + 10);
+        table.load_page(1, &data);
+        assert_eq!(table.get(PAGE_ENTRY_NUM + 3), 2);
+        assert_eq!(table.get(3), 0);
+    }
+}
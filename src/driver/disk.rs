@@ -11,10 +11,14 @@ impl DiskDriver {
         }
     }
 
-    pub fn disk_read(&self, block_no: u32) -> [[u8; 4096]; 128] {
-        [[0; 4096]; 128]
+    pub fn disk_read(&self, block_no: u32) -> Vec<[u8; 4096]> {
+        vec![[0; 4096]; 128]
     }
     
+    pub fn disk_read_page(&self, address: u32) -> [u8; 4096] {
+        [0; 4096]
+    }
+
     pub fn disk_write(&mut self, address: u32, data: [u8; 4096]) {
     }
     
@@ -1,67 +1,118 @@
 use crate::write_buf;
-use crate::util::array;
-use crate::driver::{disk, fake_disk};
+use crate::metrics;
+use crate::driver::{disk, fake_disk, timing};
 
 pub struct DiskManager {
     pub is_virtual: bool,
+    pub size: u32,
     pub driver: Option<disk::DiskDriver>,
     pub fake_disk: Option<fake_disk::FakeDisk>,
     pub write_cache: write_buf::WriteCache,
+    pub metrics: metrics::MetricsLink,
 }
 
 impl DiskManager {
     pub fn new(is_virtual: bool) -> DiskManager {
+        DiskManager::new_with_size(is_virtual, 4096) // 32 Block 4096 Page
+    }
+
+    // size以Page为单位，需为128的整数倍
+    pub fn new_with_size(is_virtual: bool, size: u32) -> DiskManager {
         let mut driver = None;
         let mut fake_disk = None;
         if is_virtual {
-            fake_disk = Some(fake_disk::FakeDisk::new(4096)); // 32 Block 1024 Page
+            fake_disk = Some(fake_disk::FakeDisk::new(size));
         } else {
             driver = Some(disk::DiskDriver::new());
         }
         DiskManager {
             is_virtual,
+            size,
             driver,
             fake_disk,
             write_cache: write_buf::WriteCache::new(),
+            metrics: metrics::new_link(),
+        }
+    }
+
+    pub fn get_block_num(&self) -> u32 {
+        self.size / 128
+    }
+
+    // 只对虚拟磁盘生效
+    pub fn set_timing_model(&mut self, model: timing::TimingModel) {
+        if self.is_virtual {
+            self.fake_disk.as_mut().unwrap().set_timing_model(model);
+        }
+    }
+
+    pub fn get_clock(&self) -> Option<&timing::VirtualClock> {
+        if self.is_virtual {
+            return Some(&self.fake_disk.as_ref().unwrap().clock);
         }
+        None
     }
 
-    pub fn read(&self, block_no: u32) -> [[u8; 4096]; 128] {
+    pub fn read(&mut self, block_no: u32) -> Vec<[u8; 4096]> {
         let start_index = block_no * 128;
         let end_index = (block_no + 1) * 128;
-        let mut exist_indexs = vec![];
+        let mut block_data = self.disk_read(block_no);
         for index in start_index..end_index {
-            if self.write_cache.contains_address(index) {
-                exist_indexs.push(index);
+            if let Some(data) = self.write_cache.read(index) {
+                block_data[(index - start_index) as usize] = data;
             }
         }
-        let mut block_data = DiskManager::transfer(self.disk_read(block_no));
-        for index in exist_indexs.into_iter() {
-            let data = self.write_cache.read(index).unwrap();
-            block_data.set(index - start_index, data);
+        block_data
+    }
+
+    pub fn read_page(&mut self, address: u32) -> [u8; 4096] {
+        if let Some(data) = self.write_cache.read(address) {
+            return data;
+        }
+        self.disk_read_page(address)
+    }
+
+    pub fn disk_read_page(&mut self, address: u32) -> [u8; 4096] {
+        self.metrics.borrow_mut().record_device_read(1);
+        if self.is_virtual {
+            self.fake_disk.as_mut().unwrap().fake_disk_read_page(address)
+        } else {
+            self.driver.as_ref().unwrap().disk_read_page(address)
         }
-        DiskManager::reverse(&block_data)
     }
 
-    pub fn disk_read(&self, block_no: u32) -> [[u8; 4096]; 128] {
+    pub fn disk_read(&mut self, block_no: u32) -> Vec<[u8; 4096]> {
+        self.metrics.borrow_mut().record_device_read(128);
         if self.is_virtual {
-            self.fake_disk.as_ref().unwrap().fake_disk_read(block_no)
+            self.fake_disk.as_mut().unwrap().fake_disk_read(block_no)
         } else {
             self.driver.as_ref().unwrap().disk_read(block_no)
         }
     }
     
+    // WriteCache已满时先写回再写入
     pub fn disk_write(&mut self, address: u32, data: [u8; 4096]) {
-        self.write_cache.write(address, data);
+        if !self.write_cache.write(address, data) {
+            self.sync();
+            self.write_cache.write(address, data);
+        }
         if !self.write_cache.need_sync() {
             return;
         }
-        let data = self.write_cache.get_all();
+        self.sync();
+    }
+
+    // 将WriteCache中的Page按物理地址顺序写入磁盘
+    pub fn sync(&mut self) {
+        let mut data = self.write_cache.get_all();
+        data.sort_by_key(|entry| entry.0);
+        self.metrics.borrow_mut().record_device_program(data.len() as u32);
         for entry in data.into_iter() {
             if self.is_virtual {
-                return self.fake_disk.as_mut().unwrap().fake_disk_write(entry.0, entry.1);
+                self.fake_disk.as_mut().unwrap().fake_disk_write(entry.0, entry.1);
+            } else {
+                self.driver.as_mut().unwrap().disk_write(entry.0, entry.1);
             }
-            self.driver.as_mut().unwrap().disk_write(entry.0, entry.1);
         }
         self.write_cache.sync();
     }
@@ -72,29 +123,13 @@ impl DiskManager {
         for index in start_index..end_index {
             self.write_cache.recall_write(index);
         }
+        self.metrics.borrow_mut().record_device_erase();
         if self.is_virtual {
             self.fake_disk.as_mut().unwrap().fake_disk_erase(block_no);
             return;
         }
         self.driver.as_mut().unwrap().disk_erase(block_no);
     }
-
-    pub fn transfer(data: [[u8; 4096]; 128]) -> array::Array1<[u8; 4096]> {
-        let mut res = array::Array1::new(128);
-        res.init([0; 4096]);
-        for i in 0..128 {
-            res.set(i, data[i as usize]);
-        }
-        res
-    }
-
-    pub fn reverse(data: &array::Array1<[u8; 4096]>) -> [[u8; 4096]; 128] {
-        let mut ret = [[0; 4096]; 128];
-        for i in 0..128 {
-            ret[i] = data.get(i as u32);
-        }
-        ret
-    }
 }
 
 mod test {
@@ -108,9 +143,46 @@ mod test {
         manager.disk_write(100, data);
         let data = manager.read(0);
         assert_eq!(data[100], [1; 4096]);
+        assert_eq!(manager.read_page(100), [1; 4096]);
 
         manager.disk_erase(0);
         let data = manager.read(0); 
         assert_eq!(data[100], [0; 4096]);
+
+        for address in 0..300 {
+            manager.disk_write(address, [2; 4096]);
+        }
+        manager.sync();
+        assert_eq!(manager.write_cache.cache.len(), 0);
+        assert_eq!(manager.disk_read_page(0), [2; 4096]);
+        assert_eq!(manager.disk_read_page(299), [2; 4096]);
+
+        // WriteCache已满时先写回，不会丢弃写入
+        for address in 400..528 {
+            manager.write_cache.write(address, [3; 4096]);
+        }
+        manager.disk_write(600, [4; 4096]);
+        assert_eq!(manager.write_cache.cache.len(), 1);
+        assert_eq!(manager.disk_read_page(527), [3; 4096]);
+        assert_eq!(manager.read_page(600), [4; 4096]);
+    }
+
+    #[test]
+    fn sparse() {
+        let manager = DiskManager::new_with_size(true, 8 * 1024 * 1024);
+        assert_eq!(manager.get_block_num(), 64 * 1024);
+        assert_eq!(manager.fake_disk.as_ref().unwrap().used_page_num(), 0);
+    }
+
+    #[test]
+    fn timing() {
+        let mut manager = DiskManager::new(true);
+        let model = timing::TimingModel::new();
+        manager.set_timing_model(model);
+        manager.read(1);
+        let clock = manager.get_clock().unwrap();
+        assert_eq!(clock.read_num, 128);
+        assert_eq!(clock.elapsed(), (model.page_read + model.transfer_time(4096)) * 128);
+        assert!(clock.throughput() > 0);
     }
 }
\ No newline at end of file
@@ -1,67 +1,95 @@
+use std::collections::{HashMap, HashSet};
+use crate::driver::timing;
+
 // Disk I/O Simulator
+// 只保存已写入的非全0 Page，其余Page读出全0
+// 写入过的地址单独记录，擦除前不能再次写入
 
 pub struct FakeDisk {
     pub size: u32,
     pub block_num: u32,
-    pub data: Vec<[u8; 4096]>,
+    pub data: HashMap<u32, [u8; 4096]>,
+    pub programmed: HashSet<u32>,
+    pub clock: timing::VirtualClock,
 }
 
 impl FakeDisk {
     pub fn new(size: u32) -> FakeDisk {
-        let mut data = vec![];
         if size % 128 != 0 {
             panic!("FakeDisk: not available size")
         }
-        for _ in 0..size {
-            data.push([0; 4096]);
-        }
-        let block_num;
-        if size % 128 == 0 {
-            block_num = size / 128;
-        } else {
-            block_num = size / 128 + 1;
-        }
+        let block_num = size / 128;
         FakeDisk {
             size,
-            data,
+            data: HashMap::new(),
+            programmed: HashSet::new(),
             block_num,
+            clock: timing::VirtualClock::new(timing::TimingModel::new()),
         }
     }
 
-    pub fn fake_disk_read(&self, block_no: u32) -> [[u8; 4096]; 128] {
+    pub fn set_timing_model(&mut self, model: timing::TimingModel) {
+        self.clock = timing::VirtualClock::new(model);
+    }
+
+    pub fn fake_disk_read(&mut self, block_no: u32) -> Vec<[u8; 4096]> {
         if block_no > self.block_num - 1 {
             panic!("FakeKV: read at too big block number");
         }
-        let mut data = [[0; 4096]; 128];
+        let mut data = vec![[0; 4096]; 128];
         let start_index = block_no * 128;
         let end_index = (block_no + 1) * 128;
         for index in start_index..end_index {
-            data[(index - start_index) as usize] = self.data[index as usize];
+            self.clock.read_page(block_no);
+            if let Some(page) = self.data.get(&index) {
+                data[(index - start_index) as usize] = *page;
+            }
         }
         data
     }
     
+    pub fn fake_disk_read_page(&mut self, address: u32) -> [u8; 4096] {
+        if address > self.size - 1 {
+            panic!("FakeDisk: read at not available address");
+        }
+        self.clock.read_page(address / 128);
+        match self.data.get(&address) {
+            Some(page) => *page,
+            None => [0; 4096],
+        }
+    }
+
     pub fn fake_disk_write(&mut self, address: u32, data: [u8; 4096]) {
         if address > self.size - 1 {
             panic!("FakeDisk: write at not available address");
         }
-        let o_data = self.data[address as usize];
-        if o_data != [0; 4096] {
+        if !self.programmed.insert(address) {
             panic!("FakeDisk: write at not clean address");
         }
-        self.data[address as usize] = data;
+        self.clock.program_page(address / 128);
+        if data == [0; 4096] {
+            return;
+        }
+        self.data.insert(address, data);
     }
     
     pub fn fake_disk_erase(&mut self, block_no: u32) {
         if block_no > self.block_num - 1 {
             panic!("FakeKV: erase at too big block number");
         }
+        self.clock.erase_block(block_no);
         let start_index = block_no * 128;
         let end_index = (block_no + 1) * 128;
         for index in start_index..end_index {
-            self.data[index as usize] = [0; 4096];
+            self.data.remove(&index);
+            self.programmed.remove(&index);
         }
     }
+
+    // 已写入的Page数量，即实际占用的内存
+    pub fn used_page_num(&self) -> u32 {
+        self.data.len() as u32
+    }
 }
 
 #[cfg(test)]
@@ -83,6 +111,7 @@ mod test {
         disk.fake_disk_write(256, data);
         let data = disk.fake_disk_read(1);
         assert_eq!(data[2], [0; 4096]);
+        assert_eq!(disk.fake_disk_read_page(256), [2; 4096]);
         
         disk.fake_disk_erase(2);
         let data = disk.fake_disk_read(1);
@@ -91,4 +120,53 @@ mod test {
         assert_eq!(disk.block_num, 8);
         assert_eq!(disk.size, 1024);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn sparse() {
+        // Create 64GB Disk
+        let mut disk = FakeDisk::new(16 * 1024 * 1024);
+        assert_eq!(disk.block_num, 128 * 1024);
+        assert_eq!(disk.used_page_num(), 0);
+
+        let last = disk.size - 1;
+        disk.fake_disk_write(last, [3; 4096]);
+        disk.fake_disk_write(0, [4; 4096]);
+        disk.fake_disk_write(1, [0; 4096]);
+        assert_eq!(disk.used_page_num(), 2);
+
+        let data = disk.fake_disk_read(disk.block_num - 1);
+        assert_eq!(data[127], [3; 4096]);
+        assert_eq!(data[0], [0; 4096]);
+
+        disk.fake_disk_erase(disk.block_num - 1);
+        assert_eq!(disk.used_page_num(), 1);
+        let data = disk.fake_disk_read(disk.block_num - 1);
+        assert_eq!(data[127], [0; 4096]);
+    }
+
+    #[test]
+    #[should_panic(expected = "FakeDisk: write at not clean address")]
+    fn overwrite() {
+        // 全0的Page不保存，但擦除前同样不能再次写入
+        let mut disk = FakeDisk::new(1024);
+        disk.fake_disk_write(1, [0; 4096]);
+        disk.fake_disk_write(1, [5; 4096]);
+    }
+
+    #[test]
+    fn timing() {
+        let mut disk = FakeDisk::new(1024);
+        let mut model = timing::TimingModel::new();
+        model.channel_num = 2;
+        disk.set_timing_model(model);
+
+        disk.fake_disk_write(0, [1; 4096]);
+        disk.fake_disk_write(128, [1; 4096]);
+        disk.fake_disk_read(0);
+        disk.fake_disk_erase(1);
+        assert_eq!(disk.clock.program_num, 2);
+        assert_eq!(disk.clock.read_num, 128);
+        assert_eq!(disk.clock.erase_num, 1);
+        assert!(disk.clock.elapsed() >= model.page_read * 128 + model.block_erase);
+    }
+}
@@ -1,3 +1,4 @@
 pub mod disk;
 pub mod fake_disk;
+pub mod timing;
 pub mod disk_manager;
\ No newline at end of file
@@ -0,0 +1,196 @@
+use std::cmp::max;
+
+// NAND Flash的时延与带宽模型，时间以ns为单位
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TimingModel {
+    pub page_read: u64,     // tR
+    pub page_program: u64,  // tPROG
+    pub block_erase: u64,   // tBERS
+    pub bandwidth: u64,     // 每个Channel的总线带宽，以Byte/s为单位
+    pub channel_num: u32,
+    pub plane_num: u32,     // 每个Channel上可并行的Plane数量，1即不开启Multi-Plane
+}
+
+impl TimingModel {
+    pub fn new() -> TimingModel {
+        TimingModel {
+            page_read: 50_000,
+            page_program: 600_000,
+            block_erase: 3_000_000,
+            bandwidth: 400 * 1024 * 1024,
+            channel_num: 1,
+            plane_num: 1,
+        }
+    }
+
+    pub fn transfer_time(&self, bytes: u64) -> u64 {
+        bytes * 1_000_000_000 / self.bandwidth
+    }
+
+    // Block按Channel、Plane依次交错分布
+    pub fn get_channel(&self, block_no: u32) -> u32 {
+        block_no % self.channel_num
+    }
+
+    pub fn get_plane(&self, block_no: u32) -> u32 {
+        block_no / self.channel_num % self.plane_num
+    }
+}
+
+// 每次操作推进的虚拟时钟
+// 读操作需等待数据返回，写和擦除在总线传输完成后即返回
+pub struct VirtualClock {
+    pub model: TimingModel,
+    pub now: u64,
+    pub channel_free: Vec<u64>,
+    pub plane_free: Vec<u64>,
+    pub read_num: u64,
+    pub program_num: u64,
+    pub erase_num: u64,
+    pub read_time: u64,
+    pub program_time: u64,
+    pub erase_time: u64,
+    pub transfer_time: u64,
+}
+
+impl VirtualClock {
+    pub fn new(model: TimingModel) -> VirtualClock {
+        if model.channel_num == 0 || model.plane_num == 0 || model.bandwidth == 0 {
+            panic!("VirtualClock: new not available model");
+        }
+        VirtualClock {
+            model,
+            now: 0,
+            channel_free: vec![0; model.channel_num as usize],
+            plane_free: vec![0; (model.channel_num * model.plane_num) as usize],
+            read_num: 0,
+            program_num: 0,
+            erase_num: 0,
+            read_time: 0,
+            program_time: 0,
+            erase_time: 0,
+            transfer_time: 0,
+        }
+    }
+
+    pub fn read_page(&mut self, block_no: u32) {
+        let (channel, plane) = self.get_unit(block_no);
+        let transfer = self.model.transfer_time(4096);
+        let start = max(self.now, self.plane_free[plane]);
+        let bus_start = max(start + self.model.page_read, self.channel_free[channel]);
+        let end = bus_start + transfer;
+        self.plane_free[plane] = end;
+        self.channel_free[channel] = end;
+        self.now = end;
+        self.read_num += 1;
+        self.read_time += self.model.page_read;
+        self.transfer_time += transfer;
+    }
+
+    pub fn program_page(&mut self, block_no: u32) {
+        let (channel, plane) = self.get_unit(block_no);
+        let transfer = self.model.transfer_time(4096);
+        let bus_start = max(self.now, max(self.channel_free[channel], self.plane_free[plane]));
+        let bus_end = bus_start + transfer;
+        self.channel_free[channel] = bus_end;
+        self.plane_free[plane] = bus_end + self.model.page_program;
+        self.now = bus_end;
+        self.program_num += 1;
+        self.program_time += self.model.page_program;
+        self.transfer_time += transfer;
+    }
+
+    pub fn erase_block(&mut self, block_no: u32) {
+        let (_, plane) = self.get_unit(block_no);
+        let start = max(self.now, self.plane_free[plane]);
+        self.plane_free[plane] = start + self.model.block_erase;
+        self.erase_num += 1;
+        self.erase_time += self.model.block_erase;
+    }
+
+    // 所有已发出的操作全部完成的时间
+    pub fn elapsed(&self) -> u64 {
+        let mut res = self.now;
+        for time in self.channel_free.iter().chain(self.plane_free.iter()) {
+            res = max(res, *time);
+        }
+        res
+    }
+
+    // 以Byte/s为单位的读写吞吐量
+    pub fn throughput(&self) -> u64 {
+        let elapsed = self.elapsed();
+        if elapsed == 0 {
+            return 0;
+        }
+        let bytes = (self.read_num + self.program_num) * 4096;
+        (bytes as u128 * 1_000_000_000 / elapsed as u128) as u64
+    }
+
+    pub fn reset(&mut self) {
+        *self = VirtualClock::new(self.model);
+    }
+}
+
+impl VirtualClock {
+    fn get_unit(&self, block_no: u32) -> (usize, usize) {
+        let channel = self.model.get_channel(block_no);
+        let plane = self.model.get_plane(block_no);
+        (channel as usize, (channel * self.model.plane_num + plane) as usize)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        let model = TimingModel::new();
+        let transfer = model.transfer_time(4096);
+        let mut clock = VirtualClock::new(model);
+
+        clock.read_page(0);
+        assert_eq!(clock.now, model.page_read + transfer);
+        clock.program_page(0);
+        assert_eq!(clock.now, model.page_read + transfer * 2);
+        assert_eq!(clock.elapsed(), model.page_read + transfer * 2 + model.page_program);
+        clock.erase_block(0);
+        assert_eq!(clock.elapsed(), model.page_read + transfer * 2 + model.page_program + model.block_erase);
+        assert_eq!(clock.read_num, 1);
+        assert_eq!(clock.program_num, 1);
+        assert_eq!(clock.erase_num, 1);
+
+        clock.reset();
+        assert_eq!(clock.elapsed(), 0);
+        assert_eq!(clock.throughput(), 0);
+    }
+
+    #[test]
+    fn parallel() {
+        let mut model = TimingModel::new();
+        let mut serial = VirtualClock::new(model);
+        for _ in 0..16 {
+            serial.program_page(0);
+            serial.program_page(1);
+        }
+        model.channel_num = 2;
+        let mut channel = VirtualClock::new(model);
+        for _ in 0..16 {
+            channel.program_page(0);
+            channel.program_page(1);
+        }
+        model.channel_num = 1;
+        model.plane_num = 2;
+        let mut plane = VirtualClock::new(model);
+        for _ in 0..16 {
+            plane.program_page(0);
+            plane.program_page(1);
+        }
+        assert!(channel.elapsed() < serial.elapsed());
+        assert!(plane.elapsed() < serial.elapsed());
+        assert!(channel.throughput() > serial.throughput());
+        assert_eq!(model.get_channel(3), 0);
+        assert_eq!(model.get_plane(3), 1);
+    }
+}
@@ -3,6 +3,37 @@ use std::sync::Arc;
 use crate::inode::inode_manager;
 use crate::common::file_table;
 use crate::inode::inode;
+use crate::inode::mode;
+
+// 进程的凭据，uid为0时为root
+#[derive(Clone, PartialEq, Debug)]
+pub struct Cred {
+    pub uid: u32,
+    pub gid: u16,
+    pub groups: Vec<u16>,   // 附加组
+}
+
+impl Cred {
+    pub fn new(uid: u32, gid: u16) -> Cred {
+        Cred {
+            uid,
+            gid,
+            groups: vec![],
+        }
+    }
+
+    pub fn root() -> Cred {
+        Cred::new(0, 0)
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.uid == 0
+    }
+
+    pub fn in_group(&self, gid: u16) -> bool {
+        self.gid == gid || self.groups.contains(&gid)
+    }
+}
 
 // Per-process state
 pub struct Proc {
@@ -11,6 +42,8 @@ pub struct Proc {
     pub max_file: u32,
     pub file_table: file_table::FileTable,
     pub inode_manager: inode_manager::InodeManager,
+    pub cred: Cred,
+    pub umask: u16,
 }
 
 pub fn my_proc() -> Proc {
@@ -24,5 +57,75 @@ pub fn my_proc() -> Proc {
         max_file: 16,
         file_table: file_table::FileTable::new(),
         inode_manager: inode_manager::InodeManager::new(),
+        cred: Cred::root(),
+        umask: 0o022,
     }
-}
\ No newline at end of file
+}
+
+impl Proc {
+    // 返回原来的umask
+    pub fn set_umask(&mut self, umask: u16) -> u16 {
+        let o_umask = self.umask;
+        self.umask = umask & 0o777;
+        o_umask
+    }
+
+    // 分配新的Inode，权限去掉umask中的位，所有者为当前进程
+    pub fn create_inode(&mut self, file_type: inode::InodeFileType, perm: u16) -> Option<inode_manager::InodeLink> {
+        let link = self.inode_manager.i_alloc()?;
+        let mut stat = link.borrow().get_stat();
+        stat.file_type = file_type;
+        stat.mode = mode::make_mode(file_type, perm & !self.umask);
+        stat.uid = self.cred.uid;
+        stat.gid = self.cred.gid;
+        link.borrow_mut().modify_stat(stat);
+        Some(link)
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::file;
+    use crate::core::error::FsError;
+
+    #[test]
+    fn permission() {
+        let mut proc = my_proc();
+        proc.inode_manager.mount().unwrap();
+        assert_eq!(proc.set_umask(0o027), 0o022);
+        proc.cred = Cred::new(100, 10);
+        let link = proc.create_inode(inode::InodeFileType::File, 0o4777).unwrap();
+        let stat = link.borrow().get_stat();
+        assert_eq!((stat.mode, stat.uid, stat.gid), (mode::S_IFREG | 0o4750, 100, 10));
+
+        // 所有者可读写，同组只读，其他用户无权限
+        let mut other = Cred::new(200, 20);
+        assert!(link.borrow().permission(&proc.cred, mode::R_OK | mode::W_OK));
+        assert!(!link.borrow().permission(&other, mode::R_OK));
+        other.groups.push(10);
+        assert!(link.borrow().permission(&other, mode::R_OK));
+        assert!(!link.borrow().permission(&other, mode::W_OK));
+        let mut file = file::File::new();
+        assert_eq!(file.file_open(link.clone(), &other, true, true), -13);
+        assert_eq!(file.file_open(link.clone(), &other, true, false), 0);
+        assert_eq!(file.file_write(1, &vec![1]), -1);
+
+        // 只有所有者与root可以chmod、chown，非root修改所有者后清除setuid
+        assert_eq!(link.borrow_mut().chmod(&other, 0o777), Err(FsError::NotPermitted));
+        assert_eq!(link.borrow_mut().chown(&proc.cred, Some(200), None), Err(FsError::NotPermitted));
+        assert_eq!(link.borrow_mut().chown(&proc.cred, None, Some(20)), Err(FsError::NotPermitted));
+        proc.cred.groups.push(20);
+        link.borrow_mut().chown(&proc.cred, None, Some(20)).unwrap();
+        assert_eq!((link.borrow().gid, link.borrow().mode), (20, mode::S_IFREG | 0o750));
+        link.borrow_mut().chown(&Cred::root(), Some(200), Some(10)).unwrap();
+        link.borrow_mut().chmod(&other, 0o2700).unwrap();
+        assert_eq!(link.borrow().mode, mode::S_IFREG | 0o2700);
+        other.groups.clear();
+        link.borrow_mut().chmod(&other, 0o2700).unwrap();
+        assert_eq!(link.borrow().mode, mode::S_IFREG | 0o700);
+        // root不受权限位限制，但执行需要有x权限
+        link.borrow_mut().chmod(&Cred::root(), 0o600).unwrap();
+        assert!(link.borrow().permission(&Cred::root(), mode::R_OK | mode::W_OK));
+        assert!(!link.borrow().permission(&Cred::root(), mode::X_OK));
+    }
+}
@@ -0,0 +1,149 @@
+// 后台GC的运行状态
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BackgroundGCState {
+    Stopped,
+    Idle,
+    Running,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct BackgroundGCStat {
+    pub wake_num: u64,   // 空闲Block低于低水位被唤醒的次数
+    pub round_num: u64,  // 回收的Block数量
+    pub step_num: u64,   // 增量GC执行的步数
+    pub move_num: u64,   // 搬移的Page数量
+    pub yield_num: u64,  // 未到高水位但用完本次配额，让出给前台IO的次数
+    pub wear_num: u64,   // 静态磨损均衡搬移的Block数量
+}
+
+impl BackgroundGCStat {
+    pub fn new() -> BackgroundGCStat {
+        BackgroundGCStat {
+            wake_num: 0,
+            round_num: 0,
+            step_num: 0,
+            move_num: 0,
+            yield_num: 0,
+            wear_num: 0,
+        }
+    }
+}
+
+// 只维护水位与状态，实际回收由上层空闲时通过InodeManager::idle调用CoreManager::background_gc执行
+// 前台的读写与删除不会触发后台回收，mount时启动，unmount时停止
+// 空闲Block数量低于low_watermark时唤醒，回收到high_watermark后休眠
+// 每次最多执行budget步增量GC，避免长时间阻塞前台IO
+pub struct BackgroundGC {
+    state: BackgroundGCState,
+    low_watermark: u32,
+    high_watermark: u32,
+    budget: u32,
+    stat: BackgroundGCStat,
+}
+
+impl BackgroundGC {
+    pub fn new(low_watermark: u32, high_watermark: u32, budget: u32) -> BackgroundGC {
+        if low_watermark > high_watermark {
+            panic!("BackgroundGC: low watermark above high watermark");
+        }
+        BackgroundGC {
+            state: BackgroundGCState::Stopped,
+            low_watermark,
+            high_watermark,
+            budget,
+            stat: BackgroundGCStat::new(),
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.state = BackgroundGCState::Idle;
+    }
+
+    pub fn stop(&mut self) {
+        self.state = BackgroundGCState::Stopped;
+    }
+
+    pub fn get_state(&self) -> BackgroundGCState {
+        self.state
+    }
+
+    pub fn get_budget(&self) -> u32 {
+        self.budget
+    }
+
+    // 根据当前空闲Block数量判断本次是否需要回收
+    pub fn wake(&mut self, free_num: u32) -> bool {
+        match self.state {
+            BackgroundGCState::Stopped => false,
+            BackgroundGCState::Idle => {
+                if free_num < self.low_watermark {
+                    self.state = BackgroundGCState::Running;
+                    self.stat.wake_num += 1;
+                    return true;
+                }
+                false
+            }
+            BackgroundGCState::Running => {
+                if free_num >= self.high_watermark {
+                    self.state = BackgroundGCState::Idle;
+                    return false;
+                }
+                true
+            }
+        }
+    }
+
+    // 到达高水位或没有可回收的Block时休眠
+    pub fn sleep(&mut self) {
+        if self.state == BackgroundGCState::Running {
+            self.state = BackgroundGCState::Idle;
+        }
+    }
+
+    pub fn is_satisfied(&self, free_num: u32) -> bool {
+        free_num >= self.high_watermark
+    }
+
+    pub fn record_step(&mut self, move_num: u32) {
+        self.stat.step_num += 1;
+        self.stat.move_num += move_num as u64;
+    }
+
+    pub fn record_round(&mut self) {
+        self.stat.round_num += 1;
+    }
+
+    pub fn record_yield(&mut self) {
+        self.stat.yield_num += 1;
+    }
+
+    pub fn record_wear_leveling(&mut self) {
+        self.stat.wear_num += 1;
+    }
+
+    pub fn get_stat(&self) -> BackgroundGCStat {
+        self.stat
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        let mut gc = BackgroundGC::new(2, 4, 1);
+        assert_eq!(gc.wake(0), false);
+        gc.start();
+        assert_eq!(gc.wake(3), false);
+        assert_eq!(gc.wake(1), true);
+        assert_eq!(gc.get_state(), BackgroundGCState::Running);
+        // 运行中直到高水位才休眠
+        assert_eq!(gc.wake(3), true);
+        assert_eq!(gc.wake(4), false);
+        assert_eq!(gc.get_state(), BackgroundGCState::Idle);
+        assert_eq!(gc.get_stat().wake_num, 1);
+        gc.stop();
+        assert_eq!(gc.wake(0), false);
+    }
+}
@@ -1,9 +1,50 @@
-#[derive(Copy, Clone)]
+use crate::gc::gc_manager::PageUsedStatus;
+
+// Clean的Page为free，Dirty为invalid，Busy为valid
+// Page只能在write_pointer处依次写入，擦除后write_pointer回到Block开头
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct BlockInfo {
     pub size: u32,
     pub block_no: u32,
-    pub reserved_size: u32,
-    pub reserved_offset: u32,
+    pub valid_size: u32,
+    pub invalid_size: u32,
+    pub free_size: u32,
+    pub write_pointer: u32,
+    pub erase_count: u32,
+    pub modify_time: u64,    // 最近一次写入的逻辑时间
+}
+
+impl BlockInfo {
+    pub fn new(block_no: u32) -> BlockInfo {
+        BlockInfo {
+            size: 128,
+            block_no,
+            valid_size: 0,
+            invalid_size: 0,
+            free_size: 128,
+            write_pointer: 0,
+            erase_count: 0,
+            modify_time: 0,
+        }
+    }
+
+    // write_pointer之后还能写入的Page数量
+    pub fn get_reserved_size(&self) -> u32 {
+        self.size - self.write_pointer
+    }
+
+    pub fn is_free(&self) -> bool {
+        self.free_size == self.size
+    }
+
+    pub fn check(&self) {
+        if self.valid_size + self.invalid_size + self.free_size != self.size {
+            panic!("BlockTable: block {} page count not matched", self.block_no);
+        }
+        if self.write_pointer > self.size || self.free_size < self.get_reserved_size() {
+            panic!("BlockTable: block {} write pointer out of range", self.block_no);
+        }
+    }
 }
 
 pub struct BlockTable {
@@ -15,13 +56,7 @@ impl BlockTable {
     pub fn new(size: u32) -> BlockTable {
         let mut table = vec![];
         for i in 0..size {
-            let block = BlockInfo {
-                size: 128,
-                block_no: i,
-                reserved_size: 128,
-                reserved_offset: 0,
-            };
-            table.push(block);
+            table.push(BlockInfo::new(i));
         }
         BlockTable {
             size,
@@ -29,22 +64,47 @@ impl BlockTable {
         }
     }
 
-    pub fn clean_page(&mut self, address: u32) {
+    // Page状态改变时更新所在Block的统计
+    pub fn set_page(&mut self, address: u32, o_status: PageUsedStatus, status: PageUsedStatus, time: u64) {
         let block_no = address / 128;
         if block_no > self.size - 1 {
-            panic!("BlockTable: clean at too big address");
+            panic!("BlockTable: set at too big address");
+        }
+        let block = &mut self.table[block_no as usize];
+        match o_status {
+            PageUsedStatus::Clean => block.free_size -= 1,
+            PageUsedStatus::Dirty => block.invalid_size -= 1,
+            PageUsedStatus::Busy(_) => block.valid_size -= 1,
+        }
+        match status {
+            PageUsedStatus::Clean => block.free_size += 1,
+            PageUsedStatus::Dirty => block.invalid_size += 1,
+            PageUsedStatus::Busy(_) => block.valid_size += 1,
+        }
+        if o_status == PageUsedStatus::Clean && status != PageUsedStatus::Clean {
+            block.write_pointer = block.write_pointer.max(address % 128 + 1);
+            block.modify_time = time;
+        }
+        if let (PageUsedStatus::Dirty, PageUsedStatus::Busy(_)) = (o_status, status) {
+            block.modify_time = time;
+        }
+        if block.is_free() {
+            block.write_pointer = 0;
         }
-        self.table[block_no as usize].reserved_size = 128;
-        self.table[block_no as usize].reserved_offset = 0;
+        block.check();
     }
 
-    pub fn use_page(&mut self, address: u32) {
-        let block_no = address / 128;
+    // 擦除前Block中的Page必须都已经Clean
+    pub fn erase_block(&mut self, block_no: u32) {
         if block_no > self.size - 1 {
-            panic!("BlockTable: use at too big address");
+            panic!("BlockTable: erase at too big block");
+        }
+        let block = &mut self.table[block_no as usize];
+        if !block.is_free() {
+            panic!("BlockTable: erase block {} with used page", block_no);
         }
-        self.table[block_no as usize].reserved_offset += 1;
-        self.table[block_no as usize].reserved_size -= 1;
+        block.write_pointer = 0;
+        block.erase_count += 1;
     }
 }
 
@@ -56,21 +116,35 @@ mod test {
     fn basics() {
         let mut table = BlockTable::new(32);
 
-        table.use_page(0);
-        table.use_page(1);
-        table.use_page(2);
-        table.use_page(3);
+        for address in 0..4 {
+            table.set_page(address, PageUsedStatus::Clean, PageUsedStatus::Dirty, address as u64);
+            table.set_page(address, PageUsedStatus::Dirty, PageUsedStatus::Busy(1), address as u64);
+        }
 
         assert_eq!(table.table[0].block_no, 0);
-        assert_eq!(table.table[0].reserved_offset, 4);
-        assert_eq!(table.table[0].reserved_size, 124);
+        assert_eq!(table.table[0].write_pointer, 4);
+        assert_eq!(table.table[0].get_reserved_size(), 124);
+        assert_eq!((table.table[0].valid_size, table.table[0].free_size), (4, 124));
+        assert_eq!(table.table[0].modify_time, 3);
 
-        table.clean_page(0);
-        table.clean_page(1);
-        table.clean_page(2);
-        table.clean_page(3);
+        table.set_page(1, PageUsedStatus::Busy(1), PageUsedStatus::Dirty, 10);
+        assert_eq!((table.table[0].valid_size, table.table[0].invalid_size), (3, 1));
+        // 跳过的Page仍然是free，但不能再写入
+        table.set_page(10, PageUsedStatus::Clean, PageUsedStatus::Busy(2), 11);
+        assert_eq!((table.table[0].write_pointer, table.table[0].free_size), (11, 123));
 
-        assert_eq!(table.table[0].reserved_offset, 0);
-        assert_eq!(table.table[0].reserved_size, 128);
+        for address in 0..11 {
+            let o_status = match address {
+                1 => PageUsedStatus::Dirty,
+                0 | 2 | 3 => PageUsedStatus::Busy(1),
+                10 => PageUsedStatus::Busy(2),
+                _ => PageUsedStatus::Clean,
+            };
+            table.set_page(address, o_status, PageUsedStatus::Clean, 12);
+        }
+        assert_eq!(table.table[0].write_pointer, 0);
+        table.erase_block(0);
+        assert_eq!(table.table[0].erase_count, 1);
+        assert_eq!(table.table[0].get_reserved_size(), 128);
     }
-}
\ No newline at end of file
+}
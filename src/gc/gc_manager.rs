@@ -1,6 +1,8 @@
 use crate::gc::gc_event;
 use crate::gc::main_table;
 use crate::gc::block_table;
+use crate::gc::victim;
+use crate::gc::temperature::{self, Temperature};
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum PageUsedStatus {
@@ -9,86 +11,280 @@ pub enum PageUsedStatus {
     Busy(u32),
 }
 
+// 以Page为单位，available为扣除保留空间后还能写入的有效数据量
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SpaceStat {
+    pub total: u32,
+    pub valid: u32,
+    pub invalid: u32,
+    pub free: u32,
+    pub reserved: u32,
+    pub available: u32,
+}
+
 pub struct GCManager {
     main_table: main_table::MainTable,
     block_table: block_table::BlockTable,
+    selector: Box<dyn victim::VictimSelector>,
+    time: u64,    // 逻辑时间，每写入一个Page加一
+    heads: [Option<u32>; temperature::TEMPERATURE_NUM],   // 各温度正在写入的Block
+    reserve: u32,   // 保留给GC的Block数量，不计入可用空间
+    victim: Option<u32>,   // 正在增量回收的Block
+    step_size: u32,        // 增量GC每步最多搬移的Page数量
 }
 
 impl GCManager {
-    pub fn new() -> GCManager {
+    // size为Main Region的Block数量
+    pub fn new(size: u32) -> GCManager {
         GCManager {
             main_table: main_table::MainTable::new(),
-            block_table: block_table::BlockTable::new(4096 * 8 * 128),
+            block_table: block_table::BlockTable::new(size),
+            selector: victim::new_selector(victim::VictimPolicy::Greedy),
+            time: 0,
+            heads: [None; temperature::TEMPERATURE_NUM],
+            reserve: 0,
+            victim: None,
+            step_size: 128,
         }
     }
 
-    pub fn find_next_pos_to_write(&self, size: u32) -> Option<u32> {
-        for block in self.block_table.table.iter() {
-            if block.reserved_size >= size {
-                let offset = block.reserved_offset;
-                return Some((block.block_no * 128) as u32 + offset);
+    pub fn set_reserve(&mut self, reserve: u32) {
+        if reserve >= self.block_table.size {
+            panic!("GCManager: reserve too many blocks");
+        }
+        self.reserve = reserve;
+    }
+
+    pub fn set_victim_policy(&mut self, policy: victim::VictimPolicy) {
+        self.selector = victim::new_selector(policy);
+    }
+
+    pub fn get_block_num(&self) -> u32 {
+        self.block_table.size
+    }
+
+    pub fn find_next_pos_to_write(&mut self, size: u32, temperature: Temperature) -> Option<u32> {
+        self.find_block_to_write(size, temperature, None)
+    }
+
+    // 连续的写入最多占用一个Block，返回(address, len)，len不超过size
+    // 没有Block能容纳时写入剩余空间最多的Block，len为其剩余的空间
+    pub fn find_next_range_to_write(&mut self, size: u32, temperature: Temperature) -> Option<(u32, u32)> {
+        let len = size.min(128);
+        if let Some(address) = self.find_block_to_write(len, temperature, None) {
+            return Some((address, len));
+        }
+        let victim = self.victim;
+        let block = self.block_table.table.iter()
+            .filter(|block| !block.is_free() && block.get_reserved_size() > 0 && Some(block.block_no) != victim)
+            .max_by_key(|block| block.get_reserved_size())?;
+        Some((block.block_no * 128 + block.write_pointer, block.get_reserved_size()))
+    }
+
+    pub fn find_next_pos_to_write_except(&mut self, size: u32, temperature: Temperature, block_no: u32) -> Option<u32> {
+        self.find_block_to_write(size, temperature, Some(block_no))
+    }
+
+    // 写入对应温度正在使用的Block，空间不足时优先换到擦除次数最少的空闲Block
+    // 没有空闲Block时使用其他温度以外剩余空间足够的Block，仍然没有时才与其他温度混写
+    // 正在增量回收的Block不参与分配
+    // 空闲Block不多于保留数量时，只有GC与磨损均衡(指定except)可以开始写入新的空闲Block
+    fn find_block_to_write(&mut self, size: u32, temperature: Temperature, except: Option<u32>) -> Option<u32> {
+        let index = temperature as usize;
+        let victim = self.victim;
+        if let Some(block_no) = self.heads[index] {
+            let block = self.block_table.table[block_no as usize];
+            if Some(block_no) != except && Some(block_no) != victim && block.get_reserved_size() >= size {
+                return Some(block_no * 128 + block.write_pointer);
+            }
+        }
+        let heads = self.heads;
+        let use_free = except.is_some() || self.get_free_block_num() > self.reserve;
+        let candidates = || self.block_table.table.iter()
+            .filter(|block| block.get_reserved_size() >= size && Some(block.block_no) != except && Some(block.block_no) != victim)
+            .filter(|block| use_free || !block.is_free());
+        let block = candidates().filter(|block| block.is_free() && !heads.contains(&Some(block.block_no)))
+            .min_by_key(|block| block.erase_count)
+            .or_else(|| candidates().find(|block| !heads.contains(&Some(block.block_no))));
+        match block {
+            Some(block) => {
+                let address = block.block_no * 128 + block.write_pointer;
+                self.heads[index] = Some(block.block_no);
+                Some(address)
+            }
+            None => {
+                let block = candidates().next()?;
+                Some(block.block_no * 128 + block.write_pointer)
             }
         }
+    }
+
+    // 擦除次数最少且不是写入位置的空闲Block
+    pub fn find_free_block(&self) -> Option<u32> {
+        self.block_table.table.iter()
+            .filter(|block| block.is_free() && !self.heads.contains(&Some(block.block_no)))
+            .min_by_key(|block| block.erase_count)
+            .map(|block| block.block_no)
+    }
+
+    // 静态磨损均衡：擦除次数差距超过threshold时，选择保存数据且擦除次数最少的Block
+    // 其中的数据很少被修改，搬走后该Block可以重新参与分配
+    pub fn select_wear_victim(&self, threshold: u32) -> Option<u32> {
+        let max_count = self.block_table.table.iter().map(|block| block.erase_count).max()?;
+        let block = self.block_table.table.iter()
+            .filter(|block| block.valid_size > 0 && !self.heads.contains(&Some(block.block_no)))
+            .min_by_key(|block| block.erase_count)?;
+        if max_count - block.erase_count > threshold {
+            return Some(block.block_no);
+        }
         None
     }
 
-    pub fn find_next_pos_to_write_except(&self, size: u32, block_no: u32) -> Option<u32> {
-        for block in self.block_table.table.iter() {
-            if block.reserved_size >= size && block.block_no != block_no {
-                let offset = block.reserved_offset;
-                return Some((block.block_no * 128) as u32 + offset);
+    // 把磨损均衡选出的Block作为下一个增量回收的对象，已有回收中的Block时不替换
+    pub fn set_wear_victim(&mut self, threshold: u32) -> bool {
+        if self.victim.is_some() {
+            return false;
+        }
+        match self.select_wear_victim(threshold) {
+            Some(block_no) => {
+                self.set_victim(block_no);
+                true
             }
+            None => false,
         }
-        None
     }
 
-    pub fn generate_gc_event(&mut self) -> gc_event::GCEventGroup {
+    pub fn get_head(&self, temperature: Temperature) -> Option<u32> {
+        self.heads[temperature as usize]
+    }
+
+    // 未写入任何Page的Block数量
+    pub fn get_free_block_num(&self) -> u32 {
+        self.block_table.table.iter().filter(|block| block.is_free()).count() as u32
+    }
+
+    // 没有可回收的Block时退化为选择剩余空间最小的Block
+    pub fn select_victim(&self) -> u32 {
+        if let Some(block_no) = self.selector.select(&self.block_table, self.time) {
+            return block_no;
+        }
         let mut gc_block = self.block_table.table[0];
         for block in self.block_table.table.iter() {
-            if block.reserved_size < gc_block.reserved_size {
+            if block.write_pointer > gc_block.write_pointer {
                 gc_block = *block;
             }
         }
+        gc_block.block_no
+    }
+
+    // Block中存在已写入但不再有效的Page时，回收才能腾出空间
+    pub fn is_reclaimable(&self, block_no: u32) -> bool {
+        self.block_table.table[block_no as usize].invalid_size > 0
+    }
+
+    pub fn generate_gc_event(&mut self) -> Option<gc_event::GCEventGroup> {
+        let block_no = self.select_victim();
+        self.generate_gc_event_for(block_no)
+    }
+
+    // 搬走指定Block中的有效Page并擦除，没有位置容纳有效Page时返回None
+    pub fn generate_gc_event_for(&mut self, block_no: u32) -> Option<gc_event::GCEventGroup> {
+        let used_entries = self.collect_used_entries(block_no, 128);
+        let mut gc_group = self.generate_move_event(block_no, used_entries)?;
+        let event = gc_event::EraseGCEvent {
+            index: gc_group.events.len() as u32,
+            block_no,
+        };
+        gc_group.events.push(gc_event::GCEvent::Erase(event));
+        Some(gc_group)
+    }
+
+    // 增量GC：每次最多搬移step_size个有效Page，回收对象可以跨多次调用保持部分搬移的状态
+    // 最后一步搬完剩余的有效Page后擦除，没有可回收的Block或没有位置容纳有效Page时返回None
+    // 磨损均衡选出的Block中可能没有无效的Page，同样逐步搬完
+    pub fn generate_gc_step(&mut self) -> Option<gc_event::GCEventGroup> {
+        let block_no = self.get_gc_victim();
+        if self.victim != Some(block_no) {
+            if !self.is_reclaimable(block_no) {
+                return None;
+            }
+            self.set_victim(block_no);
+        }
+        let used_entries = self.collect_used_entries(block_no, self.step_size);
+        let move_num: u32 = used_entries.iter().map(|entry| entry.1).sum();
+        let mut gc_group = self.generate_move_event(block_no, used_entries)?;
+        if move_num == self.block_table.table[block_no as usize].valid_size {
+            let event = gc_event::EraseGCEvent {
+                index: gc_group.events.len() as u32,
+                block_no,
+            };
+            gc_group.events.push(gc_event::GCEvent::Erase(event));
+        }
+        Some(gc_group)
+    }
+
+    // 回收中的Block不再接收新的写入
+    fn set_victim(&mut self, block_no: u32) {
+        self.victim = Some(block_no);
+        for head in self.heads.iter_mut() {
+            if *head == Some(block_no) {
+                *head = None;
+            }
+        }
+    }
+
+    // 正在增量回收的Block，没有时重新选择
+    pub fn get_gc_victim(&self) -> u32 {
+        match self.victim {
+            Some(block_no) => block_no,
+            None => self.select_victim(),
+        }
+    }
+
+    pub fn set_step_size(&mut self, step_size: u32) {
+        if step_size == 0 {
+            panic!("GCManager: set zero step size");
+        }
+        self.step_size = step_size;
+    }
+
+    // 按地址顺序收集Block中连续属于同一文件的有效Page，返回(ino, size, o_address, d_address)
+    // 有效Page总数超过limit时截断
+    fn collect_used_entries(&self, block_no: u32, limit: u32) -> Vec<(u32, u32, u32, u32)> {
         let mut used_entries: Vec<(u32, u32, u32, u32)> = vec![];
-        let block_no = gc_block.block_no;
-        let start_index = block_no * 128;
-        let end_index = (block_no + 1) * 128;
-        let mut size = 0;
-        let mut last_entry: Option<(u32, u32, u32, u32)> = None;
-        for address in start_index..end_index {
-            let status = self.main_table.get_page(address);
-            match status {
+        let mut total = 0;
+        for address in block_no * 128..(block_no + 1) * 128 {
+            if total == limit {
+                break;
+            }
+            match self.main_table.get_page(address) {
                 PageUsedStatus::Busy(ino) => {
-                    if last_entry.is_some() {
-                        if last_entry.unwrap().0 == ino {
-                            size += 1;
-                        } else {
-                            last_entry.as_mut().unwrap().1 = size;
-                            used_entries.push(last_entry.unwrap());
-                            last_entry = Some((ino, 0, address, 0));
-                        }
-                    } else {
-                        last_entry = Some((ino, 0, address, 0));
-                        size = 1;
-                    }
-                }
-                _ => {
-                    if last_entry.is_some() {
-                        last_entry.as_mut().unwrap().1 = size;
-                        used_entries.push(last_entry.unwrap());
-                        last_entry = None;
-                        size = 0;
+                    match used_entries.last_mut() {
+                        Some(entry) if entry.0 == ino && entry.2 + entry.1 == address => entry.1 += 1,
+                        _ => used_entries.push((ino, 1, address, 0)),
                     }
+                    total += 1;
                 }
+                _ => (),
             }
         }
-        if last_entry.is_some() {
-            last_entry.as_mut().unwrap().1 = size;
-            used_entries.push(last_entry.unwrap());
-        }
+        used_entries
+    }
+
+    fn generate_move_event(&mut self, block_no: u32, used_entries: Vec<(u32, u32, u32, u32)>) -> Option<gc_event::GCEventGroup> {
+        let mut used_entries = used_entries;
+        // 同一Block中的有效Page依次写入目标位置，避免多个Move的目标重叠
+        let total: u32 = used_entries.iter().map(|entry| entry.1).sum();
+        // 需要GC搬移的数据很少被修改，写入Cold
+        let mut next = self.find_next_pos_to_write_except(total, Temperature::Cold, block_no);
         for entry in used_entries.iter_mut() {
-            let d_address = self.find_next_pos_to_write_except(entry.1, block_no);
-            entry.3 = d_address.unwrap();
+            match next {
+                Some(d_address) => {
+                    entry.3 = d_address;
+                    next = Some(d_address + entry.1);
+                }
+                None => entry.3 = self.find_next_pos_to_write_except(entry.1, Temperature::Cold, block_no)?,
+            }
         }
         let mut gc_group = gc_event::GCEventGroup::new();
         let mut index = 0;
@@ -103,33 +299,71 @@ impl GCManager {
             gc_group.events.push(gc_event::GCEvent::Move(event));
             index += 1;
         }
-        let event = gc_event::EraseGCEvent {
-            index,
-            block_no,
-        };
-        gc_group.events.push(gc_event::GCEvent::Erase(event));
-        gc_group
+        Some(gc_group)
     }
 }
 
 // 提供MainTable的接口
 impl GCManager {
     pub fn set_table(&mut self, address: u32, status: PageUsedStatus) {
-        match status {
-            PageUsedStatus::Busy(_) => {
-                self.block_table.use_page(address);
-            }
-            PageUsedStatus::Clean => {
-                self.block_table.clean_page(address);
-            }
-            _ => ()
+        let o_status = self.main_table.get_page(address);
+        if o_status == PageUsedStatus::Clean && status != PageUsedStatus::Clean {
+            self.time += 1;
         }
+        self.block_table.set_page(address, o_status, status, self.time);
         self.main_table.set_page(address, status);
     }
 
     pub fn get_table(&self, address: u32) -> PageUsedStatus {
         self.main_table.get_page(address)
     }
+
+    pub fn erase_block(&mut self, block_no: u32) {
+        self.block_table.erase_block(block_no);
+        if self.victim == Some(block_no) {
+            self.victim = None;
+        }
+        for head in self.heads.iter_mut() {
+            if *head == Some(block_no) {
+                *head = None;
+            }
+        }
+    }
+
+    // 挂载或交换物理Block后与实际的擦除次数同步
+    pub fn set_erase_count(&mut self, block_no: u32, count: u32) {
+        self.block_table.table[block_no as usize].erase_count = count;
+    }
+
+    pub fn get_block_info(&self, block_no: u32) -> block_table::BlockInfo {
+        self.block_table.table[block_no as usize]
+    }
+
+    // 无效的Page都可以通过GC回收，只有有效数据与保留空间不可写入
+    pub fn get_space_stat(&self) -> SpaceStat {
+        let (valid, invalid, free) = self.get_page_stat();
+        let total = self.block_table.size * 128;
+        let reserved = self.reserve * 128;
+        SpaceStat {
+            total,
+            valid,
+            invalid,
+            free,
+            reserved,
+            available: (total - reserved).saturating_sub(valid),
+        }
+    }
+
+    // 返回整个Main Region中(valid, invalid, free)的Page数量
+    pub fn get_page_stat(&self) -> (u32, u32, u32) {
+        let mut stat = (0, 0, 0);
+        for block in self.block_table.table.iter() {
+            stat.0 += block.valid_size;
+            stat.1 += block.invalid_size;
+            stat.2 += block.free_size;
+        }
+        stat
+    }
 }
 
 
@@ -139,14 +373,14 @@ mod test {
 
     #[test]
     fn basics() {
-        let mut manager = GCManager::new();
+        let mut manager = GCManager::new(32);
 
         for address in 0..32 * 128 {
             manager.set_table(address, PageUsedStatus::Clean);
         }
 
-        assert_eq!(manager.find_next_pos_to_write(5), Some(0));
-        
+        assert_eq!(manager.find_next_pos_to_write(5, Temperature::Warm), Some(0));
+
         manager.set_table(0, PageUsedStatus::Busy(0));
         manager.set_table(1, PageUsedStatus::Busy(0));
         manager.set_table(2, PageUsedStatus::Busy(0));
@@ -154,10 +388,112 @@ mod test {
         manager.set_table(4, PageUsedStatus::Busy(0));
 
         assert_eq!(manager.get_table(0), PageUsedStatus::Busy(0));
-        assert_eq!(manager.find_next_pos_to_write(128), Some(128));
+        assert_eq!(manager.find_next_pos_to_write(5, Temperature::Warm), Some(5));
+        // 当前Block空间不足时换到新的Block，不同温度使用不同的Block
+        assert_eq!(manager.find_next_pos_to_write(128, Temperature::Warm), Some(128));
+        assert_eq!(manager.find_next_pos_to_write(5, Temperature::Hot), Some(256));
+        assert_eq!(manager.find_next_pos_to_write(5, Temperature::Warm), Some(128));
+        assert_eq!(manager.get_free_block_num(), 31);
+        assert_eq!(manager.is_reclaimable(0), false);
+        manager.set_table(4, PageUsedStatus::Dirty);
+        assert_eq!(manager.is_reclaimable(0), true);
+        assert_eq!(manager.get_page_stat(), (4, 1, 32 * 128 - 5));
+        assert_eq!(manager.get_block_info(0).write_pointer, 5);
+        manager.set_table(4, PageUsedStatus::Busy(0));
 
-        let event = manager.generate_gc_event();
-        assert_eq!(event.events[0], gc_event::GCEvent::Move(gc_event::MoveGCEvent{ index: 0, ino: 0, size: 5, o_address: 0, d_address: 128 }));
+        // 搬移的数据写入Cold对应的Block
+        let event = manager.generate_gc_event().unwrap();
+        assert_eq!(manager.get_head(Temperature::Cold), Some(3));
+        assert_eq!(event.events[0], gc_event::GCEvent::Move(gc_event::MoveGCEvent{ index: 0, ino: 0, size: 5, o_address: 0, d_address: 384 }));
         assert_eq!(event.events[1], gc_event::GCEvent::Erase(gc_event::EraseGCEvent{ index: 1, block_no: 0 }));
     }
+
+    #[test]
+    fn reserve() {
+        let mut manager = GCManager::new(4);
+        manager.set_reserve(2);
+        for (block_no, temperature) in [(0, Temperature::Warm), (1, Temperature::Hot)] {
+            assert_eq!(manager.find_next_pos_to_write(128, temperature), Some(block_no * 128));
+            for address in block_no * 128..(block_no + 1) * 128 {
+                manager.set_table(address, PageUsedStatus::Busy(1));
+            }
+        }
+        // 剩下的空闲Block保留给GC，前台写入不能使用
+        assert_eq!(manager.find_next_pos_to_write(1, Temperature::Warm), None);
+        assert_eq!(manager.find_next_pos_to_write_except(1, Temperature::Cold, 0), Some(256));
+    }
+
+    #[test]
+    fn range() {
+        let mut manager = GCManager::new(4);
+        manager.set_reserve(2);
+        assert_eq!(manager.find_next_range_to_write(300, Temperature::Warm), Some((0, 128)));
+        for address in 0..100 {
+            manager.set_table(address, PageUsedStatus::Busy(1));
+        }
+        assert_eq!(manager.find_next_range_to_write(172, Temperature::Warm), Some((128, 128)));
+        for address in 128..256 {
+            manager.set_table(address, PageUsedStatus::Busy(1));
+        }
+        // 没有可以开始写入的空闲Block时写满Block 0剩余的空间
+        assert_eq!(manager.find_next_range_to_write(44, Temperature::Warm), Some((100, 28)));
+    }
+
+    #[test]
+    fn incremental() {
+        let mut manager = GCManager::new(32);
+        manager.set_step_size(4);
+        assert_eq!(manager.find_next_pos_to_write(11, Temperature::Warm), Some(0));
+        for address in 0..10 {
+            manager.set_table(address, PageUsedStatus::Busy(1));
+        }
+        manager.set_table(10, PageUsedStatus::Dirty);
+        // 模拟搬移完成：原Page变为Dirty，目标Page变为Busy
+        let dispose = |manager: &mut GCManager, group: gc_event::GCEventGroup| {
+            for event in group.events {
+                if let gc_event::GCEvent::Move(event) = event {
+                    for i in 0..event.size {
+                        manager.set_table(event.o_address + i, PageUsedStatus::Dirty);
+                        manager.set_table(event.d_address + i, PageUsedStatus::Busy(event.ino));
+                    }
+                }
+            }
+        };
+
+        let group = manager.generate_gc_step().unwrap();
+        assert_eq!(group.events, vec![gc_event::GCEvent::Move(gc_event::MoveGCEvent{ index: 0, ino: 1, size: 4, o_address: 0, d_address: 128 })]);
+        dispose(&mut manager, group);
+        // 部分搬移的Block不再接收写入，下一步继续回收同一个Block
+        assert_eq!(manager.get_head(Temperature::Warm), None);
+        assert_eq!(manager.find_next_pos_to_write(1, Temperature::Warm), Some(256));
+        assert_eq!(manager.get_gc_victim(), 0);
+        let group = manager.generate_gc_step().unwrap();
+        assert_eq!(group.events, vec![gc_event::GCEvent::Move(gc_event::MoveGCEvent{ index: 0, ino: 1, size: 4, o_address: 4, d_address: 132 })]);
+        dispose(&mut manager, group);
+        let group = manager.generate_gc_step().unwrap();
+        assert_eq!(group.events[0], gc_event::GCEvent::Move(gc_event::MoveGCEvent{ index: 0, ino: 1, size: 2, o_address: 8, d_address: 136 }));
+        assert_eq!(group.events[1], gc_event::GCEvent::Erase(gc_event::EraseGCEvent{ index: 1, block_no: 0 }));
+        dispose(&mut manager, group);
+        for address in 0..11 {
+            manager.set_table(address, PageUsedStatus::Clean);
+        }
+        manager.erase_block(0);
+        assert!(manager.generate_gc_step().is_none());
+    }
+
+    #[test]
+    fn sparse() {
+        // 64GB Main Region，未设置的Page视为Clean
+        let mut manager = GCManager::new(128 * 1024);
+        assert_eq!(manager.get_block_num(), 128 * 1024);
+        assert_eq!(manager.get_table(128 * 1024 * 128 - 1), PageUsedStatus::Clean);
+
+        manager.set_table(0, PageUsedStatus::Busy(3));
+        manager.set_table(1, PageUsedStatus::Busy(3));
+        manager.set_table(2, PageUsedStatus::Busy(4));
+        let event = manager.generate_gc_event().unwrap();
+        assert_eq!(event.events[0], gc_event::GCEvent::Move(gc_event::MoveGCEvent{ index: 0, ino: 3, size: 2, o_address: 0, d_address: 128 }));
+        assert_eq!(event.events[1], gc_event::GCEvent::Move(gc_event::MoveGCEvent{ index: 1, ino: 4, size: 1, o_address: 2, d_address: 130 }));
+        assert_eq!(manager.main_table.table.len(), 3);
+    }
 }
\ No newline at end of file
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use crate::gc::gc_manager;
 
 // 内存中的disk全局信息
+// 只保存非Clean的Page，不存在的Page视为Clean
 pub struct MainTable {
     pub table: HashMap<u32, gc_manager::PageUsedStatus>,
 }
@@ -14,6 +15,10 @@ impl MainTable {
     }
 
     pub fn set_page(&mut self, address: u32, status: gc_manager::PageUsedStatus) {
+        if status == gc_manager::PageUsedStatus::Clean {
+            self.table.remove(&address);
+            return;
+        }
         if !self.table.contains_key(&address) {
             self.table.insert(address, status);
             return;
@@ -22,10 +27,10 @@ impl MainTable {
     }
 
     pub fn get_page(&self, address: u32) -> gc_manager::PageUsedStatus {
-        if !self.table.contains_key(&address) {
-            panic!("MainTable: get no that page");
+        match self.table.get(&address) {
+            Some(status) => *status,
+            None => gc_manager::PageUsedStatus::Clean,
         }
-        self.table.get(&address).unwrap().clone()
     }
 }
 
@@ -49,5 +54,10 @@ mod test {
 
         tabel.set_page(102, gc_manager::PageUsedStatus::Busy(21));
         assert_eq!(tabel.get_page(102), gc_manager::PageUsedStatus::Busy(21));
+
+        tabel.set_page(102, gc_manager::PageUsedStatus::Clean);
+        assert_eq!(tabel.get_page(102), gc_manager::PageUsedStatus::Clean);
+        assert_eq!(tabel.get_page(1000), gc_manager::PageUsedStatus::Clean);
+        assert_eq!(tabel.table.len(), 2);
     }
 }
\ No newline at end of file
@@ -1,4 +1,7 @@
 pub mod gc_event;
 pub mod gc_manager;
 pub mod main_table;
-pub mod block_table;
\ No newline at end of file
+pub mod block_table;
+pub mod background_gc;
+pub mod victim;
+pub mod temperature;
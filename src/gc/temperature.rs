@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+// 数据的冷热程度，每种温度各有一个正在写入的Block
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Temperature {
+    Hot,
+    Warm,
+    Cold,
+}
+
+pub const TEMPERATURE_NUM: usize = 3;
+
+// 文件被更新HOT_THRESHOLD次后视为热数据
+pub const HOT_THRESHOLD: u32 = 8;
+
+// 根据文件类型、更新频率与用户提示判断新写入数据的温度
+// GC搬移的数据直接写入Cold，不经过这里
+pub struct TemperatureTracker {
+    update_count: HashMap<u32, u32>,
+    hints: HashMap<u32, Temperature>,
+}
+
+impl TemperatureTracker {
+    pub fn new() -> TemperatureTracker {
+        TemperatureTracker {
+            update_count: HashMap::new(),
+            hints: HashMap::new(),
+        }
+    }
+
+    // 提示优先，目录内容经常修改视为Hot，其余按更新次数判断
+    pub fn classify(&self, ino: u32, is_dir: bool) -> Temperature {
+        if let Some(temperature) = self.hints.get(&ino) {
+            return *temperature;
+        }
+        if is_dir || self.get_update_count(ino) >= HOT_THRESHOLD {
+            return Temperature::Hot;
+        }
+        Temperature::Warm
+    }
+
+    pub fn record_update(&mut self, ino: u32) {
+        *self.update_count.entry(ino).or_insert(0) += 1;
+    }
+
+    pub fn get_update_count(&self, ino: u32) -> u32 {
+        self.update_count.get(&ino).copied().unwrap_or(0)
+    }
+
+    pub fn set_hint(&mut self, ino: u32, temperature: Temperature) {
+        self.hints.insert(ino, temperature);
+    }
+
+    pub fn remove(&mut self, ino: u32) {
+        self.update_count.remove(&ino);
+        self.hints.remove(&ino);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        let mut tracker = TemperatureTracker::new();
+        assert_eq!(tracker.classify(1, true), Temperature::Hot);
+        assert_eq!(tracker.classify(2, false), Temperature::Warm);
+        for _ in 0..HOT_THRESHOLD {
+            tracker.record_update(2);
+        }
+        assert_eq!(tracker.classify(2, false), Temperature::Hot);
+        tracker.set_hint(2, Temperature::Cold);
+        assert_eq!(tracker.classify(2, false), Temperature::Cold);
+        tracker.remove(2);
+        assert_eq!(tracker.classify(2, false), Temperature::Warm);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::gc::block_table;
+
+// GC选择回收Block的策略
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum VictimPolicy {
+    Greedy,
+    CostBenefit,
+    CAT,
+}
+
+// 只在存在无效Page的Block中选择，没有可回收的Block时返回None
+pub trait VictimSelector {
+    fn select(&self, table: &block_table::BlockTable, now: u64) -> Option<u32>;
+}
+
+pub fn new_selector(policy: VictimPolicy) -> Box<dyn VictimSelector> {
+    match policy {
+        VictimPolicy::Greedy => Box::new(GreedySelector {}),
+        VictimPolicy::CostBenefit => Box::new(CostBenefitSelector {}),
+        VictimPolicy::CAT => Box::new(CATSelector {}),
+    }
+}
+
+// 有效Page所占的比例
+fn utilization(block: &block_table::BlockInfo) -> f64 {
+    block.valid_size as f64 / block.size as f64
+}
+
+fn age(block: &block_table::BlockInfo, now: u64) -> f64 {
+    (now - block.modify_time.min(now) + 1) as f64
+}
+
+// 分数最小的Block作为回收对象，分数相同时选择编号小的
+fn select_min<F>(table: &block_table::BlockTable, score: F) -> Option<u32>
+where F: Fn(&block_table::BlockInfo) -> f64 {
+    let mut res: Option<(f64, u32)> = None;
+    for block in table.table.iter() {
+        if block.invalid_size == 0 {
+            continue;
+        }
+        let block_score = score(block);
+        if res.map_or(true, |(min, _)| block_score < min) {
+            res = Some((block_score, block.block_no));
+        }
+    }
+    res.map(|(_, block_no)| block_no)
+}
+
+// 选择有效Page最少的Block，搬移代价最小
+pub struct GreedySelector {}
+
+impl VictimSelector for GreedySelector {
+    fn select(&self, table: &block_table::BlockTable, _now: u64) -> Option<u32> {
+        select_min(table, |block| block.valid_size as f64)
+    }
+}
+
+// LFS的Cost-Benefit：收益为腾出的空间乘以数据年龄，代价为读入与写回有效Page
+// benefit / cost = (1 - u) * age / (1 + u)
+pub struct CostBenefitSelector {}
+
+impl VictimSelector for CostBenefitSelector {
+    fn select(&self, table: &block_table::BlockTable, now: u64) -> Option<u32> {
+        select_min(table, |block| {
+            let u = utilization(block);
+            -((1.0 - u) * age(block, now) / (1.0 + u))
+        })
+    }
+}
+
+// Cost-Age-Times：在Cost-Benefit的基础上考虑擦除次数，兼顾磨损均衡
+// score = u / (1 - u) * 1 / age * erase_count
+pub struct CATSelector {}
+
+impl VictimSelector for CATSelector {
+    fn select(&self, table: &block_table::BlockTable, now: u64) -> Option<u32> {
+        select_min(table, |block| {
+            let u = utilization(block);
+            u / (1.0 - u) / age(block, now) * (block.erase_count + 1) as f64
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn init_table() -> block_table::BlockTable {
+        let mut table = block_table::BlockTable::new(4);
+        // Block 0: 写满，64个有效Page，较新，擦除过多次
+        // Block 1: 写满，96个有效Page，很旧
+        // Block 2: 写满，64个有效Page，较新
+        for (block_no, valid_size, modify_time, erase_count) in [(0, 64, 90, 5), (1, 96, 0, 0), (2, 64, 90, 0)] {
+            let block = &mut table.table[block_no];
+            block.write_pointer = 128;
+            block.free_size = 0;
+            block.valid_size = valid_size;
+            block.invalid_size = 128 - valid_size;
+            block.modify_time = modify_time;
+            block.erase_count = erase_count;
+        }
+        table
+    }
+
+    #[test]
+    fn basics() {
+        let mut table = init_table();
+        assert_eq!(new_selector(VictimPolicy::Greedy).select(&table, 100), Some(0));
+        // 旧数据不太可能再被修改，优先回收
+        assert_eq!(new_selector(VictimPolicy::CostBenefit).select(&table, 100), Some(1));
+        assert_eq!(new_selector(VictimPolicy::CAT).select(&table, 100), Some(1));
+        // 擦除次数多的Block推迟回收
+        table.table[1].erase_count = 10;
+        assert_eq!(new_selector(VictimPolicy::CostBenefit).select(&table, 100), Some(1));
+        assert_eq!(new_selector(VictimPolicy::CAT).select(&table, 100), Some(2));
+        // 没有无效Page的Block不回收
+        let table = block_table::BlockTable::new(4);
+        assert_eq!(new_selector(VictimPolicy::Greedy).select(&table, 100), None);
+    }
+}
@@ -3,6 +3,12 @@ use std::cmp::{max, min};
 use crate::inode::inode_event;
 use crate::inode::inode_manager;
 use crate::compress::compress;
+use crate::core::error::FsError;
+use crate::core::options::AtimePolicy;
+use crate::util::time;
+use crate::inode::mode;
+use crate::inode::xattr;
+use crate::fake_proc::Cred;
 
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum InodeFileType {
@@ -15,18 +21,32 @@ pub enum InodeFileType {
 pub struct InodeStat {
     pub file_type: InodeFileType,
     pub ino: u32,
-    pub size: u32,
+    pub size: u64,
     pub uid: u32,
     pub gid: u16,
     pub ref_cnt: u8,
     pub n_link: u8,
+    pub generation: u32,
+    pub atime: u64,
+    pub mtime: u64,
+    pub ctime: u64,
+    pub btime: u64,
+    pub mode: u16,
+}
+
+// utimens中atime与mtime的取值，与UTIME_NOW、UTIME_OMIT对应
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SetTime {
+    Now,
+    Omit,
+    Time(u64),
 }
 
 #[derive(Copy, Clone)]
 pub struct InodeEntry {
     pub valid: bool,
-    pub offset: u32,
-    pub len: u32,           // 以Byte为单位
+    pub offset: u64,
+    pub len: u64,           // 以Byte为单位
     pub size: u32,          // 以Page为单位
     pub address: u32,
 }
@@ -35,13 +55,22 @@ pub struct Inode {
     pub valid: bool,
     pub file_type: InodeFileType,
     pub ino: u32,
-    pub size: u32,
+    pub size: u64,
     pub uid: u32,
     pub gid: u16,
     pub ref_cnt: u8,
     pub n_link: u8,
+    pub generation: u32,   // ino被复用时加一，用于判断引用是否失效
+    pub atime: u64,        // 以纳秒为单位，btime为创建时间
+    pub mtime: u64,
+    pub ctime: u64,
+    pub btime: u64,
+    pub mode: u16,         // 文件类型与权限位，类型与file_type一致
     pub lock: Mutex<bool>,
     pub data: Vec<InodeEntry>,
+    pub xattr: Vec<u8>,    // 编码后的扩展属性，较大时保存在xattr_entry中，此处为空
+    pub xattr_entry: Option<InodeEntry>,
+    pub dirty: bool,       // atime只在内存中更新，尚未写回
     pub core: Option<inode_manager::CoreLink>,
 }
 
@@ -54,61 +83,66 @@ impl Inode {
             uid: 0,
             gid: 0,
             n_link: 0,
+            generation: 0,
+            atime: 0,
+            mtime: 0,
+            ctime: 0,
+            btime: 0,
+            mode: 0,
             data: vec![],
+            xattr: vec![],
+            xattr_entry: None,
             valid: false,
             ref_cnt: 0,
             lock: Mutex::new(false),
+            dirty: false,
             core: None,
         }
     }
 
+    // 至多读取i32::MAX字节
     pub fn read_all(&mut self, buf: &mut Vec<u8>) -> i32 {
-        self.read(0, self.size, buf)
+        self.read(0, min(self.size, i32::MAX as u64) as u32, buf)
     }
 
-    pub fn read(&mut self, offset: u32, len: u32, buf: &mut Vec<u8>) -> i32 {
+    // 空洞部分读出全零，长度截断到i32::MAX以内，返回值不会溢出
+    pub fn read(&mut self, offset: u64, len: u32, buf: &mut Vec<u8>) -> i32 {
         buf.clear();
-        let mut len = len;
-        let mut count = 0;
-        let mut flag = false;
         if offset >= self.size {
             return -1;
         }
-        if offset + len > self.size {
-            len = self.size - offset;
-        }
+        let len = min(min(len, i32::MAX as u32) as u64, self.size - offset);
+        buf.resize(len as usize, 0);
         for entry in self.data.clone().iter() {
-            if entry.offset + entry.len < offset {
+            let start = max(offset, entry.offset);
+            let end = min(offset + len, entry.offset + entry.len);
+            if start >= end {
                 continue;
             }
-            let cur_count;
-            if !flag {
-                flag = true;
-                cur_count = min(len, entry.offset + entry.len - offset);
-            } else {
-                cur_count = min(len, entry.len);
-            }
-            let data = self.read_entry(&entry, 0, cur_count);
-            for byte in data.into_iter() {
-                buf.push(byte);
-            }
-            len -= cur_count;
-            count += cur_count as i32;
-            if len == 0 {
-                break;
-            }
+            let data = self.read_entry(&entry, start - entry.offset, end - entry.offset);
+            buf[(start - offset) as usize..(end - offset) as usize].copy_from_slice(&data);
+        }
+        self.touch_atime();
+        len as i32
+    }
+
+    // 文件数据不能到达扩展属性Extent使用的偏移
+    pub fn check_end(end: u64, len: u32) -> Result<(), FsError> {
+        match end.checked_add(len as u64) {
+            Some(end) if end <= xattr::EXTENT_OFFSET => Ok(()),
+            _ => Err(FsError::FileTooBig),
         }
-        count
     }
 
-    pub fn write(&mut self, offset: u32, len: u32, buf: &Vec<u8>) -> bool {
+    pub fn write(&mut self, offset: u64, len: u32, buf: &Vec<u8>) -> Result<(), FsError> {
+        Inode::check_end(offset, len)?;
         let mut event_group = inode_event::InodeEventGroup::new();
         event_group.inode = self.copy_inode();
         let mut index = 0;
         let mut flag = false;
         let new_entry = InodeEntry {
             offset,
-            len,
+            len: len as u64,
             valid: false,
             size: len,
             address: 0,
@@ -116,9 +150,6 @@ impl Inode {
         let mut second_entry = None;
         let mut second_o_entry = None;
         let mut second_index = 0;
-        if offset > self.size {
-            return false;
-        }
         for entry in self.data.iter() {
             if entry.offset + entry.len <= new_entry.offset {
                 index += 1;
@@ -126,8 +157,8 @@ impl Inode {
             } else if entry.offset >= new_entry.offset + new_entry.len {
                 continue
             } else {
-                let valid_prev = max(0, new_entry.offset as i32 - entry.offset as i32) as u32;
-                let valid_suffix = max(0, entry.offset as i32 + entry.len as i32 - new_entry.offset as i32 - new_entry.len as i32) as u32;
+                let valid_prev = new_entry.offset.saturating_sub(entry.offset);
+                let valid_suffix = (entry.offset + entry.len).saturating_sub(new_entry.offset + new_entry.len);
                 if valid_prev == 0 {
                     let event = inode_event::DeleteContentInodeEvent {
                         index,
@@ -140,7 +171,7 @@ impl Inode {
                         index,
                         offset: entry.offset,
                         len: valid_prev,
-                        size: valid_prev / 4096 + 1,
+                        size: (valid_prev / 4096 + 1) as u32,
                         o_size: entry.size,
                         v_address: entry.address,
                     };
@@ -151,7 +182,7 @@ impl Inode {
                     let event = inode_event::AddContentInodeEvent {
                         index,
                         offset,
-                        len,
+                        len: len as u64,
                         size: len / 4096 + 1,
                         content: buf.clone(),
                     };
@@ -165,7 +196,7 @@ impl Inode {
                         offset: entry.offset + entry.len - valid_suffix,
                         len: valid_suffix,
                         valid: false,
-                        size: valid_suffix / 4096 + 1,
+                        size: (valid_suffix / 4096 + 1) as u32,
                         address: 0,
                     });
                     second_index = index;
@@ -174,7 +205,7 @@ impl Inode {
         }
         if !flag {
             let event = inode_event::AddContentInodeEvent {
-                index: self.data.len() as u32,
+                index,
                 offset: new_entry.offset,
                 len: new_entry.len,
                 size: len / 4096 + 1,
@@ -194,19 +225,25 @@ impl Inode {
             };
             event_group.events.push(inode_event::InodeEvent::AddContent(event));
         }
-        let inode = self.core.as_mut().unwrap().borrow_mut().dispose_event_group(event_group).unwrap();
+        // 超过文件末尾写入时，中间留下空洞
+        event_group.inode.size = max(self.size, offset + len as u64);
+        event_group.inode.touch_content();
+        let inode = self.core.as_mut().unwrap().borrow_mut().dispose_event_group(event_group)?.unwrap();
         self.update_by_another_inode(inode);
-        true
+        self.core.as_mut().unwrap().borrow_mut().record_host_write(len);
+        Ok(())
     }
 
-    pub fn insert(&mut self, offset: u32, len: u32, buf: &Vec<u8>) -> bool {
+    pub fn insert(&mut self, offset: u64, len: u32, buf: &Vec<u8>) -> Result<(), FsError> {
+        // 插入后原有数据整体后移
+        Inode::check_end(max(offset, self.size), len)?;
         let mut event_group = inode_event::InodeEventGroup::new();
         event_group.inode = self.copy_inode();
         let mut index = 0;
         let mut flag = false;
         let new_entry = InodeEntry {
             offset,
-            len,
+            len: len as u64,
             valid: false,
             size: len,
             address: 0,
@@ -215,67 +252,72 @@ impl Inode {
         let mut second_o_entry = None;
         let mut second_index = 0;
         if offset > self.size {
-            return false;
+            return Err(FsError::InvalidArgument);
         }
-        for entry in self.data.iter_mut() {
+        for entry in self.data.iter() {
             if flag {
                 let event = inode_event::ChangeContentInodeEvent {
                     index: index,
-                    offset: entry.offset + len,
+                    offset: entry.offset + len as u64,
                     v_address: entry.address,
                 };
                 event_group.events.push(inode_event::InodeEvent::ChangeContent(event));
-            } else {
-                if new_entry.offset < entry.offset + entry.len {
-                    flag = true;
-                    let valid_prev = max(0, new_entry.offset - entry.offset);
-                    let valid_suffix = max(0, entry.offset as i32 + entry.len as i32 - new_entry.offset as i32) as u32;
-                    if valid_prev == 0 {
-                        let event = inode_event::DeleteContentInodeEvent {
-                            index,
-                            size: entry.size,
-                            v_address: entry.address,
-                        };
-                        event_group.events.push(inode_event::InodeEvent::DeleteContent(event));
-                    } else {
-                        let event = inode_event::TruncateContentInodeEvent {
-                            index,
-                            offset: entry.offset,
-                            len: valid_prev,
-                            size: valid_prev / 4096 + 1,
-                            o_size: entry.size,
-                            v_address: entry.address,
-                        };
-                        event_group.events.push(inode_event::InodeEvent::TruncateContent(event));
-                    }
-                    index  += 1;
-                    let event = inode_event::AddContentInodeEvent {
-                        index,
-                        offset,
-                        len,
-                        size: len / 4096 + 1,
-                        content: buf.clone(),
-                    };
-                    event_group.events.push(inode_event::InodeEvent::AddContent(event));
-                    index += 1;
-                    if valid_suffix > 0 {
-                        second_o_entry= Some(entry.clone());
-                        second_entry = Some(InodeEntry {
-                            offset: entry.offset + entry.len + len - valid_suffix,
-                            len: valid_suffix,
-                            valid: false,
-                            size: valid_suffix / 4096 + 1,
-                            address: 0,
-                        });
-                        second_index = index;
-                    }
-                }
+            } else if new_entry.offset <= entry.offset {
+                // 插入位置在Entry之前（包括空洞中），该Entry及之后的都后移
+                flag = true;
+                let event = inode_event::AddContentInodeEvent {
+                    index,
+                    offset,
+                    len: len as u64,
+                    size: len / 4096 + 1,
+                    content: buf.clone(),
+                };
+                event_group.events.push(inode_event::InodeEvent::AddContent(event));
+                index += 1;
+                let event = inode_event::ChangeContentInodeEvent {
+                    index,
+                    offset: entry.offset + len as u64,
+                    v_address: entry.address,
+                };
+                event_group.events.push(inode_event::InodeEvent::ChangeContent(event));
+            } else if new_entry.offset < entry.offset + entry.len {
+                flag = true;
+                let valid_prev = new_entry.offset - entry.offset;
+                let valid_suffix = entry.offset + entry.len - new_entry.offset;
+                let event = inode_event::TruncateContentInodeEvent {
+                    index,
+                    offset: entry.offset,
+                    len: valid_prev,
+                    size: (valid_prev / 4096 + 1) as u32,
+                    o_size: entry.size,
+                    v_address: entry.address,
+                };
+                event_group.events.push(inode_event::InodeEvent::TruncateContent(event));
+                index += 1;
+                let event = inode_event::AddContentInodeEvent {
+                    index,
+                    offset,
+                    len: len as u64,
+                    size: len / 4096 + 1,
+                    content: buf.clone(),
+                };
+                event_group.events.push(inode_event::InodeEvent::AddContent(event));
+                index += 1;
+                second_o_entry = Some(entry.clone());
+                second_entry = Some(InodeEntry {
+                    offset: entry.offset + entry.len + len as u64 - valid_suffix,
+                    len: valid_suffix,
+                    valid: false,
+                    size: (valid_suffix / 4096 + 1) as u32,
+                    address: 0,
+                });
+                second_index = index;
             }
             index += 1;
         }
         if !flag {
             let event = inode_event::AddContentInodeEvent {
-                index: self.data.len() as u32,
+                index,
                 offset: new_entry.offset,
                 len: new_entry.len,
                 size: len / 4096 + 1,
@@ -285,7 +327,7 @@ impl Inode {
         }
         if second_entry.is_some() {
             let second_entry = second_entry.unwrap();
-            let data = self.read_entry(&second_o_entry.unwrap(), second_entry.offset - len - second_o_entry.unwrap().offset, second_entry.offset + second_entry.len - len - second_o_entry.unwrap().offset);
+            let data = self.read_entry(&second_o_entry.unwrap(), second_entry.offset - len as u64 - second_o_entry.unwrap().offset, second_entry.offset + second_entry.len - len as u64 - second_o_entry.unwrap().offset);
             let event = inode_event::AddContentInodeEvent {
                 index: second_index,
                 offset: second_entry.offset,
@@ -295,12 +337,17 @@ impl Inode {
             };
             event_group.events.push(inode_event::InodeEvent::AddContent(event));
         }
-        let inode = self.core.as_mut().unwrap().borrow_mut().dispose_event_group(event_group).unwrap();
+        event_group.inode.size = self.size + len as u64;
+        event_group.inode.touch_content();
+        let inode = self.core.as_mut().unwrap().borrow_mut().dispose_event_group(event_group)?.unwrap();
         self.update_by_another_inode(inode);
-        true
+        Ok(())
     }
 
-    pub fn truncate(&mut self, offset: u32, len: u32) -> bool {
+    pub fn truncate(&mut self, offset: u64, len: u64) -> Result<(), FsError> {
+        if offset > self.size {
+            return Err(FsError::InvalidArgument);
+        }
         let mut event_group = inode_event::InodeEventGroup::new();
         event_group.inode = self.copy_inode();
         let mut new_entry = None;
@@ -321,8 +368,8 @@ impl Inode {
                 index += 1;
                 continue
             } else {
-                let valid_prev = max(0, offset as i32 - entry.offset as i32) as u32;
-                let valid_suffix = max(0, entry.offset as i32 + entry.len as i32 - offset as i32 - len as i32) as u32;
+                let valid_prev = offset.saturating_sub(entry.offset);
+                let valid_suffix = (entry.offset + entry.len).saturating_sub(offset + len);
                 if valid_prev == 0 {
                     let event = inode_event::DeleteContentInodeEvent {
                         index,
@@ -335,7 +382,7 @@ impl Inode {
                         index: (index as u32),
                         offset: entry.offset,
                         len: valid_prev,
-                        size: valid_prev / 4096 + 1,
+                        size: (valid_prev / 4096 + 1) as u32,
                         o_size: entry.size,
                         v_address: entry.address,
                     };
@@ -347,7 +394,7 @@ impl Inode {
                         offset: entry.offset + entry.len - valid_suffix - len,
                         len: valid_suffix,
                         valid: false,
-                        size: valid_suffix / 4096 + 1,
+                        size: (valid_suffix / 4096 + 1) as u32,
                         address: 0,
                     });
                     new_index = index;
@@ -368,14 +415,50 @@ impl Inode {
             };
             event_group.events.push(inode_event::InodeEvent::AddContent(event));
         }
-        let inode = self.core.as_mut().unwrap().borrow_mut().dispose_event_group(event_group).unwrap();
+        event_group.inode.size = self.size - min(len, self.size - offset);
+        event_group.inode.touch_content();
+        let inode = self.core.as_mut().unwrap().borrow_mut().dispose_event_group(event_group)?.unwrap();
         self.update_by_another_inode(inode);
-        true
+        Ok(())
     }
 
-    pub fn truncate_to_end(&mut self, offset: u32) -> bool {
+    pub fn truncate_to_end(&mut self, offset: u64) -> Result<(), FsError> {
         self.truncate(offset, self.size - offset)
     }
+
+    // 返回不小于offset的第一个有数据的位置，offset之后全是空洞时返回None
+    pub fn seek_data(&self, offset: u64) -> Option<u64> {
+        if offset >= self.size {
+            return None;
+        }
+        self.data.iter()
+            .find(|entry| entry.offset + entry.len > offset)
+            .map(|entry| max(entry.offset, offset))
+    }
+
+    // 返回不小于offset的第一个空洞的位置，文件末尾也视为空洞
+    pub fn seek_hole(&self, offset: u64) -> Option<u64> {
+        if offset >= self.size {
+            return None;
+        }
+        let mut pos = offset;
+        for entry in self.data.iter() {
+            if entry.offset > pos {
+                break;
+            }
+            pos = max(pos, entry.offset + entry.len);
+        }
+        Some(min(pos, self.size))
+    }
+
+    // 写回atime后将缓存中的数据写入磁盘
+    pub fn sync(&mut self) -> bool {
+        if self.write_back_atime().is_err() {
+            return false;
+        }
+        self.core.as_mut().unwrap().borrow_mut().sync();
+        true
+    }
 }
 
 impl Inode {
@@ -388,10 +471,25 @@ impl Inode {
             gid: self.gid,
             ref_cnt: self.ref_cnt,
             n_link: self.n_link,
+            generation: self.generation,
+            atime: self.atime,
+            mtime: self.mtime,
+            ctime: self.ctime,
+            btime: self.btime,
+            mode: self.mode,
         }
     }
 
+    // 写入失败时panic，需要返回错误的调用者使用commit_stat
     pub fn modify_stat(&mut self, stat: InodeStat) -> bool {
+        if self.commit_stat(stat).is_err() {
+            panic!("Inode: modify stat internal error");
+        }
+        true
+    }
+
+    // 同时更新ctime，btime不能修改，stat中的btime被忽略，mode中的类型由file_type决定
+    pub fn commit_stat(&mut self, stat: InodeStat) -> Result<(), FsError> {
         let mut event_group = inode_event::InodeEventGroup::new();
         event_group.inode = self.copy_inode();
         if stat.ino != self.ino {
@@ -400,6 +498,9 @@ impl Inode {
         if stat.size != self.size {
             panic!("Inode: modify stat can't change size");
         }
+        if stat.generation != self.generation {
+            panic!("Inode: modify stat can't change generation");
+        }
         let event = inode_event::ModifyInodeStatInodeEvent {
             file_type: stat.file_type,
             ino: stat.ino,
@@ -407,11 +508,15 @@ impl Inode {
             uid: stat.uid,
             gid: stat.gid,
             n_link: stat.n_link,
+            mode: mode::make_mode(stat.file_type, stat.mode),
+            atime: stat.atime,
+            mtime: stat.mtime,
+            ctime: time::now(),
         };
         event_group.events.push(inode_event::InodeEvent::ModifyStat(event));
-        let inode = self.core.as_mut().unwrap().borrow_mut().dispose_event_group(event_group).unwrap();
+        let inode = self.core.as_mut().unwrap().borrow_mut().dispose_event_group(event_group)?.unwrap();
         self.update_by_another_inode(inode);
-        true
+        Ok(())
     }
 
     pub fn dup(&mut self) -> bool {
@@ -423,15 +528,168 @@ impl Inode {
             gid: self.gid,
             ref_cnt: self.ref_cnt,
             n_link: self.n_link + 1,
+            generation: self.generation,
+            atime: self.atime,
+            mtime: self.mtime,
+            ctime: self.ctime,
+            btime: self.btime,
+            mode: self.mode,
         };
         self.modify_stat(stat)
     }
 
+    // 显式设置atime与mtime，两者都为Omit时不做修改
+    // 设置为指定时间需要是所有者或root，只设置为当前时间时有写权限即可
+    pub fn utimens(&mut self, cred: &Cred, atime: SetTime, mtime: SetTime) -> Result<(), FsError> {
+        if atime == SetTime::Omit && mtime == SetTime::Omit {
+            return Ok(());
+        }
+        let is_owner = cred.is_root() || cred.uid == self.uid;
+        let explicit = matches!(atime, SetTime::Time(_)) || matches!(mtime, SetTime::Time(_));
+        if explicit && !is_owner {
+            return Err(FsError::NotPermitted);
+        }
+        if !is_owner && !self.permission(cred, mode::W_OK) {
+            return Err(FsError::PermissionDenied);
+        }
+        let now = time::now();
+        let mut stat = self.get_stat();
+        for (time, set_time) in [(&mut stat.atime, atime), (&mut stat.mtime, mtime)] {
+            match set_time {
+                SetTime::Now => *time = now,
+                SetTime::Time(value) => *time = value,
+                SetTime::Omit => (),
+            }
+        }
+        self.commit_stat(stat)
+    }
+
+    // root不受权限位限制，但执行需要至少有一组x权限，目录总可以搜索
+    pub fn permission(&self, cred: &Cred, mask: u16) -> bool {
+        if cred.is_root() {
+            return mask & mode::X_OK == 0 || self.file_type == InodeFileType::Directory || self.mode & 0o111 != 0;
+        }
+        let bits = if cred.uid == self.uid {
+            self.mode >> 6
+        } else if cred.in_group(self.gid) {
+            self.mode >> 3
+        } else {
+            self.mode
+        };
+        bits & mask & 7 == mask
+    }
+
+    // 只有所有者与root可以修改，不在文件所属组中的非root用户不能设置setgid
+    pub fn chmod(&mut self, cred: &Cred, perm: u16) -> Result<(), FsError> {
+        if !cred.is_root() && cred.uid != self.uid {
+            return Err(FsError::NotPermitted);
+        }
+        let mut perm = perm & mode::PERM_MASK;
+        if !cred.is_root() && !cred.in_group(self.gid) {
+            perm &= !mode::S_ISGID;
+        }
+        let mut stat = self.get_stat();
+        stat.mode = perm;
+        self.commit_stat(stat)
+    }
+
+    // root可以任意修改，所有者只能把组改为自己所在的组
+    // 非root修改后清除普通文件的setuid与setgid
+    pub fn chown(&mut self, cred: &Cred, uid: Option<u32>, gid: Option<u16>) -> Result<(), FsError> {
+        if !cred.is_root() {
+            if cred.uid != self.uid || uid.map_or(false, |uid| uid != self.uid) {
+                return Err(FsError::NotPermitted);
+            }
+            if gid.map_or(false, |gid| !cred.in_group(gid)) {
+                return Err(FsError::NotPermitted);
+            }
+        }
+        let mut stat = self.get_stat();
+        stat.uid = uid.unwrap_or(stat.uid);
+        stat.gid = gid.unwrap_or(stat.gid);
+        if !cred.is_root() && self.file_type != InodeFileType::Directory {
+            stat.mode &= !(mode::S_ISUID | mode::S_ISGID);
+        }
+        self.commit_stat(stat)
+    }
+
+    // 修改user命名空间需要写权限，trusted与security只有root可以修改，system需要是所有者或root
+    pub fn xattr_permission(&self, cred: &Cred, name: &str) -> Result<(), FsError> {
+        if name.starts_with("user.") {
+            if !self.permission(cred, mode::W_OK) {
+                return Err(FsError::PermissionDenied);
+            }
+        } else if name.starts_with("system.") {
+            if !cred.is_root() && cred.uid != self.uid {
+                return Err(FsError::NotPermitted);
+            }
+        } else if !cred.is_root() {
+            return Err(FsError::NotPermitted);
+        }
+        Ok(())
+    }
+
+    // 读取时user需要读权限，trusted只有root可以读取
+    pub fn xattr_read_permission(&self, cred: &Cred, name: &str) -> Result<(), FsError> {
+        if name.starts_with("user.") {
+            if !self.permission(cred, mode::R_OK) {
+                return Err(FsError::PermissionDenied);
+            }
+        } else if name.starts_with("trusted.") && !cred.is_root() {
+            return Err(FsError::NotPermitted);
+        }
+        Ok(())
+    }
+
+    pub fn get_xattr(&mut self, cred: &Cred, name: &str) -> Result<Vec<u8>, FsError> {
+        xattr::check_name(name)?;
+        self.xattr_read_permission(cred, name)?;
+        self.load_xattr().remove(name).ok_or(FsError::NoData)
+    }
+
+    // flags为XATTR_CREATE时名字不能已存在，为XATTR_REPLACE时名字必须已存在
+    pub fn set_xattr(&mut self, cred: &Cred, name: &str, value: &[u8], flags: u32) -> Result<(), FsError> {
+        xattr::check_name(name)?;
+        self.xattr_permission(cred, name)?;
+        if value.len() > xattr::VALUE_MAX {
+            return Err(FsError::TooBig);
+        }
+        let mut map = self.load_xattr();
+        let exists = map.contains_key(name);
+        if flags & xattr::XATTR_CREATE != 0 && exists {
+            return Err(FsError::Exists);
+        }
+        if flags & xattr::XATTR_REPLACE != 0 && !exists {
+            return Err(FsError::NoData);
+        }
+        map.insert(name.to_string(), value.to_vec());
+        if xattr::encode(&map).len() > xattr::TOTAL_MAX {
+            return Err(FsError::NoSpace);
+        }
+        self.store_xattr(&map)
+    }
+
+    // 只列出cred可以读取的名字
+    pub fn list_xattr(&mut self, cred: &Cred) -> Vec<String> {
+        let map = self.load_xattr();
+        map.into_keys().filter(|name| self.xattr_read_permission(cred, name).is_ok()).collect()
+    }
+
+    pub fn remove_xattr(&mut self, cred: &Cred, name: &str) -> Result<(), FsError> {
+        xattr::check_name(name)?;
+        self.xattr_permission(cred, name)?;
+        let mut map = self.load_xattr();
+        if map.remove(name).is_none() {
+            return Err(FsError::NoData);
+        }
+        self.store_xattr(&map)
+    }
+
     pub fn delete(&mut self) -> bool {
         let mut event_group = inode_event::InodeEventGroup::new();
         event_group.inode = self.copy_inode();
         event_group.need_delete = true;
-        if self.core.as_mut().unwrap().borrow_mut().dispose_event_group(event_group).is_some() {
+        if self.core.as_mut().unwrap().borrow_mut().dispose_event_group(event_group).map_or(true, |inode| inode.is_some()) {
             panic!("Inode: delete internal error");
         }
         true
@@ -439,10 +697,10 @@ impl Inode {
 }
 
 impl Inode {
-    pub fn read_entry(&mut self, entry: &InodeEntry, start: u32, end: u32) -> Vec<u8> {
-        let start_index = start / 4096;
+    pub fn read_entry(&mut self, entry: &InodeEntry, start: u64, end: u64) -> Vec<u8> {
+        let start_index = (start / 4096) as u32;
         let start_off = start % 4096;
-        let end_index = (end - 1) / 4096;
+        let end_index = ((end - 1) / 4096) as u32;
         let end_off = (end - 1) % 4096;
         let mut pages = vec![];
         for i in start_index..end_index + 1 {
@@ -469,6 +727,60 @@ impl Inode {
         res
     }
 
+    pub fn load_xattr(&mut self) -> xattr::XattrMap {
+        match self.xattr_entry {
+            Some(entry) => xattr::decode(&self.read_entry(&entry, 0, entry.len)),
+            None => xattr::decode(&self.xattr),
+        }
+    }
+
+    // 整体写回所有扩展属性，同时更新ctime
+    pub fn store_xattr(&mut self, map: &xattr::XattrMap) -> Result<(), FsError> {
+        let mut event_group = inode_event::InodeEventGroup::new();
+        event_group.inode = self.copy_inode();
+        event_group.inode.ctime = time::now();
+        let event = inode_event::SetXattrInodeEvent {
+            content: xattr::encode(map),
+        };
+        event_group.events.push(inode_event::InodeEvent::SetXattr(event));
+        let inode = self.core.as_mut().unwrap().borrow_mut().dispose_event_group(event_group)?.unwrap();
+        self.update_by_another_inode(inode);
+        Ok(())
+    }
+
+    // 内容改变时同时更新mtime与ctime
+    pub fn touch_content(&mut self) {
+        let now = time::now();
+        self.mtime = now;
+        self.ctime = now;
+    }
+
+    // 按挂载时的策略更新atime，只修改内存中的值，在sync或引用全部释放时写回
+    pub fn touch_atime(&mut self) {
+        let now = time::now();
+        let update = match self.core.as_ref().unwrap().borrow().get_atime_policy() {
+            AtimePolicy::Strict => true,
+            AtimePolicy::Relatime => self.atime <= self.mtime || self.atime <= self.ctime || now >= self.atime + 24 * 3600 * time::NSEC_PER_SEC,
+            AtimePolicy::NoAtime => false,
+        };
+        if update {
+            self.atime = now;
+            self.dirty = true;
+        }
+    }
+
+    // 写回内存中更新过的atime，之后的任何写入也会一并写回
+    pub fn write_back_atime(&mut self) -> Result<(), FsError> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let mut event_group = inode_event::InodeEventGroup::new();
+        event_group.inode = self.copy_inode();
+        let inode = self.core.as_mut().unwrap().borrow_mut().dispose_event_group(event_group)?.unwrap();
+        self.update_by_another_inode(inode);
+        Ok(())
+    }
+
     pub fn update_by_another_inode(&mut self, inode: Inode) {
         self.valid = inode.valid;
         self.file_type = inode.file_type;
@@ -478,7 +790,16 @@ impl Inode {
         self.gid = inode.gid;
         self.ref_cnt = inode.ref_cnt;
         self.n_link = inode.n_link;
+        self.generation = inode.generation;
+        self.atime = inode.atime;
+        self.mtime = inode.mtime;
+        self.ctime = inode.ctime;
+        self.btime = inode.btime;
+        self.mode = inode.mode;
         self.data = inode.data;
+        self.xattr = inode.xattr;
+        self.xattr_entry = inode.xattr_entry;
+        self.dirty = false;
     }
 
     pub fn copy_inode(&self) -> Inode {
@@ -489,10 +810,19 @@ impl Inode {
             uid: self.uid,
             gid: self.gid,
             n_link: self.n_link,
+            generation: self.generation,
+            atime: self.atime,
+            mtime: self.mtime,
+            ctime: self.ctime,
+            btime: self.btime,
+            mode: self.mode,
             data: self.data.clone(),
+            xattr: self.xattr.clone(),
+            xattr_entry: self.xattr_entry,
             valid: self.valid,
             ref_cnt: self.ref_cnt,
             lock: Mutex::new(false),
+            dirty: false,
             core: None,
         }
     }
@@ -509,6 +839,7 @@ impl Inode {
 mod test {
     use std::sync::Arc;
     use crate::inode::inode_manager;
+    use crate::core::options;
     use super::*;
 
     #[test]
@@ -519,7 +850,7 @@ mod test {
     #[test]
     fn write() {
         let mut inode_manager = inode_manager::InodeManager::new();
-        inode_manager.core_manager.borrow_mut().mount();
+        inode_manager.core_manager.borrow_mut().mount().unwrap();
         let mut link = inode_manager.i_alloc();
         let mut buf_1 = vec![];
         for _ in 0..100 {
@@ -537,10 +868,10 @@ mod test {
         for _ in 0..30 {
             buf_4.push(21);
         }
-        link.as_ref().unwrap().borrow_mut().write(0, 100, &buf_1);
-        link.as_ref().unwrap().borrow_mut().write(13, 27, &buf_2);
-        link.as_ref().unwrap().borrow_mut().write(89, 10, &buf_3);
-        link.as_mut().unwrap().borrow_mut().write(5, 30, &buf_4);
+        link.as_ref().unwrap().borrow_mut().write(0, 100, &buf_1).unwrap();
+        link.as_ref().unwrap().borrow_mut().write(13, 27, &buf_2).unwrap();
+        link.as_ref().unwrap().borrow_mut().write(89, 10, &buf_3).unwrap();
+        link.as_mut().unwrap().borrow_mut().write(5, 30, &buf_4).unwrap();
         let mut buf = vec![];
         link.as_mut().unwrap().borrow_mut().read_all(&mut buf);
         assert_eq!(buf.len(), 100);
@@ -550,17 +881,261 @@ mod test {
         for _ in 0..10000 {
             buf_5.push(37)
         }
-        link.as_mut().unwrap().borrow_mut().write(5, 10000, &buf_5);
+        link.as_mut().unwrap().borrow_mut().write(5, 10000, &buf_5).unwrap();
         link.as_mut().unwrap().borrow_mut().read_all(&mut buf);
         assert_eq!(buf.len(), 10005);
         link.as_mut().unwrap().borrow_mut().read(50, 8000, &mut buf);
         assert_eq!(buf.len(), 8000);
     }
 
+    #[test]
+    fn metrics() {
+        let mut inode_manager = inode_manager::InodeManager::new();
+        inode_manager.core_manager.borrow_mut().mount().unwrap();
+        let link = inode_manager.i_alloc();
+        link.as_ref().unwrap().borrow_mut().write(0, 8192, &vec![1; 8192]).unwrap();
+        let snapshot = inode_manager.core_manager.borrow().get_metrics();
+        assert_eq!((snapshot.metrics.host_write_num, snapshot.metrics.host_write_pages), (1, 2));
+        // 末尾多出的全零Page不写入
+        assert_eq!(snapshot.metrics.data_write_pages, 2);
+        // 写入磁盘后才计入device的写入
+        link.as_ref().unwrap().borrow_mut().sync();
+        let mut buf = vec![];
+        link.as_ref().unwrap().borrow_mut().read_all(&mut buf);
+        let snapshot = inode_manager.core_manager.borrow().get_metrics();
+        assert!(snapshot.metrics.device_program_pages >= 2);
+        assert!(snapshot.write_amplification() >= 1.0);
+        assert!(snapshot.metrics.cache_hit_num > 0);
+        assert!(snapshot.expose().contains("sffs_host_write_bytes 8192\n"));
+
+        inode_manager.core_manager.borrow_mut().reset_metrics();
+        let snapshot = inode_manager.core_manager.borrow().get_metrics();
+        assert_eq!(snapshot.metrics.host_write_bytes, 0);
+        assert!(!snapshot.erase_count.is_empty());
+    }
+
+    #[test]
+    fn sparse() {
+        let mut inode_manager = inode_manager::InodeManager::new();
+        inode_manager.core_manager.borrow_mut().mount().unwrap();
+        let link = inode_manager.i_alloc();
+        let mut inode = link.as_ref().unwrap().borrow_mut();
+        inode.write(0, 100, &vec![7; 100]).unwrap();
+        inode.write(30000, 100, &vec![9; 100]).unwrap();
+        assert_eq!(inode.size, 30100);
+        assert_eq!(inode.data.len(), 2);
+        let mut buf = vec![];
+        assert_eq!(inode.read(90, 20, &mut buf), 20);
+        assert_eq!(buf, [vec![7; 10], vec![0; 10]].concat());
+        assert_eq!(inode.read(29990, 200, &mut buf), 110);
+        assert_eq!(buf, [vec![0; 10], vec![9; 100]].concat());
+
+        // 全零的Page不占用空间，中间的零页把Entry拆开
+        let mut content = vec![0; 4096 * 3];
+        content[0] = 1;
+        content[4096 * 2] = 2;
+        inode.write(8192, 4096 * 3, &content).unwrap();
+        assert_eq!(inode.data.len(), 4);
+        assert_eq!(inode.data.iter().map(|entry| entry.size).sum::<u32>(), 4);
+        inode.read(8192, 4096 * 3, &mut buf);
+        assert_eq!(buf, content);
+
+        assert_eq!(inode.seek_data(100), Some(8192));
+        assert_eq!(inode.seek_hole(0), Some(100));
+        assert_eq!(inode.seek_hole(8192), Some(8192 + 4096));
+        assert_eq!(inode.seek_data(8192 + 4096), Some(8192 + 4096 * 2));
+        assert_eq!(inode.seek_hole(30050), Some(30100));
+        assert_eq!(inode.seek_data(30100), None);
+
+        // 在空洞中插入，之后的数据整体后移
+        inode.insert(5000, 10, &vec![3; 10]).unwrap();
+        assert_eq!(inode.size, 30110);
+        assert_eq!(inode.seek_data(5010), Some(8202));
+        inode.read(30100, 10, &mut buf);
+        assert_eq!(buf, vec![9; 10]);
+        inode.truncate(50, 4950).unwrap();
+        assert_eq!(inode.size, 25160);
+        inode.read(40, 30, &mut buf);
+        assert_eq!(buf, [vec![7; 10], vec![3; 10], vec![0; 10]].concat());
+    }
+
+    #[test]
+    fn large_file() {
+        let mut inode_manager = inode_manager::InodeManager::new();
+        inode_manager.core_manager.borrow_mut().mount().unwrap();
+        let link = inode_manager.i_alloc();
+        let mut inode = link.as_ref().unwrap().borrow_mut();
+        // 跨过4GiB的写入，之前的部分都是空洞，不占用空间
+        let offset = (4 << 30) - 50;
+        inode.write(offset, 100, &vec![5; 100]).unwrap();
+        inode.write(5 << 30, 100, &vec![6; 100]).unwrap();
+        assert_eq!(inode.size, (5 << 30) + 100);
+        assert_eq!(inode.data.iter().map(|entry| entry.size).sum::<u32>(), 2);
+        let mut buf = vec![];
+        assert_eq!(inode.read(offset - 10, 120, &mut buf), 120);
+        assert_eq!(buf, [vec![0; 10], vec![5; 100], vec![0; 10]].concat());
+        assert_eq!(inode.seek_data(offset + 100), Some(5 << 30));
+        assert_eq!(inode.seek_hole(5 << 30), Some((5 << 30) + 100));
+        inode.truncate(0, 4 << 30).unwrap();
+        assert_eq!(inode.size, (1 << 30) + 100);
+        inode.read(0, 60, &mut buf);
+        assert_eq!(buf, [vec![5; 50], vec![0; 10]].concat());
+        inode.read(1 << 30, 100, &mut buf);
+        assert_eq!(buf, vec![6; 100]);
+
+        // 超过文件大小上限时返回FileTooBig，不做修改
+        assert_eq!(inode.write(xattr::EXTENT_OFFSET - 50, 100, &vec![1; 100]), Err(FsError::FileTooBig));
+        assert_eq!(inode.write(u64::MAX - 50, 100, &vec![1; 100]), Err(FsError::FileTooBig));
+        assert_eq!(inode.insert(u64::MAX, 100, &vec![1; 100]), Err(FsError::FileTooBig));
+        inode.write(xattr::EXTENT_OFFSET - 100, 100, &vec![1; 100]).unwrap();
+        assert_eq!(inode.size, xattr::EXTENT_OFFSET);
+        assert_eq!(inode.insert(0, 1, &vec![1]), Err(FsError::FileTooBig));
+        assert_eq!(inode.size, xattr::EXTENT_OFFSET);
+    }
+
+    #[test]
+    fn xattr() {
+        let mut inode_manager = inode_manager::InodeManager::new();
+        inode_manager.mount().unwrap();
+        let link = inode_manager.i_alloc();
+        let mut inode = link.as_ref().unwrap().borrow_mut();
+        let core = inode.core.clone().unwrap();
+        let valid = core.borrow().get_space_stat().valid;
+        let root = Cred::new(0, 0);
+        inode.set_xattr(&root, "security.selinux", b"system_u:object_r:bin_t", 0).unwrap();
+        inode.set_xattr(&root, "user.empty", b"", xattr::XATTR_CREATE).unwrap();
+        assert_eq!(inode.xattr_entry.is_none(), true);
+        assert_eq!(inode.set_xattr(&root, "user.empty", b"1", xattr::XATTR_CREATE), Err(FsError::Exists));
+        assert_eq!(inode.set_xattr(&root, "user.none", b"1", xattr::XATTR_REPLACE), Err(FsError::NoData));
+        assert_eq!(inode.set_xattr(&root, "os2.name", b"1", 0), Err(FsError::NotSupported));
+        assert_eq!(inode.set_xattr(&root, "user.big", &vec![1; xattr::VALUE_MAX + 1], 0), Err(FsError::TooBig));
+        assert_eq!(inode.get_xattr(&root, "user.none"), Err(FsError::NoData));
+
+        // user需要写权限，trusted与security只有root可以修改
+        inode.chown(&root, Some(100), Some(10)).unwrap();
+        inode.chmod(&root, 0o644).unwrap();
+        let owner = Cred::new(100, 10);
+        let other = Cred::new(200, 20);
+        assert_eq!(inode.set_xattr(&other, "user.other", b"1", 0), Err(FsError::PermissionDenied));
+        assert_eq!(inode.remove_xattr(&other, "user.empty"), Err(FsError::PermissionDenied));
+        assert_eq!(inode.set_xattr(&owner, "trusted.owner", b"1", 0), Err(FsError::NotPermitted));
+        assert_eq!(inode.remove_xattr(&owner, "security.selinux"), Err(FsError::NotPermitted));
+        inode.set_xattr(&owner, "user.owner", b"1", 0).unwrap();
+        inode.remove_xattr(&owner, "user.owner").unwrap();
+
+        // user需要读权限，trusted只有root可以读取，列出时同样过滤
+        inode.set_xattr(&root, "trusted.secret", b"1", 0).unwrap();
+        assert_eq!(inode.get_xattr(&other, "user.empty"), Ok(vec![]));
+        assert_eq!(inode.get_xattr(&owner, "trusted.secret"), Err(FsError::NotPermitted));
+        assert_eq!(inode.list_xattr(&other), vec!["security.selinux", "user.empty"]);
+        inode.chmod(&root, 0o600).unwrap();
+        assert_eq!(inode.get_xattr(&other, "user.empty"), Err(FsError::PermissionDenied));
+        assert_eq!(inode.get_xattr(&other, "security.selinux"), Ok(b"system_u:object_r:bin_t".to_vec()));
+        assert_eq!(inode.list_xattr(&other), vec!["security.selinux"]);
+        assert_eq!(inode.list_xattr(&owner), vec!["security.selinux", "user.empty"]);
+        inode.remove_xattr(&root, "trusted.secret").unwrap();
+
+        // 超过内联大小后保存在单独的Extent中，重新读取Inode后仍然存在
+        inode.set_xattr(&root, "user.big", &vec![7; 10000], 0).unwrap();
+        assert_eq!((inode.xattr.is_empty(), inode.xattr_entry.unwrap().size), (true, 3));
+        assert_eq!(core.borrow().get_space_stat().valid, valid + 3);
+        let mut stored = core.borrow_mut().get_inode(inode.ino);
+        stored.core = Some(core.clone());
+        assert_eq!(stored.get_xattr(&root, "user.big"), Ok(vec![7; 10000]));
+        assert_eq!(stored.get_xattr(&root, "security.selinux"), Ok(b"system_u:object_r:bin_t".to_vec()));
+        assert_eq!(stored.list_xattr(&root), vec!["security.selinux", "user.big", "user.empty"]);
+
+        // 编码后的总长度超过上限时不做修改
+        for i in 0..15 {
+            inode.set_xattr(&root, &format!("user.v{}", i), &vec![1; xattr::VALUE_MAX], 0).unwrap();
+        }
+        let entry = inode.xattr_entry.unwrap();
+        assert_eq!(inode.set_xattr(&root, "user.v15", &vec![1; xattr::VALUE_MAX], 0), Err(FsError::NoSpace));
+        assert_eq!(inode.xattr_entry.unwrap().address, entry.address);
+        for i in 0..15 {
+            inode.remove_xattr(&root, &format!("user.v{}", i)).unwrap();
+        }
+
+        // 删除后回到内联保存，Extent占用的Page被释放
+        inode.remove_xattr(&root, "user.big").unwrap();
+        assert_eq!(inode.remove_xattr(&root, "user.big"), Err(FsError::NoData));
+        assert_eq!((inode.xattr.is_empty(), inode.xattr_entry.is_none()), (false, true));
+        assert_eq!(core.borrow().get_space_stat().valid, valid);
+        let mut stored = core.borrow_mut().get_inode(inode.ino);
+        stored.core = Some(core.clone());
+        assert_eq!(stored.list_xattr(&root), vec!["security.selinux", "user.empty"]);
+        inode.set_xattr(&root, "user.big", &vec![7; 10000], 0).unwrap();
+        inode.delete();
+        assert_eq!(core.borrow().get_space_stat().valid, valid);
+    }
+
+    #[test]
+    fn time() {
+        let mut inode_manager = inode_manager::InodeManager::new();
+        inode_manager.mount().unwrap();
+        let link = inode_manager.i_alloc();
+        let mut inode = link.as_ref().unwrap().borrow_mut();
+        let btime = inode.btime;
+        assert!(btime > 0);
+        assert_eq!((inode.atime, inode.mtime, inode.ctime), (btime, btime, btime));
+
+        inode.write(0, 100, &vec![1; 100]).unwrap();
+        assert!(inode.mtime >= btime && inode.ctime == inode.mtime);
+        // relatime下atime不晚于mtime时才更新
+        let mut buf = vec![];
+        inode.read(0, 10, &mut buf);
+        let atime = inode.atime;
+        assert!(atime >= inode.mtime);
+        inode.read(0, 10, &mut buf);
+        assert_eq!(inode.atime, atime);
+        // atime在sync时才写回
+        assert!(inode.dirty);
+        assert_eq!(inode.core.as_ref().unwrap().borrow_mut().get_inode(inode.ino).atime, btime);
+        assert!(inode.sync());
+        assert!(!inode.dirty);
+        assert_eq!(inode.core.as_ref().unwrap().borrow_mut().get_inode(inode.ino).atime, atime);
+
+        let mtime = inode.mtime;
+        let root = Cred::new(0, 0);
+        inode.utimens(&root, SetTime::Time(5), SetTime::Omit).unwrap();
+        assert_eq!((inode.atime, inode.mtime), (5, mtime));
+        assert!(inode.ctime >= mtime);
+        let stored = inode.core.as_ref().unwrap().borrow_mut().get_inode(inode.ino);
+        assert_eq!((stored.atime, stored.mtime, stored.btime), (5, mtime, btime));
+        inode.truncate_to_end(50).unwrap();
+        assert!(inode.mtime >= mtime && inode.btime == btime);
+
+        // 非所有者只能在有写权限时设置为当前时间
+        inode.chown(&root, Some(100), Some(10)).unwrap();
+        inode.chmod(&root, 0o644).unwrap();
+        let other = Cred::new(200, 20);
+        assert_eq!(inode.utimens(&other, SetTime::Time(6), SetTime::Omit), Err(FsError::NotPermitted));
+        assert_eq!(inode.utimens(&other, SetTime::Now, SetTime::Now), Err(FsError::PermissionDenied));
+        assert_eq!(inode.utimens(&other, SetTime::Omit, SetTime::Omit), Ok(()));
+        assert_eq!(inode.atime, 5);
+        inode.chmod(&root, 0o646).unwrap();
+        assert_eq!(inode.utimens(&other, SetTime::Now, SetTime::Omit), Ok(()));
+        assert!(inode.atime > 5);
+        assert_eq!(inode.utimens(&other, SetTime::Time(6), SetTime::Now), Err(FsError::NotPermitted));
+        assert_eq!(inode.utimens(&Cred::new(100, 10), SetTime::Time(6), SetTime::Omit), Ok(()));
+        assert_eq!(inode.atime, 6);
+
+        let mut inode_manager = inode_manager::InodeManager::new();
+        let mut options = options::MountOptions::new();
+        options.atime_policy = AtimePolicy::NoAtime;
+        inode_manager.mount_with_options(options).unwrap();
+        let link = inode_manager.i_alloc();
+        let mut inode = link.as_ref().unwrap().borrow_mut();
+        inode.write(0, 10, &vec![1; 10]).unwrap();
+        inode.utimens(&Cred::new(0, 0), SetTime::Time(5), SetTime::Now).unwrap();
+        inode.read(0, 10, &mut buf);
+        assert_eq!(inode.atime, 5);
+    }
+
     #[test]
     fn insert() {
         let mut inode_manager = inode_manager::InodeManager::new();
-        inode_manager.core_manager.borrow_mut().mount();
+        inode_manager.core_manager.borrow_mut().mount().unwrap();
         let mut link = inode_manager.i_alloc();
         let mut buf_1 = vec![];
         for _ in 0..100 {
@@ -578,10 +1153,10 @@ mod test {
         for _ in 0..30 {
             buf_4.push(21);
         }
-        link.as_ref().unwrap().borrow_mut().insert(0, 100, &buf_1);
-        link.as_ref().unwrap().borrow_mut().insert(40, 30, &buf_2);
-        link.as_ref().unwrap().borrow_mut().insert(45, 10, &buf_3);
-        link.as_mut().unwrap().borrow_mut().insert(35, 30, &buf_4);
+        link.as_ref().unwrap().borrow_mut().insert(0, 100, &buf_1).unwrap();
+        link.as_ref().unwrap().borrow_mut().insert(40, 30, &buf_2).unwrap();
+        link.as_ref().unwrap().borrow_mut().insert(45, 10, &buf_3).unwrap();
+        link.as_mut().unwrap().borrow_mut().insert(35, 30, &buf_4).unwrap();
         let mut buf = vec![];
         link.as_mut().unwrap().borrow_mut().read_all(&mut buf);
         assert_eq!(buf.len(), 170);
@@ -592,7 +1167,7 @@ mod test {
     #[test]
     fn truncate() {
         let mut inode_manager = inode_manager::InodeManager::new();
-        inode_manager.core_manager.borrow_mut().mount();
+        inode_manager.core_manager.borrow_mut().mount().unwrap();
         let mut link = inode_manager.i_alloc();
         let mut buf_1 = vec![];
         for _ in 0..100 {
@@ -610,10 +1185,10 @@ mod test {
         for _ in 0..30 {
             buf_4.push(21);
         }
-        link.as_ref().unwrap().borrow_mut().insert(0, 100, &buf_1);
-        link.as_ref().unwrap().borrow_mut().insert(40, 30, &buf_2);
-        link.as_ref().unwrap().borrow_mut().insert(45, 10, &buf_3);
-        link.as_ref().unwrap().borrow_mut().truncate(30, 100);
+        link.as_ref().unwrap().borrow_mut().insert(0, 100, &buf_1).unwrap();
+        link.as_ref().unwrap().borrow_mut().insert(40, 30, &buf_2).unwrap();
+        link.as_ref().unwrap().borrow_mut().insert(45, 10, &buf_3).unwrap();
+        link.as_ref().unwrap().borrow_mut().truncate(30, 100).unwrap();
         let mut buf = vec![];
         link.as_mut().unwrap().borrow_mut().read_all(&mut buf);
         assert_eq!(buf.len(), 40);
@@ -622,7 +1197,7 @@ mod test {
     #[test]
     fn modify() {
         let mut inode_manager = inode_manager::InodeManager::new();
-        inode_manager.core_manager.borrow_mut().mount();
+        inode_manager.core_manager.borrow_mut().mount().unwrap();
         let link = inode_manager.i_alloc();
         let stat = InodeStat {
             file_type: InodeFileType::Directory,
@@ -632,6 +1207,12 @@ mod test {
             gid: 44,
             ref_cnt: 10,
             n_link: 10,
+            generation: 0,
+            atime: 0,
+            mtime: 0,
+            ctime: 0,
+            btime: 0,
+            mode: 0o755,
         };
         link.as_ref().unwrap().borrow_mut().modify_stat(stat);
         let link = link.as_ref().unwrap().borrow_mut().core.as_mut().unwrap().borrow_mut().get_inode(1);
@@ -642,7 +1223,7 @@ mod test {
     #[test]
     fn delete() {
         let mut inode_manager = inode_manager::InodeManager::new();
-        inode_manager.core_manager.borrow_mut().mount();
+        inode_manager.core_manager.borrow_mut().mount().unwrap();
         let link = inode_manager.i_alloc();
         let mut buf_1 = vec![];
         for _ in 0..100 {
@@ -652,8 +1233,8 @@ mod test {
         for _ in 0..27 {
             buf_2.push(31);
         }
-        link.as_ref().unwrap().borrow_mut().write(0, 100, &buf_1);
-        link.as_ref().unwrap().borrow_mut().write(13, 27, &buf_2);
+        link.as_ref().unwrap().borrow_mut().write(0, 100, &buf_1).unwrap();
+        link.as_ref().unwrap().borrow_mut().write(13, 27, &buf_2).unwrap();
         link.as_ref().unwrap().borrow_mut().delete();
     }
 }
\ No newline at end of file
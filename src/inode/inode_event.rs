@@ -42,6 +42,9 @@ impl InodeEventGroup {
                 InodeEvent::ModifyStat(event) => {
                     println!("InodeEventGroup::Debug:{}, Modify size: {} uid: {} gid: {} n_link: {}", index, event.size, event.uid, event.gid, event.n_link);
                 },
+                InodeEvent::SetXattr(event) => {
+                    println!("InodeEventGroup::Debug:{}, SetXattr len: {}", index, event.content.len());
+                },
                 InodeEvent::None => {
                     println!("InodeEventGroup::Debug:{}, None", index);
                 },
@@ -57,6 +60,7 @@ pub enum InodeEvent {
     ChangeContent(ChangeContentInodeEvent),
     DeleteContent(DeleteContentInodeEvent),
     ModifyStat(ModifyInodeStatInodeEvent),
+    SetXattr(SetXattrInodeEvent),
     None,
 }
 
@@ -69,6 +73,7 @@ impl InodeEvent {
             InodeEvent::ChangeContent(event) => index = event.index as i32,
             InodeEvent::DeleteContent(event) => index = event.index as i32,
             InodeEvent::ModifyStat(_) => index = -1,
+            InodeEvent::SetXattr(_) => index = -1,
             InodeEvent::None => (),
         }
         index
@@ -78,8 +83,8 @@ impl InodeEvent {
 #[derive(Clone, PartialEq, Debug)]
 pub struct AddContentInodeEvent {
     pub index: u32,
-    pub offset: u32,
-    pub len: u32,
+    pub offset: u64,
+    pub len: u64,
     pub size: u32,
     pub content: Vec<u8>,
 }
@@ -87,8 +92,8 @@ pub struct AddContentInodeEvent {
 #[derive(Clone, PartialEq, Debug)]
 pub struct TruncateContentInodeEvent {
     pub index: u32,
-    pub offset: u32,
-    pub len: u32,
+    pub offset: u64,
+    pub len: u64,
     pub size: u32,
     pub o_size: u32,
     pub v_address: u32,
@@ -97,7 +102,7 @@ pub struct TruncateContentInodeEvent {
 #[derive(Clone, PartialEq, Debug)]
 pub struct ChangeContentInodeEvent {
     pub index: u32,
-    pub offset: u32,
+    pub offset: u64,
     pub v_address: u32,
 }
 
@@ -112,8 +117,18 @@ pub struct DeleteContentInodeEvent {
 pub struct ModifyInodeStatInodeEvent {
     pub file_type: inode::InodeFileType,
     pub ino: u32,
-    pub size: u32,
+    pub size: u64,
     pub uid: u32,
     pub gid: u16,
     pub n_link: u8,
-}
\ No newline at end of file
+    pub mode: u16,
+    pub atime: u64,
+    pub mtime: u64,
+    pub ctime: u64,
+}
+
+// 替换全部扩展属性，content为编码后的内容
+#[derive(Clone, PartialEq, Debug)]
+pub struct SetXattrInodeEvent {
+    pub content: Vec<u8>,
+}
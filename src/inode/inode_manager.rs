@@ -1,13 +1,17 @@
 use std::cell::RefCell;
 use std::sync::{Arc, Mutex};
 use crate::core::core_manager;
+use crate::core::options;
+use crate::core::error::FsError;
 use crate::inode::inode::Inode;
+use crate::util::cache;
 
 pub struct InodeManager {
     pub size: usize,
     pub capacity: usize,
     pub core_manager: CoreLink,
     pub inode_buffer: Vec<InodeLink>,
+    pub inode_cache: Box<dyn cache::Cache<u32, InodeLink>>, // 引用计数归零后的Inode
     pub lock: Mutex<bool>,
 }
 
@@ -26,10 +30,20 @@ impl InodeManager {
             capacity: capacity as usize,
             core_manager: Arc::new(RefCell::new(core_manager::CoreManager::new())),
             inode_buffer: buf,
+            inode_cache: cache::new_cache(cache::CachePolicy::LRU, capacity as usize),
             lock: Mutex::new(false),
         }
     }
 
+    pub fn mount(&mut self) -> Result<(), FsError> {
+        self.mount_with_options(options::MountOptions::new())
+    }
+
+    pub fn mount_with_options(&mut self, options: options::MountOptions) -> Result<(), FsError> {
+        self.inode_cache = cache::new_cache(options.inode_cache_policy, self.capacity);
+        self.core_manager.borrow_mut().mount_with_options(options)
+    }
+
     // Allocate an inode on device dev.
     // Mark it as allocated by giving it type type.
     // Returns an unlocked but allocated and referenced inode.
@@ -45,6 +59,7 @@ impl InodeManager {
             panic!("InodeManager: alloc no spare cache to store");
         }
         let mut inode = self.core_manager.borrow_mut().allocate_inode();
+        self.inode_cache.remove(&inode.ino);
         inode.ref_cnt = 1;
         let link = Arc::new(RefCell::new(inode));
         link.borrow_mut().core = Some(Arc::clone(&self.core_manager));
@@ -69,6 +84,16 @@ impl InodeManager {
         if empty_index == -1 {
             panic!("InodeManager: get no spare cache to store");
         }
+        if let Some(link) = self.inode_cache.remove(&ino) {
+            link.borrow_mut().ref_cnt = 1;
+            for (index, ip) in self.inode_buffer.iter().enumerate() {
+                if Arc::ptr_eq(ip, &link) {
+                    empty_index = index as i32;
+                }
+            }
+            self.inode_buffer[empty_index as usize] = Arc::clone(&link);
+            return Some(link);
+        }
         let mut inode = self.core_manager.borrow_mut().get_inode(ino);
         inode.ref_cnt = 1;
         let link = Arc::new(RefCell::new(inode));
@@ -93,6 +118,26 @@ impl InodeManager {
             panic!("InodeManager: put not valid inode");
         }
         inode.borrow_mut().ref_cnt -= 1;
+        if inode.borrow().ref_cnt == 0 {
+            // 写回atime，失败时保持dirty，之后sync时重试
+            let _ = inode.borrow_mut().write_back_atime();
+            let ino = inode.borrow().ino;
+            self.inode_cache.put(ino, inode);
+        }
+    }
+
+    // 写回所有Inode中只在内存中更新的atime，再将CoreManager中缓存的数据写入磁盘
+    pub fn sync(&mut self) -> Result<(), FsError> {
+        for ip in self.inode_buffer.iter() {
+            ip.borrow_mut().write_back_atime()?;
+        }
+        self.core_manager.borrow_mut().sync();
+        Ok(())
+    }
+
+    // 没有前台IO时由上层调用，执行一次后台GC
+    pub fn idle(&mut self) {
+        self.core_manager.borrow_mut().background_gc();
     }
 }
 
@@ -103,7 +148,7 @@ mod test {
     #[test]
     fn basics() {
         let mut manager = InodeManager::new();
-        manager.core_manager.borrow_mut().mount();
+        manager.core_manager.borrow_mut().mount().unwrap();
         let link = manager.i_alloc();
         assert_eq!(link.unwrap().borrow().ino, 1);
         let link = manager.i_alloc();
@@ -117,4 +162,58 @@ mod test {
         let link = manager.i_get(2);
         assert_eq!(link.as_ref().unwrap().borrow().ref_cnt, 3);
     }
+
+    #[test]
+    fn cache() {
+        let mut manager = InodeManager::new();
+        let mut options = options::MountOptions::new();
+        options.inode_cache_policy = cache::CachePolicy::CLOCK;
+        manager.mount_with_options(options).unwrap();
+        let link = manager.i_alloc().unwrap();
+        link.borrow_mut().uid = 7;
+        manager.i_put(Arc::clone(&link));
+        assert!(manager.inode_cache.contains_key(&1));
+        // 从缓存中复用，不重新从CoreManager加载
+        let link = manager.i_get(1).unwrap();
+        assert_eq!(link.borrow().uid, 7);
+        assert_eq!(link.borrow().ref_cnt, 1);
+        assert!(!manager.inode_cache.contains_key(&1));
+        let mut count = 0;
+        for ip in manager.inode_buffer.iter() {
+            if Arc::ptr_eq(ip, &link) {
+                count += 1;
+            }
+        }
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn idle() {
+        let mut manager = InodeManager::new();
+        let mut options = options::MountOptions::new();
+        options.gc_low_watermark = u32::MAX;
+        options.gc_high_watermark = u32::MAX;
+        manager.mount_with_options(options).unwrap();
+        // 写入时不执行后台GC，只在空闲时调用
+        let link = manager.i_alloc().unwrap();
+        link.borrow_mut().write(0, 10, &vec![1; 10]).unwrap();
+        assert_eq!(manager.core_manager.borrow().get_background_gc_stat().wake_num, 0);
+        manager.idle();
+        assert_eq!(manager.core_manager.borrow().get_background_gc_stat().wake_num, 1);
+    }
+    #[test]
+    fn atime() {
+        let mut manager = InodeManager::new();
+        manager.core_manager.borrow_mut().mount().unwrap();
+        let link = manager.i_alloc().unwrap();
+        link.borrow_mut().write(0, 10, &vec![1; 10]).unwrap();
+        let mut buf = vec![0; 10];
+        link.borrow_mut().read(0, 10, &mut buf);
+        let atime = link.borrow().atime;
+        assert_ne!(manager.core_manager.borrow_mut().get_inode(1).atime, atime);
+        // 引用全部释放时写回atime
+        manager.i_put(Arc::clone(&link));
+        assert!(!link.borrow().dirty);
+        assert_eq!(manager.core_manager.borrow_mut().get_inode(1).atime, atime);
+    }
 }
\ No newline at end of file
@@ -15,7 +15,7 @@ pub enum InodeFileType {
 pub struct InodeStat {
     pub file_type: InodeFileType,
     pub ino: u32,
-    pub size: u32,
+    pub size: u64,
     pub uid: u32,
     pub gid: u16,
     pub ref_cnt: u8,
@@ -25,8 +25,8 @@ pub struct InodeStat {
 #[derive(Copy, Clone)]
 pub struct InodeEntry {
     pub valid: bool,
-    pub offset: u32,
-    pub len: u32,           // 以Byte为单位
+    pub offset: u64,
+    pub len: u64,           // 以Byte为单位
     pub size: u32,          // 以Page为单位
     pub address: u32,
     pub compress_len: u32,  // 压缩后的长度
@@ -37,7 +37,7 @@ pub struct Inode {
     pub valid: bool,
     pub file_type: InodeFileType,
     pub ino: u32,
-    pub size: u32,
+    pub size: u64,
     pub uid: u32,
     pub gid: u16,
     pub ref_cnt: u8,
@@ -68,7 +68,7 @@ impl Inode {
         self.read(0, self.size, buf)
     }
 
-    pub fn read(&mut self, offset: u32, len: u32, buf: &mut Vec<u8>) -> i32 {
+    pub fn read(&mut self, offset: u64, len: u64, buf: &mut Vec<u8>) -> i32 {
         buf.clear();
         let mut len = len;
         let mut count = 0;
@@ -103,7 +103,7 @@ impl Inode {
         count
     }
 
-    pub fn write(&mut self, offset: u32, len: u32, buf: &Vec<u8>) -> bool {
+    pub fn write(&mut self, offset: u64, len: u64, buf: &Vec<u8>) -> bool {
         let mut event_group = inode_event::InodeEventGroup::new();
         // event_group.inode = self.copy_inode();
         let mut index = 0;
@@ -130,8 +130,8 @@ impl Inode {
             } else if entry.offset >= new_entry.offset + new_entry.len {
                 continue
             } else {
-                let valid_prev = max(0, new_entry.offset as i32 - entry.offset as i32) as u32;
-                let valid_suffix = max(0, entry.offset as i32 + entry.len as i32 - new_entry.offset as i32 - new_entry.len as i32) as u32;
+                let valid_prev = max(0, new_entry.offset as i64 - entry.offset as i64) as u64;
+                let valid_suffix = max(0, entry.offset as i64 + entry.len as i64 - new_entry.offset as i64 - new_entry.len as i64) as u64;
                 if valid_prev == 0 {
                     let event = inode_event::DeleteContentInodeEvent {
                         index,
@@ -147,7 +147,7 @@ impl Inode {
                         index,
                         offset: entry.offset,
                         len: valid_prev,
-                        size: valid_prev / 4096 + 1,
+                        size: (valid_prev / 4096 + 1) as u32,
                         o_size: entry.size,
                         v_address: entry.address,
                     };
@@ -159,7 +159,7 @@ impl Inode {
                         index,
                         offset,
                         len,
-                        size: len / 4096 + 1,
+                        size: (len / 4096 + 1) as u32,
                         content: buf.clone(),
                     };
                     event_group.events.push(inode_event::InodeEvent::AddContent(event));
@@ -172,7 +172,7 @@ impl Inode {
                         offset: entry.offset + entry.len - valid_suffix,
                         len: valid_suffix,
                         valid: false,
-                        size: valid_suffix / 4096 + 1,
+                        size: (valid_suffix / 4096 + 1) as u32,
                         address: 0,
                         compress_len: 0,
                         compress_type: compress::CompressType::None,
@@ -186,7 +186,7 @@ impl Inode {
                 index: self.data.len() as u32,
                 offset: new_entry.offset,
                 len: new_entry.len,
-                size: len / 4096 + 1,
+                size: (len / 4096 + 1) as u32,
                 content: buf.clone(),
             };
             event_group.events.push(inode_event::InodeEvent::AddContent(event));
@@ -225,20 +225,20 @@ impl Inode {
 }
 
 impl Inode {
-    pub fn read_decompress_entry(&mut self, entry: &InodeEntry, start: u32, end: u32) -> Vec<u8> {
+    pub fn read_decompress_entry(&mut self, entry: &InodeEntry, start: u64, end: u64) -> Vec<u8> {
         if entry.compress_type == compress::CompressType::None {
             self.read_entry(entry, start, end)
         } else {
-            let data = self.read_entry(entry, 0, entry.compress_len);
+            let data = self.read_entry(entry, 0, entry.compress_len as u64);
             let data = compress::CompressManager::decode(&data, entry.compress_type);
             data[start as usize..end as usize].to_vec()
         }
     }
 
-    pub fn read_entry(&mut self, entry: &InodeEntry, start: u32, end: u32) -> Vec<u8> {
-        let start_index = start / 4096;
+    pub fn read_entry(&mut self, entry: &InodeEntry, start: u64, end: u64) -> Vec<u8> {
+        let start_index = (start / 4096) as u32;
         let start_off = start % 4096;
-        let end_index = (end - 1) / 4096;
+        let end_index = ((end - 1) / 4096) as u32;
         let end_off = (end - 1) % 4096;
         let mut pages = vec![];
         for i in start_index..end_index + 1 {
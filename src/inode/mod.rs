@@ -1,4 +1,6 @@
 pub mod inode;
 pub mod inode_manager;
 pub mod inode_event;
-pub mod inode_test;
\ No newline at end of file
+pub mod inode_test;
+pub mod mode;
+pub mod xattr;
\ No newline at end of file
@@ -0,0 +1,42 @@
+use crate::inode::inode::InodeFileType;
+
+// 16位的mode，高4位为文件类型，之后依次为setuid、setgid、sticky与三组rwx权限，与POSIX的st_mode一致
+pub const S_IFMT: u16 = 0o170000;
+pub const S_IFLNK: u16 = 0o120000;
+pub const S_IFREG: u16 = 0o100000;
+pub const S_IFDIR: u16 = 0o040000;
+pub const S_ISUID: u16 = 0o4000;
+pub const S_ISGID: u16 = 0o2000;
+pub const S_ISVTX: u16 = 0o1000;
+// 可以通过chmod修改的部分
+pub const PERM_MASK: u16 = 0o7777;
+
+// 访问检查时请求的权限
+pub const R_OK: u16 = 4;
+pub const W_OK: u16 = 2;
+pub const X_OK: u16 = 1;
+
+// HardLink没有对应的类型，与普通文件相同
+pub fn type_bits(file_type: InodeFileType) -> u16 {
+    match file_type {
+        InodeFileType::File | InodeFileType::HardLink => S_IFREG,
+        InodeFileType::Directory => S_IFDIR,
+        InodeFileType::SoftLink => S_IFLNK,
+    }
+}
+
+pub fn make_mode(file_type: InodeFileType, perm: u16) -> u16 {
+    type_bits(file_type) | (perm & PERM_MASK)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        assert_eq!(make_mode(InodeFileType::Directory, 0o1755), 0o41755);
+        assert_eq!(make_mode(InodeFileType::File, 0o170644) & S_IFMT, S_IFREG);
+        assert_eq!(make_mode(InodeFileType::SoftLink, 0o777) & S_IFMT, S_IFLNK);
+    }
+}
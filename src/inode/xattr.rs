@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+use crate::core::error::FsError;
+
+// 编码后不超过该长度时内联保存在Inode中，否则保存在单独的Extent中
+pub const INLINE_SIZE: usize = 256;
+pub const NAME_MAX: usize = 255;
+pub const VALUE_MAX: usize = 65536;
+// 一个Inode所有扩展属性编码后的总长度上限
+pub const TOTAL_MAX: usize = 1 << 20;
+// Extent中的Page在PIT中记录的偏移从该值开始，与文件数据区分
+pub const EXTENT_OFFSET: u64 = 1 << 63;
+
+// set_xattr的flags，与setxattr一致
+pub const XATTR_CREATE: u32 = 1;
+pub const XATTR_REPLACE: u32 = 2;
+
+const NAMESPACES: [&str; 4] = ["user.", "security.", "trusted.", "system."];
+
+pub type XattrMap = BTreeMap<String, Vec<u8>>;
+
+// 名字需要带有支持的命名空间前缀
+pub fn check_name(name: &str) -> Result<(), FsError> {
+    if name.len() > NAME_MAX {
+        return Err(FsError::OutOfRange);
+    }
+    if !NAMESPACES.iter().any(|namespace| name.len() > namespace.len() && name.starts_with(namespace)) {
+        return Err(FsError::NotSupported);
+    }
+    Ok(())
+}
+
+// 每项依次为名字长度(1字节)、值长度(4字节)、名字与值
+pub fn encode(map: &XattrMap) -> Vec<u8> {
+    let mut bytes = vec![];
+    for (name, value) in map.iter() {
+        bytes.push(name.len() as u8);
+        bytes.extend_from_slice(&(value.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.extend_from_slice(value);
+    }
+    bytes
+}
+
+pub fn decode(bytes: &[u8]) -> XattrMap {
+    let mut map = BTreeMap::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let name_len = bytes[index] as usize;
+        let value_len = u32::from_be_bytes(bytes[index + 1..index + 5].try_into().unwrap()) as usize;
+        index += 5;
+        let name = String::from_utf8(bytes[index..index + name_len].to_vec()).unwrap();
+        index += name_len;
+        map.insert(name, bytes[index..index + value_len].to_vec());
+        index += value_len;
+    }
+    map
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        assert_eq!(check_name("user.mime_type"), Ok(()));
+        assert_eq!(check_name("user."), Err(FsError::NotSupported));
+        assert_eq!(check_name("os2.name"), Err(FsError::NotSupported));
+        assert_eq!(check_name(&format!("user.{}", "a".repeat(NAME_MAX))), Err(FsError::OutOfRange));
+        let mut map = XattrMap::new();
+        map.insert("security.selinux".to_string(), b"system_u:object_r:bin_t".to_vec());
+        map.insert("user.empty".to_string(), vec![]);
+        assert_eq!(decode(&encode(&map)), map);
+        assert!(decode(&[]).is_empty());
+    }
+}
@@ -1,41 +1,35 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use crate::kv::kv::{self, KV};
+use crate::kv::ino_map;
 use crate::kv::raw_inode;
 
 pub struct FakeKV {
-    pub next_ino: u32,
-    pub map: HashMap<u32, raw_inode::RawInode>,
+    pub ino_map: ino_map::InoMap,
+    pub map: BTreeMap<u32, raw_inode::RawInode>,
 }
 
 impl FakeKV {
     pub fn new() -> FakeKV {
         FakeKV {
-            next_ino: 1,
-            map: HashMap::new(),
+            ino_map: ino_map::InoMap::new(),
+            map: BTreeMap::new(),
         }
     }
+}
+
+impl KV for FakeKV {
+    fn has_inode(&self, ino: u32) -> bool {
+        self.map.contains_key(&ino)
+    }
 
-    pub fn get_inode(&self, ino: u32) -> raw_inode::RawInode {
+    fn get_inode(&self, ino: u32) -> raw_inode::RawInode {
         if !self.map.contains_key(&ino) {
             panic!("FakeKV: get no that inode");
         }
-        let raw_inode = self.map.get(&ino).unwrap();
-        let mut data = vec![];
-        for entry in raw_inode.data.iter() {
-            data.push(*entry);
-        }
-        raw_inode::RawInode {
-            ino,
-            data,
-            uid: raw_inode.uid,
-            gid: raw_inode.gid,
-            size: raw_inode.size,
-            n_link: raw_inode.n_link,
-            ref_cnt: raw_inode.ref_cnt,
-            file_type: raw_inode.file_type,
-        }
+        self.map.get(&ino).unwrap().clone()
     }
 
-    pub fn update_inode(&mut self, inode: raw_inode::RawInode) {
+    fn update_inode(&mut self, inode: raw_inode::RawInode) {
         let ino = inode.ino;
         if !self.map.contains_key(&ino) {
             panic!("FakeKV: update no that inode");
@@ -43,39 +37,42 @@ impl FakeKV {
         *self.map.get_mut(&ino).unwrap() = inode;
     }
 
-    pub fn delete_inode(&mut self, ino: u32) {
-        if !self.map.contains_key(&ino) {
-            panic!("FakeKV: delete no that inode");
+    fn delete_inode(&mut self, ino: u32) {
+        match self.map.remove(&ino) {
+            Some(raw_inode) => self.ino_map.release(ino, raw_inode.generation),
+            None => panic!("FakeKV: delete no that inode"),
         }
-        self.map.remove(&ino);
     }
 
-    pub fn allocate_inode(&mut self) -> raw_inode::RawInode {
-        let ino = self.next_ino;
-        self.next_ino += 1;
-        let raw_inode = raw_inode::RawInode {
-            ino,
-            uid: 0,
-            gid: 0,
-            size: 0,
-            n_link: 1,
-            ref_cnt: 0,
-            file_type: 0,
-            data: vec![],
-        };
-        self.map.insert(ino, raw_inode);
-        let raw_inode = raw_inode::RawInode {
-            ino,
-            uid: 0,
-            gid: 0,
-            size: 0,
-            n_link: 1,
-            ref_cnt: 0,
-            file_type: 0,
-            data: vec![],
-        };
+    fn allocate_inode(&mut self) -> raw_inode::RawInode {
+        let (ino, generation) = self.ino_map.allocate();
+        let raw_inode = kv::new_raw_inode(ino, generation);
+        self.map.insert(ino, raw_inode.clone());
         raw_inode
     }
+
+    fn scan_inode(&self, start: u32, limit: usize) -> Vec<raw_inode::RawInode> {
+        self.map.range(start..).take(limit).map(|(_, raw_inode)| raw_inode.clone()).collect()
+    }
+
+    // 先检查所有删除操作，删除不存在的Inode时panic，不影响原有数据
+    fn write_batch(&mut self, batch: kv::WriteBatch) {
+        if !batch.check(|ino| self.map.contains_key(&ino)) {
+            panic!("FakeKV: delete no that inode");
+        }
+        for op in batch.ops {
+            match op {
+                kv::BatchOp::Put(raw_inode) => {
+                    self.ino_map.reserve(raw_inode.ino);
+                    self.map.insert(raw_inode.ino, raw_inode);
+                }
+                kv::BatchOp::Delete(ino) => {
+                    let raw_inode = self.map.remove(&ino).unwrap();
+                    self.ino_map.release(ino, raw_inode.generation);
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -85,7 +82,7 @@ mod test {
     #[test]
     fn basics() {
         let mut kv = FakeKV::new();
-        
+
         let mut inode = kv.allocate_inode();
         let ino = inode.ino;
         inode.gid = 100;
@@ -95,5 +92,53 @@ mod test {
         let inode = kv.get_inode(ino);
         assert_eq!(inode.gid, 100);
         assert_eq!(inode.file_type, 1);
+
+        let mut batch = kv::WriteBatch::new();
+        batch.put(kv::new_raw_inode(5, 0));
+        batch.delete(ino);
+        kv.write_batch(batch);
+        assert_eq!(kv.has_inode(ino), false);
+        // 删除的ino被复用，generation加一
+        let inode = kv.allocate_inode();
+        assert_eq!((inode.ino, inode.generation), (ino, 1));
+        // 写入ino 5时跳过的ino先被分配
+        for ino in 2..5 {
+            assert_eq!(kv.allocate_inode().ino, ino);
+        }
+        assert_eq!(kv.allocate_inode().ino, 6);
+        let inodes = kv.scan_inode(2, 10);
+        assert_eq!(inodes.iter().map(|inode| inode.ino).collect::<Vec<u32>>(), vec![2, 3, 4, 5, 6]);
+
+        // 检查删除操作时考虑batch中之前的操作
+        let mut batch = kv::WriteBatch::new();
+        batch.put(kv::new_raw_inode(20, 0));
+        batch.delete(20);
+        assert!(batch.check(|ino| kv.has_inode(ino)));
+        batch.delete(20);
+        assert!(!batch.check(|ino| kv.has_inode(ino)));
+        let mut batch = kv::WriteBatch::new();
+        batch.delete(5);
+        batch.put(kv::new_raw_inode(5, 1));
+        kv.write_batch(batch);
+        assert_eq!(kv.get_inode(5).generation, 1);
+    }
+
+    #[test]
+    fn iter() {
+        let mut kv = FakeKV::new();
+        for _ in 0..kv::SCAN_CHUNK_SIZE * 2 + 10 {
+            kv.allocate_inode();
+        }
+        // 删除的Inode不出现在遍历结果中，跨越多次scan时仍然有序
+        let mut batch = kv::WriteBatch::new();
+        for ino in (1..kv::SCAN_CHUNK_SIZE as u32 * 2).step_by(2) {
+            batch.delete(ino);
+        }
+        kv.write_batch(batch);
+        let inos: Vec<u32> = kv.iter_inode().map(|inode| inode.ino).collect();
+        assert_eq!(inos.len(), kv.map.len());
+        assert_eq!(inos[0], 2);
+        assert!(inos.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(*inos.last().unwrap(), kv::SCAN_CHUNK_SIZE as u32 * 2 + 10);
     }
-}
\ No newline at end of file
+}
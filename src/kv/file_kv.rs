@@ -0,0 +1,323 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use crate::kv::kv::{self, KV};
+use crate::kv::ino_map;
+use crate::kv::raw_inode;
+
+const OP_PUT: u8 = 1;
+const OP_DELETE: u8 = 2;
+const OP_NEXT_INO: u8 = 3;
+const OP_FREE: u8 = 4;
+const OP_VERSION: u8 = 5;
+
+// 日志中的记录数超过该值且超过存活Inode数量的两倍时进行压缩
+pub const COMPACT_THRESHOLD: usize = 1024;
+
+// 持久化到宿主文件系统的KV，内存中保存全部Inode，修改以记录的形式追加到日志文件
+// 每条记录为长度、校验和与若干操作，一次write_batch对应一条记录
+// 打开时依次重放记录，末尾不完整或校验失败的记录视为未写入并截断
+// 删除记录同时释放ino，重放后得到与写入时相同的空闲ino与generation
+// 第一条记录以格式版本开头，版本与raw_inode::FORMAT_VERSION不同的日志无法打开
+pub struct FileKV {
+    pub path: PathBuf,
+    pub file: fs::File,
+    pub ino_map: ino_map::InoMap,
+    pub map: BTreeMap<u32, raw_inode::RawInode>,
+    pub record_num: usize,
+}
+
+impl FileKV {
+    pub fn open(path: &str) -> FileKV {
+        let path = PathBuf::from(path);
+        let bytes = fs::read(&path).unwrap_or_default();
+        let mut map = BTreeMap::new();
+        let mut ino_map = ino_map::InoMap::new();
+        let mut record_num = 0;
+        let mut offset = 0;
+        while let Some((payload, len)) = FileKV::decode_record(&bytes[offset..]) {
+            if record_num == 0 && payload.first() != Some(&OP_VERSION) {
+                panic!("FileKV: open log without format version");
+            }
+            FileKV::replay(payload, &mut map, &mut ino_map);
+            offset += len;
+            record_num += 1;
+        }
+        let file = fs::OpenOptions::new().create(true).append(true).open(&path)
+            .unwrap_or_else(|_| panic!("FileKV: open {} failed", path.display()));
+        if offset < bytes.len() {
+            file.set_len(offset as u64).unwrap();
+        }
+        let mut kv = FileKV {
+            path,
+            file,
+            ino_map,
+            map,
+            record_num,
+        };
+        if kv.record_num == 0 {
+            let mut payload = vec![];
+            FileKV::encode_version(&mut payload);
+            kv.append(&payload);
+        }
+        kv
+    }
+
+    // 将当前所有Inode写成一条记录，替换原有的日志
+    pub fn compact(&mut self) {
+        let mut payload = vec![];
+        FileKV::encode_version(&mut payload);
+        for raw_inode in self.map.values() {
+            FileKV::encode_put(&mut payload, raw_inode);
+        }
+        for (ino, generation) in self.ino_map.free.iter() {
+            FileKV::encode_free(&mut payload, *ino, *generation);
+        }
+        FileKV::encode_next_ino(&mut payload, self.ino_map.next_ino);
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let mut file = fs::File::create(&tmp_path).unwrap();
+        file.write_all(&FileKV::encode_record(&payload)).unwrap();
+        file.sync_all().unwrap();
+        fs::rename(&tmp_path, &self.path).unwrap();
+        self.file = fs::OpenOptions::new().append(true).open(&self.path).unwrap();
+        self.record_num = 1;
+    }
+
+    fn append(&mut self, payload: &[u8]) {
+        self.file.write_all(&FileKV::encode_record(payload)).unwrap();
+        self.file.sync_data().unwrap();
+        self.record_num += 1;
+        if self.record_num > COMPACT_THRESHOLD && self.record_num > self.map.len() * 2 {
+            self.compact();
+        }
+    }
+
+    fn replay(payload: &[u8], map: &mut BTreeMap<u32, raw_inode::RawInode>, ino_map: &mut ino_map::InoMap) {
+        let mut offset = 0;
+        while offset < payload.len() {
+            let op = payload[offset];
+            offset += 1;
+            match op {
+                OP_PUT => {
+                    let (raw_inode, len) = raw_inode::RawInode::decode(&payload[offset..]).unwrap();
+                    ino_map.reserve(raw_inode.ino);
+                    map.insert(raw_inode.ino, raw_inode);
+                    offset += len;
+                }
+                OP_DELETE => {
+                    let ino = read_u32(payload, offset);
+                    if let Some(raw_inode) = map.remove(&ino) {
+                        ino_map.release(ino, raw_inode.generation);
+                    }
+                    offset += 4;
+                }
+                OP_NEXT_INO => {
+                    ino_map.next_ino = ino_map.next_ino.max(read_u32(payload, offset));
+                    offset += 4;
+                }
+                OP_FREE => {
+                    ino_map.release(read_u32(payload, offset), read_u32(payload, offset + 4));
+                    offset += 8;
+                }
+                OP_VERSION => {
+                    let version = read_u32(payload, offset);
+                    if version != raw_inode::FORMAT_VERSION {
+                        panic!("FileKV: unsupported format version {}", version);
+                    }
+                    offset += 4;
+                }
+                _ => panic!("FileKV: replay unknown op {}", op),
+            }
+        }
+    }
+
+    fn encode_put(payload: &mut Vec<u8>, raw_inode: &raw_inode::RawInode) {
+        payload.push(OP_PUT);
+        payload.extend_from_slice(&raw_inode.encode());
+    }
+
+    fn encode_delete(payload: &mut Vec<u8>, ino: u32) {
+        payload.push(OP_DELETE);
+        payload.extend_from_slice(&ino.to_be_bytes());
+    }
+
+    fn encode_next_ino(payload: &mut Vec<u8>, next_ino: u32) {
+        payload.push(OP_NEXT_INO);
+        payload.extend_from_slice(&next_ino.to_be_bytes());
+    }
+
+    fn encode_free(payload: &mut Vec<u8>, ino: u32, generation: u32) {
+        payload.push(OP_FREE);
+        payload.extend_from_slice(&ino.to_be_bytes());
+        payload.extend_from_slice(&generation.to_be_bytes());
+    }
+
+    fn encode_version(payload: &mut Vec<u8>) {
+        payload.push(OP_VERSION);
+        payload.extend_from_slice(&raw_inode::FORMAT_VERSION.to_be_bytes());
+    }
+
+    fn encode_record(payload: &[u8]) -> Vec<u8> {
+        let mut record = vec![];
+        record.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        record.extend_from_slice(&checksum(payload).to_be_bytes());
+        record.extend_from_slice(payload);
+        record
+    }
+
+    // 返回记录的内容与整条记录的长度
+    fn decode_record(bytes: &[u8]) -> Option<(&[u8], usize)> {
+        if bytes.len() < 8 {
+            return None;
+        }
+        let len = read_u32(bytes, 0) as usize;
+        if bytes.len() < 8 + len {
+            return None;
+        }
+        let payload = &bytes[8..8 + len];
+        if checksum(payload) != read_u32(bytes, 4) {
+            return None;
+        }
+        Some((payload, 8 + len))
+    }
+}
+
+impl KV for FileKV {
+    fn has_inode(&self, ino: u32) -> bool {
+        self.map.contains_key(&ino)
+    }
+
+    fn get_inode(&self, ino: u32) -> raw_inode::RawInode {
+        match self.map.get(&ino) {
+            Some(raw_inode) => raw_inode.clone(),
+            None => panic!("FileKV: get no that inode"),
+        }
+    }
+
+    fn update_inode(&mut self, raw_inode: raw_inode::RawInode) {
+        if !self.map.contains_key(&raw_inode.ino) {
+            panic!("FileKV: update no that inode");
+        }
+        let mut payload = vec![];
+        FileKV::encode_put(&mut payload, &raw_inode);
+        self.append(&payload);
+        self.map.insert(raw_inode.ino, raw_inode);
+    }
+
+    fn delete_inode(&mut self, ino: u32) {
+        if !self.map.contains_key(&ino) {
+            panic!("FileKV: delete no that inode");
+        }
+        let mut payload = vec![];
+        FileKV::encode_delete(&mut payload, ino);
+        self.append(&payload);
+        let raw_inode = self.map.remove(&ino).unwrap();
+        self.ino_map.release(ino, raw_inode.generation);
+    }
+
+    fn allocate_inode(&mut self) -> raw_inode::RawInode {
+        let (ino, generation) = self.ino_map.allocate();
+        let raw_inode = kv::new_raw_inode(ino, generation);
+        let mut payload = vec![];
+        FileKV::encode_put(&mut payload, &raw_inode);
+        FileKV::encode_next_ino(&mut payload, self.ino_map.next_ino);
+        self.append(&payload);
+        self.map.insert(raw_inode.ino, raw_inode.clone());
+        raw_inode
+    }
+
+    fn scan_inode(&self, start: u32, limit: usize) -> Vec<raw_inode::RawInode> {
+        self.map.range(start..).take(limit).map(|(_, raw_inode)| raw_inode.clone()).collect()
+    }
+
+    // 整个batch写成一条记录，写入前检查所有删除操作
+    fn write_batch(&mut self, batch: kv::WriteBatch) {
+        if !batch.check(|ino| self.map.contains_key(&ino)) {
+            panic!("FileKV: delete no that inode");
+        }
+        let mut payload = vec![];
+        for op in batch.ops.iter() {
+            match op {
+                kv::BatchOp::Put(raw_inode) => FileKV::encode_put(&mut payload, raw_inode),
+                kv::BatchOp::Delete(ino) => FileKV::encode_delete(&mut payload, *ino),
+            }
+        }
+        // 先修改内存中的数据，append触发压缩时写入的是执行后的结果
+        for op in batch.ops {
+            match op {
+                kv::BatchOp::Put(raw_inode) => {
+                    self.ino_map.reserve(raw_inode.ino);
+                    self.map.insert(raw_inode.ino, raw_inode);
+                }
+                kv::BatchOp::Delete(ino) => {
+                    let raw_inode = self.map.remove(&ino).unwrap();
+                    self.ino_map.release(ino, raw_inode.generation);
+                }
+            }
+        }
+        self.append(&payload);
+    }
+}
+
+fn read_u32(bytes: &[u8], index: usize) -> u32 {
+    u32::from_be_bytes([bytes[index], bytes[index + 1], bytes[index + 2], bytes[index + 3]])
+}
+
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(17u32, |hash, byte| hash.wrapping_mul(31).wrapping_add(*byte as u32))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        let path = std::env::temp_dir().join(format!("sffs_file_kv_{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+
+        let mut kv = FileKV::open(path);
+        let mut inode = kv.allocate_inode();
+        inode.size = (6 << 30) + 100;
+        inode.data.push(raw_inode::RawEntry { len: 100, size: 1, offset: 6 << 30, address: 7 });
+        inode.atime = 5;
+        inode.xattr = vec![1, 2, 3];
+        inode.xattr_entry = Some(raw_inode::RawEntry { len: 5000, size: 2, offset: 1 << 63, address: 9 });
+        let btime = inode.btime;
+        kv.update_inode(inode);
+        let ino = kv.allocate_inode().ino;
+        let mut batch = kv::WriteBatch::new();
+        batch.put(kv::new_raw_inode(10, 0));
+        batch.delete(ino);
+        kv.write_batch(batch);
+
+        // 重新打开后恢复，末尾写了一半的记录被丢弃
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(&[0, 0, 0, 100, 1, 2]).unwrap();
+        let mut kv = FileKV::open(path);
+        assert_eq!((kv.get_inode(1).data[0].offset, kv.get_inode(1).data[0].address), (6 << 30, 7));
+        assert_eq!(kv.has_inode(ino), false);
+        assert_eq!(kv.scan_inode(0, 10).iter().map(|inode| inode.ino).collect::<Vec<u32>>(), vec![1, 10]);
+        assert_eq!(kv.ino_map.get_free_num(), 8);
+
+        // 空闲的ino在压缩后保留，复用时generation加一
+        kv.compact();
+        let mut kv = FileKV::open(path);
+        assert_eq!(kv.record_num, 1);
+        assert_eq!(kv.get_inode(1).size, (6 << 30) + 100);
+        assert_eq!((kv.get_inode(1).atime, kv.get_inode(1).btime), (5, btime));
+        assert_eq!(kv.get_inode(1).xattr, vec![1, 2, 3]);
+        assert_eq!(kv.get_inode(1).xattr_entry.map(|entry| (entry.len, entry.address)), Some((5000, 9)));
+        let inode = kv.allocate_inode();
+        assert_eq!((inode.ino, inode.generation), (ino, 1));
+        // 写入ino 10时跳过的ino先被分配
+        for ino in 3..10 {
+            assert_eq!(kv.allocate_inode().ino, ino);
+        }
+        assert_eq!(kv.allocate_inode().ino, 11);
+        fs::remove_file(path).unwrap();
+    }
+}
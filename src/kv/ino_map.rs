@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+
+// 管理ino的分配，删除的ino记录最后一次使用的generation，优先复用编号小的ino
+// 复用时generation加一，持有旧generation的引用可以据此判断Inode已经失效
+#[derive(Clone)]
+pub struct InoMap {
+    pub next_ino: u32,
+    pub free: BTreeMap<u32, u32>,
+}
+
+impl InoMap {
+    pub fn new() -> InoMap {
+        InoMap {
+            next_ino: 1,
+            free: BTreeMap::new(),
+        }
+    }
+
+    // 返回(ino, generation)
+    pub fn allocate(&mut self) -> (u32, u32) {
+        if let Some((ino, generation)) = self.free.pop_first() {
+            return (ino, generation.wrapping_add(1));
+        }
+        if self.next_ino == u32::MAX {
+            panic!("InoMap: allocate with no free ino");
+        }
+        self.next_ino += 1;
+        (self.next_ino - 1, 0)
+    }
+
+    pub fn release(&mut self, ino: u32, generation: u32) {
+        self.free.insert(ino, generation);
+    }
+
+    // 直接写入指定ino的Inode时，将其标记为已使用
+    // 跳过的ino放入free，记为u32::MAX使其分配时generation为0
+    pub fn reserve(&mut self, ino: u32) {
+        self.free.remove(&ino);
+        for skipped in self.next_ino..ino {
+            self.free.insert(skipped, u32::MAX);
+        }
+        self.next_ino = self.next_ino.max(ino + 1);
+    }
+
+    pub fn get_free_num(&self) -> usize {
+        self.free.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        let mut map = InoMap::new();
+        assert_eq!(map.allocate(), (1, 0));
+        assert_eq!(map.allocate(), (2, 0));
+        assert_eq!(map.allocate(), (3, 0));
+        map.release(3, 0);
+        map.release(2, 4);
+        assert_eq!(map.get_free_num(), 2);
+        assert_eq!(map.allocate(), (2, 5));
+        map.reserve(3);
+        map.reserve(10);
+        assert_eq!(map.get_free_num(), 6);
+        for ino in 4..10 {
+            assert_eq!(map.allocate(), (ino, 0));
+        }
+        assert_eq!(map.allocate(), (11, 0));
+    }
+}
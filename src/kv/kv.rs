@@ -1,12 +1,146 @@
+use std::collections::{HashMap, VecDeque};
+use crate::kv::raw_inode;
+use crate::util::time;
+use crate::inode::mode;
 
-pub trait KV<K: Key> {
-    fn get<'a, BK: Borrow<K>>(&self, options: ReadOptions<'a, K>, key: BK) -> Result<Option<Vec<u8>>, Error>;
+// 遍历时每次从KV中读取的Inode数量
+pub const SCAN_CHUNK_SIZE: usize = 64;
 
-    fn put<BK: Borrow<K>>(&self, options: WriteOptions, key: BK, value: &[u8]) -> Result<(), Error>;
+// 一次原子写入的多个操作，按加入的顺序执行
+#[derive(Clone)]
+pub enum BatchOp {
+    Put(raw_inode::RawInode),
+    Delete(u32),
+}
 
-    fn delete<BK: Borrow<K>>(&self, options: WriteOptions, key: BK) -> Result<(), Error>;
+#[derive(Clone)]
+pub struct WriteBatch {
+    pub ops: Vec<BatchOp>,
 }
 
-impl<K: Key> KV<K> for Database<K> {
-    
-}
\ No newline at end of file
+impl WriteBatch {
+    pub fn new() -> WriteBatch {
+        WriteBatch {
+            ops: vec![],
+        }
+    }
+
+    pub fn put(&mut self, raw_inode: raw_inode::RawInode) {
+        self.ops.push(BatchOp::Put(raw_inode));
+    }
+
+    pub fn delete(&mut self, ino: u32) {
+        self.ops.push(BatchOp::Delete(ino));
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    // 按顺序检查每个删除的Inode在执行到该操作时存在，考虑batch中之前的操作
+    pub fn check(&self, has_inode: impl Fn(u32) -> bool) -> bool {
+        let mut exist = HashMap::new();
+        for op in self.ops.iter() {
+            match op {
+                BatchOp::Put(raw_inode) => {
+                    exist.insert(raw_inode.ino, true);
+                }
+                BatchOp::Delete(ino) => {
+                    if !*exist.get(ino).unwrap_or(&has_inode(*ino)) {
+                        return false;
+                    }
+                    exist.insert(*ino, false);
+                }
+            }
+        }
+        true
+    }
+}
+
+// Inode元数据的存储，以ino为键
+// 对不存在的Inode进行get、update或delete时panic，与上层对Inode的使用方式一致
+pub trait KV {
+    fn has_inode(&self, ino: u32) -> bool;
+
+    fn get_inode(&self, ino: u32) -> raw_inode::RawInode;
+
+    // 写入已存在的Inode
+    fn update_inode(&mut self, raw_inode: raw_inode::RawInode);
+
+    fn delete_inode(&mut self, ino: u32);
+
+    // 优先复用已删除的ino，写入默认的Inode
+    fn allocate_inode(&mut self) -> raw_inode::RawInode;
+
+    // 按ino从小到大返回不小于start的至多limit个Inode
+    fn scan_inode(&self, start: u32, limit: usize) -> Vec<raw_inode::RawInode>;
+
+    // 批量写入，要么全部生效，要么全部不生效
+    fn write_batch(&mut self, batch: WriteBatch);
+
+    // 按ino从小到大遍历所有Inode
+    fn iter_inode(&self) -> InodeIter<'_, Self> where Self: Sized {
+        InodeIter::new(self)
+    }
+}
+
+// 每次通过scan_inode读取一批，遍历过程中不持有全部Inode
+pub struct InodeIter<'a, K: KV> {
+    kv: &'a K,
+    next: Option<u32>,
+    buffer: VecDeque<raw_inode::RawInode>,
+}
+
+impl<'a, K: KV> InodeIter<'a, K> {
+    pub fn new(kv: &'a K) -> InodeIter<'a, K> {
+        InodeIter {
+            kv,
+            next: Some(0),
+            buffer: VecDeque::new(),
+        }
+    }
+}
+
+impl<'a, K: KV> Iterator for InodeIter<'a, K> {
+    type Item = raw_inode::RawInode;
+
+    fn next(&mut self) -> Option<raw_inode::RawInode> {
+        if self.buffer.is_empty() {
+            let start = self.next?;
+            let inodes = self.kv.scan_inode(start, SCAN_CHUNK_SIZE);
+            self.next = match inodes.len() < SCAN_CHUNK_SIZE {
+                true => None,
+                false => inodes.last().unwrap().ino.checked_add(1),
+            };
+            self.buffer.extend(inodes);
+        }
+        self.buffer.pop_front()
+    }
+}
+
+// 新分配的Inode为普通文件，权限为0644，链接数为1，各时间戳为分配的时间
+pub fn new_raw_inode(ino: u32, generation: u32) -> raw_inode::RawInode {
+    let now = time::now();
+    raw_inode::RawInode {
+        ino,
+        uid: 0,
+        gid: 0,
+        size: 0,
+        n_link: 1,
+        ref_cnt: 0,
+        file_type: 0,
+        generation,
+        atime: now,
+        mtime: now,
+        ctime: now,
+        btime: now,
+        mode: mode::S_IFREG | 0o644,
+        data: vec![],
+        xattr: vec![],
+        xattr_entry: None,
+    }
+}
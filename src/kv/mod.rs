@@ -1,2 +1,5 @@
+pub mod kv;
+pub mod ino_map;
 pub mod fake_kv;
+pub mod file_kv;
 pub mod raw_inode;
\ No newline at end of file
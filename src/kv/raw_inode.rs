@@ -1,18 +1,112 @@
 #[derive(Copy, Clone)]
 pub struct RawEntry {
-    pub len: u32,
+    pub len: u64,
     pub size: u32,
-    pub offset: u32,
+    pub offset: u64,
     pub address: u32,
 }
 
+#[derive(Clone)]
 pub struct RawInode {
     pub ino: u32,
     pub uid: u32,
     pub gid: u16,
-    pub size: u32,
+    pub size: u64,
     pub n_link: u8,
     pub ref_cnt: u8,
     pub file_type: u8, // 0 File 1 Directory 2 SoftLink 3 HardLink
+    pub generation: u32,   // ino每被复用一次加一
+    pub atime: u64,        // 以纳秒为单位
+    pub mtime: u64,
+    pub ctime: u64,
+    pub btime: u64,
+    pub mode: u16,         // 文件类型与权限位
     pub data: Vec<RawEntry>,
-}
\ No newline at end of file
+    pub xattr: Vec<u8>,    // 内联的扩展属性，保存在Extent中时为空
+    pub xattr_entry: Option<RawEntry>,
+}
+
+// 编码格式的版本，文件大小与偏移改为64位后为2，加入时间戳后为3，加入mode后为4，加入扩展属性后为5
+pub const FORMAT_VERSION: u32 = 5;
+// 编码后的固定头部长度，之后依次为每个RawEntry、扩展属性的Extent与内联的扩展属性
+pub const HEADER_SIZE: usize = 68;
+pub const ENTRY_SIZE: usize = 24;
+
+impl RawInode {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&self.ino.to_be_bytes());
+        bytes.extend_from_slice(&self.uid.to_be_bytes());
+        bytes.extend_from_slice(&self.gid.to_be_bytes());
+        bytes.extend_from_slice(&self.size.to_be_bytes());
+        bytes.push(self.n_link);
+        bytes.push(self.ref_cnt);
+        bytes.push(self.file_type);
+        bytes.extend_from_slice(&self.generation.to_be_bytes());
+        for time in [self.atime, self.mtime, self.ctime, self.btime] {
+            bytes.extend_from_slice(&time.to_be_bytes());
+        }
+        bytes.extend_from_slice(&self.mode.to_be_bytes());
+        bytes.extend_from_slice(&(self.data.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&(self.xattr.len() as u32).to_be_bytes());
+        bytes.push(self.xattr_entry.is_some() as u8);
+        for entry in self.data.iter().chain(self.xattr_entry.iter()) {
+            bytes.extend_from_slice(&entry.len.to_be_bytes());
+            bytes.extend_from_slice(&entry.size.to_be_bytes());
+            bytes.extend_from_slice(&entry.offset.to_be_bytes());
+            bytes.extend_from_slice(&entry.address.to_be_bytes());
+        }
+        bytes.extend_from_slice(&self.xattr);
+        bytes
+    }
+
+    // 返回解码出的Inode与占用的字节数，数据不完整时返回None
+    pub fn decode(bytes: &[u8]) -> Option<(RawInode, usize)> {
+        if bytes.len() < HEADER_SIZE {
+            return None;
+        }
+        let read_u32 = |index: usize| u32::from_be_bytes(bytes[index..index + 4].try_into().unwrap());
+        let read_u64 = |index: usize| u64::from_be_bytes(bytes[index..index + 8].try_into().unwrap());
+        let entry_num = read_u32(59) as usize;
+        let xattr_len = read_u32(63) as usize;
+        let has_xattr_entry = bytes[67] != 0;
+        let xattr_offset = HEADER_SIZE + (entry_num + has_xattr_entry as usize) * ENTRY_SIZE;
+        let len = xattr_offset + xattr_len;
+        if bytes.len() < len {
+            return None;
+        }
+        let read_entry = |index: usize| RawEntry {
+            len: read_u64(index),
+            size: read_u32(index + 8),
+            offset: read_u64(index + 12),
+            address: read_u32(index + 20),
+        };
+        let mut data = vec![];
+        for i in 0..entry_num {
+            data.push(read_entry(HEADER_SIZE + i * ENTRY_SIZE));
+        }
+        let xattr_entry = match has_xattr_entry {
+            true => Some(read_entry(HEADER_SIZE + entry_num * ENTRY_SIZE)),
+            false => None,
+        };
+        let raw_inode = RawInode {
+            ino: read_u32(0),
+            uid: read_u32(4),
+            gid: u16::from_be_bytes([bytes[8], bytes[9]]),
+            size: read_u64(10),
+            n_link: bytes[18],
+            ref_cnt: bytes[19],
+            file_type: bytes[20],
+            generation: read_u32(21),
+            atime: read_u64(25),
+            mtime: read_u64(33),
+            ctime: read_u64(41),
+            btime: read_u64(49),
+            mode: u16::from_be_bytes([bytes[57], bytes[58]]),
+            data,
+            xattr: bytes[xattr_offset..len].to_vec(),
+            xattr_entry,
+        };
+        Some((raw_inode, len))
+    }
+}
@@ -13,6 +13,7 @@ mod sys_file;
 mod fake_proc;
 mod write_buf;
 mod super_stat;
+mod metrics;
 
 fn main() {
     println!("Hello, world!");
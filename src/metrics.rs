@@ -0,0 +1,190 @@
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::sync::Arc;
+
+// BufCache、DiskManager与CoreManager共享同一个统计表
+pub type MetricsLink = Arc<RefCell<Metrics>>;
+
+pub fn new_link() -> MetricsLink {
+    Arc::new(RefCell::new(Metrics::new()))
+}
+
+// 以Page为单位的计数，host为文件接口写入的数据，device为实际写入磁盘的数据
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Metrics {
+    pub host_write_num: u64,      // Inode::write的调用次数
+    pub host_write_bytes: u64,
+    pub host_write_pages: u64,
+    pub data_write_pages: u64,    // dispose_event_group写入的数据Page，包含改写时重写的剩余部分
+    pub gc_step_num: u64,
+    pub gc_move_pages: u64,
+    pub gc_erase_num: u64,
+    pub cache_hit_num: u64,
+    pub cache_miss_num: u64,
+    pub device_read_pages: u64,
+    pub device_program_pages: u64,
+    pub device_erase_num: u64,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics {
+            host_write_num: 0,
+            host_write_bytes: 0,
+            host_write_pages: 0,
+            data_write_pages: 0,
+            gc_step_num: 0,
+            gc_move_pages: 0,
+            gc_erase_num: 0,
+            cache_hit_num: 0,
+            cache_miss_num: 0,
+            device_read_pages: 0,
+            device_program_pages: 0,
+            device_erase_num: 0,
+        }
+    }
+
+    pub fn record_host_write(&mut self, len: u32) {
+        self.host_write_num += 1;
+        self.host_write_bytes += len as u64;
+        self.host_write_pages += (len as u64).div_ceil(4096);
+    }
+
+    pub fn record_data_write(&mut self, size: u32) {
+        self.data_write_pages += size as u64;
+    }
+
+    pub fn record_gc(&mut self, move_pages: u32, erase_num: u32) {
+        self.gc_step_num += 1;
+        self.gc_move_pages += move_pages as u64;
+        self.gc_erase_num += erase_num as u64;
+    }
+
+    pub fn record_cache(&mut self, hit: bool) {
+        match hit {
+            true => self.cache_hit_num += 1,
+            false => self.cache_miss_num += 1,
+        }
+    }
+
+    pub fn record_device_read(&mut self, size: u32) {
+        self.device_read_pages += size as u64;
+    }
+
+    pub fn record_device_program(&mut self, size: u32) {
+        self.device_program_pages += size as u64;
+    }
+
+    pub fn record_device_erase(&mut self) {
+        self.device_erase_num += 1;
+    }
+
+    pub fn reset(&mut self) {
+        *self = Metrics::new();
+    }
+}
+
+// 某一时刻的统计，erase_count为Main Region中每个Block的擦除次数，不随reset清零
+#[derive(Clone, PartialEq, Debug)]
+pub struct MetricsSnapshot {
+    pub metrics: Metrics,
+    pub erase_count: Vec<u32>,
+}
+
+impl MetricsSnapshot {
+    // 实际写入磁盘的Page数量与文件接口写入的Page数量之比
+    pub fn write_amplification(&self) -> f64 {
+        ratio(self.metrics.device_program_pages, self.metrics.host_write_pages)
+    }
+
+    // 回收的Block中无效Page所占的比例，越高说明搬移的代价越小
+    pub fn gc_efficiency(&self) -> f64 {
+        let total = self.metrics.gc_erase_num * 128;
+        ratio(total.saturating_sub(self.metrics.gc_move_pages), total)
+    }
+
+    pub fn cache_hit_rate(&self) -> f64 {
+        ratio(self.metrics.cache_hit_num, self.metrics.cache_hit_num + self.metrics.cache_miss_num)
+    }
+
+    // 返回擦除次数的(min, max, mean)
+    pub fn erase_distribution(&self) -> (u32, u32, f64) {
+        let min = self.erase_count.iter().copied().min().unwrap_or(0);
+        let max = self.erase_count.iter().copied().max().unwrap_or(0);
+        let sum: u64 = self.erase_count.iter().map(|count| *count as u64).sum();
+        (min, max, ratio(sum, self.erase_count.len() as u64))
+    }
+
+    // 每行为"名称 值"，每个Block的擦除次数以block标签区分
+    pub fn expose(&self) -> String {
+        let metrics = &self.metrics;
+        let mut text = String::new();
+        for (name, value) in [
+            ("host_write_num", metrics.host_write_num),
+            ("host_write_bytes", metrics.host_write_bytes),
+            ("host_write_pages", metrics.host_write_pages),
+            ("data_write_pages", metrics.data_write_pages),
+            ("gc_step_num", metrics.gc_step_num),
+            ("gc_move_pages", metrics.gc_move_pages),
+            ("gc_erase_num", metrics.gc_erase_num),
+            ("cache_hit_num", metrics.cache_hit_num),
+            ("cache_miss_num", metrics.cache_miss_num),
+            ("device_read_pages", metrics.device_read_pages),
+            ("device_program_pages", metrics.device_program_pages),
+            ("device_erase_num", metrics.device_erase_num),
+        ] {
+            writeln!(text, "sffs_{} {}", name, value).unwrap();
+        }
+        let (min, max, mean) = self.erase_distribution();
+        writeln!(text, "sffs_write_amplification {:.3}", self.write_amplification()).unwrap();
+        writeln!(text, "sffs_gc_efficiency {:.3}", self.gc_efficiency()).unwrap();
+        writeln!(text, "sffs_cache_hit_rate {:.3}", self.cache_hit_rate()).unwrap();
+        writeln!(text, "sffs_erase_count_min {}", min).unwrap();
+        writeln!(text, "sffs_erase_count_max {}", max).unwrap();
+        writeln!(text, "sffs_erase_count_mean {:.3}", mean).unwrap();
+        for (block_no, count) in self.erase_count.iter().enumerate() {
+            writeln!(text, "sffs_erase_count{{block=\"{}\"}} {}", block_no, count).unwrap();
+        }
+        text
+    }
+}
+
+fn ratio(a: u64, b: u64) -> f64 {
+    if b == 0 {
+        return 0.0;
+    }
+    a as f64 / b as f64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        let mut metrics = Metrics::new();
+        metrics.record_host_write(5000);
+        metrics.record_device_program(3);
+        metrics.record_gc(32, 1);
+        metrics.record_cache(true);
+        metrics.record_cache(false);
+        metrics.record_cache(false);
+        metrics.record_cache(false);
+        let snapshot = MetricsSnapshot { metrics, erase_count: vec![1, 3] };
+        assert_eq!(snapshot.metrics.host_write_pages, 2);
+        assert_eq!(snapshot.write_amplification(), 1.5);
+        assert_eq!(snapshot.gc_efficiency(), 0.75);
+        assert_eq!(snapshot.cache_hit_rate(), 0.25);
+        assert_eq!(snapshot.erase_distribution(), (1, 3, 2.0));
+        let text = snapshot.expose();
+        assert!(text.contains("sffs_host_write_bytes 5000\n"));
+        assert!(text.contains("sffs_write_amplification 1.500\n"));
+        assert!(text.contains("sffs_erase_count{block=\"1\"} 3\n"));
+
+        metrics.reset();
+        assert_eq!(metrics, Metrics::new());
+        // 接近u32::MAX的长度不会溢出
+        metrics.record_host_write(u32::MAX);
+        assert_eq!(metrics.host_write_pages, 1 << 20);
+    }
+}
@@ -238,4 +238,38 @@
 //     }
 //     proc.cwd = ip;
 //     0
-// }
\ No newline at end of file
+// }
+// pub fn sys_fgetxattr() -> i32 {
+//     let proc = fake_proc::my_proc();
+//     let (_, file) = arg_fd().unwrap();
+//     let name = "".to_string();
+//     let mut buf = vec![];
+//     let ret = file.borrow_mut().file_get_xattr(&proc.cred, &name, &mut buf);
+//     ret
+// }
+
+// pub fn sys_fsetxattr() -> i32 {
+//     let proc = fake_proc::my_proc();
+//     let (_, file) = arg_fd().unwrap();
+//     let name = "".to_string();
+//     let value = vec![];
+//     let flags = 0;
+//     let ret = file.borrow_mut().file_set_xattr(&proc.cred, &name, &value, flags);
+//     ret
+// }
+
+// pub fn sys_flistxattr() -> i32 {
+//     let proc = fake_proc::my_proc();
+//     let (_, file) = arg_fd().unwrap();
+//     let mut buf = vec![];
+//     let ret = file.borrow_mut().file_list_xattr(&proc.cred, &mut buf);
+//     ret
+// }
+
+// pub fn sys_fremovexattr() -> i32 {
+//     let proc = fake_proc::my_proc();
+//     let (_, file) = arg_fd().unwrap();
+//     let name = "".to_string();
+//     let ret = file.borrow_mut().file_remove_xattr(&proc.cred, &name);
+//     ret
+// }
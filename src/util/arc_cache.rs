@@ -0,0 +1,178 @@
+use std::hash::Hash;
+use std::collections::HashMap;
+use crate::util::cache::{Cache, CacheStat, KeyList};
+
+// ARC: T1保存只访问过一次的元素，T2保存访问过多次的元素
+// B1、B2分别记录从T1、T2中替换出的Key，根据在哪个队列命中来调整T1的目标大小p
+pub struct ARCCache<K, V> {
+    capacity: usize,
+    p: usize,
+    t1: KeyList<K>,
+    t2: KeyList<K>,
+    b1: KeyList<K>,
+    b2: KeyList<K>,
+    map: HashMap<K, V>,
+    stat: CacheStat,
+}
+
+impl<K: Hash + Eq + Clone, V> ARCCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        if capacity == 0 {
+            panic!("ARCCache: capacity cannot be zero");
+        }
+        ARCCache {
+            capacity,
+            p: 0,
+            t1: KeyList::new(),
+            t2: KeyList::new(),
+            b1: KeyList::new(),
+            b2: KeyList::new(),
+            map: HashMap::with_capacity(capacity),
+            stat: CacheStat::new(),
+        }
+    }
+
+    // 缓存已满时从T1或T2中替换出一个元素
+    fn replace(&mut self, in_b2: bool) -> Option<(K, V)> {
+        if self.map.len() < self.capacity {
+            return None;
+        }
+        let t1_len = self.t1.len();
+        let key;
+        if t1_len > 0 && (t1_len > self.p || (in_b2 && t1_len == self.p) || self.t2.len() == 0) {
+            key = self.t1.pop_back().unwrap();
+            self.b1.push_front(key.clone());
+        } else {
+            key = self.t2.pop_back().unwrap();
+            self.b2.push_front(key.clone());
+        }
+        let value = self.map.remove(&key).unwrap();
+        Some((key, value))
+    }
+
+    fn promote(&mut self, key: &K) {
+        if self.t1.remove(key) {
+            self.t2.push_front(key.clone());
+        } else {
+            self.t2.move_to_front(key);
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Cache<K, V> for ARCCache<K, V> {
+    fn get(&mut self, key: &K) -> Option<&V> {
+        if !self.map.contains_key(key) {
+            self.stat.miss += 1;
+            return None;
+        }
+        self.stat.hit += 1;
+        self.promote(key);
+        self.map.get(key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        if !self.map.contains_key(key) {
+            self.stat.miss += 1;
+            return None;
+        }
+        self.stat.hit += 1;
+        self.promote(key);
+        self.map.get_mut(key)
+    }
+
+    fn put(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if self.map.contains_key(&key) {
+            self.promote(&key);
+            self.map.insert(key, value);
+            return None;
+        }
+        let res;
+        if self.b1.contains(&key) {
+            let delta = (self.b2.len() / self.b1.len()).max(1);
+            self.p = (self.p + delta).min(self.capacity);
+            res = self.replace(false);
+            self.b1.remove(&key);
+            self.t2.push_front(key.clone());
+        } else if self.b2.contains(&key) {
+            let delta = (self.b1.len() / self.b2.len()).max(1);
+            self.p = self.p.saturating_sub(delta);
+            res = self.replace(true);
+            self.b2.remove(&key);
+            self.t2.push_front(key.clone());
+        } else {
+            let l1 = self.t1.len() + self.b1.len();
+            let total = l1 + self.t2.len() + self.b2.len();
+            if l1 >= self.capacity {
+                if self.t1.len() < self.capacity {
+                    self.b1.pop_back();
+                    res = self.replace(false);
+                } else {
+                    let old_key = self.t1.pop_back().unwrap();
+                    let old_value = self.map.remove(&old_key).unwrap();
+                    res = Some((old_key, old_value));
+                }
+            } else {
+                if total >= 2 * self.capacity {
+                    self.b2.pop_back();
+                }
+                res = self.replace(false);
+            }
+            self.t1.push_front(key.clone());
+        }
+        self.map.insert(key, value);
+        res
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.t1.remove(key);
+        self.t2.remove(key);
+        self.map.remove(key)
+    }
+
+    fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    fn get_size(&self) -> usize {
+        self.map.len()
+    }
+
+    fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn get_stat(&self) -> CacheStat {
+        self.stat
+    }
+
+    fn reset_stat(&mut self) {
+        self.stat = CacheStat::new();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        let mut cache = ARCCache::<u32, u32>::new(4);
+        cache.put(0, 0);
+        cache.put(1, 1);
+        assert_eq!(*cache.get(&0).unwrap(), 0);
+        assert!(cache.t2.contains(&0));
+        cache.put(2, 2);
+        cache.put(3, 3);
+        // T1中最久未访问的1被替换到B1
+        assert_eq!(cache.put(4, 4), Some((1, 1)));
+        assert!(cache.b1.contains(&1));
+        // B1命中，增大T1的目标大小
+        cache.put(1, 1);
+        assert_eq!(cache.p, 1);
+        assert!(cache.t2.contains(&1));
+        assert_eq!(cache.get_size(), 4);
+        assert_eq!(cache.remove(&1), Some(1));
+        assert!(cache.get(&1).is_none());
+        assert_eq!(cache.get_stat(), CacheStat { hit: 1, miss: 1 });
+    }
+}
@@ -0,0 +1,210 @@
+use std::hash::Hash;
+use std::collections::HashMap;
+use crate::util::lru_cache;
+use crate::util::clock_cache;
+use crate::util::two_q_cache;
+use crate::util::arc_cache;
+
+// 缓存替换策略
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum CachePolicy {
+    LRU,
+    CLOCK,
+    TwoQ,
+    ARC,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct CacheStat {
+    pub hit: u64,
+    pub miss: u64,
+}
+
+impl CacheStat {
+    pub fn new() -> CacheStat {
+        CacheStat {
+            hit: 0,
+            miss: 0,
+        }
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        if self.hit + self.miss == 0 {
+            return 0.0;
+        }
+        self.hit as f64 / (self.hit + self.miss) as f64
+    }
+}
+
+// 只有get会计入命中统计，contains_key不改变缓存状态
+pub trait Cache<K, V> {
+    fn get(&mut self, key: &K) -> Option<&V>;
+    fn get_mut(&mut self, key: &K) -> Option<&mut V>;
+    // 返回被替换出的元素
+    fn put(&mut self, key: K, value: V) -> Option<(K, V)>;
+    fn remove(&mut self, key: &K) -> Option<V>;
+    fn contains_key(&self, key: &K) -> bool;
+    fn get_size(&self) -> usize;
+    fn get_capacity(&self) -> usize;
+    fn get_stat(&self) -> CacheStat;
+    fn reset_stat(&mut self);
+}
+
+pub fn new_cache<K, V>(policy: CachePolicy, capacity: usize) -> Box<dyn Cache<K, V>>
+where K: Hash + Eq + Clone + 'static, V: 'static {
+    match policy {
+        CachePolicy::LRU => Box::new(lru_cache::LRUCache::new(capacity)),
+        CachePolicy::CLOCK => Box::new(clock_cache::ClockCache::new(capacity)),
+        CachePolicy::TwoQ => Box::new(two_q_cache::TwoQCache::new(capacity)),
+        CachePolicy::ARC => Box::new(arc_cache::ARCCache::new(capacity)),
+    }
+}
+
+// 以下标代替指针的双向链表，头部为最近使用的Key
+pub struct KeyList<K> {
+    nodes: Vec<KeyNode<K>>,
+    free: Vec<usize>,
+    head: Option<usize>,
+    tail: Option<usize>,
+    map: HashMap<K, usize>,
+}
+
+struct KeyNode<K> {
+    key: Option<K>,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+impl<K: Hash + Eq + Clone> KeyList<K> {
+    pub fn new() -> KeyList<K> {
+        KeyList {
+            nodes: vec![],
+            free: vec![],
+            head: None,
+            tail: None,
+            map: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    pub fn push_front(&mut self, key: K) {
+        if self.map.contains_key(&key) {
+            panic!("KeyList: push front has exist");
+        }
+        let node = KeyNode {
+            key: Some(key.clone()),
+            prev: None,
+            next: self.head,
+        };
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        match self.head {
+            Some(head) => self.nodes[head].prev = Some(index),
+            None => self.tail = Some(index),
+        }
+        self.head = Some(index);
+        self.map.insert(key, index);
+    }
+
+    pub fn remove(&mut self, key: &K) -> bool {
+        let index = match self.map.remove(key) {
+            Some(index) => index,
+            None => return false,
+        };
+        let prev = self.nodes[index].prev;
+        let next = self.nodes[index].next;
+        match prev {
+            Some(prev) => self.nodes[prev].next = next,
+            None => self.head = next,
+        }
+        match next {
+            Some(next) => self.nodes[next].prev = prev,
+            None => self.tail = prev,
+        }
+        self.nodes[index].key = None;
+        self.free.push(index);
+        true
+    }
+
+    pub fn move_to_front(&mut self, key: &K) {
+        if self.remove(key) {
+            self.push_front(key.clone());
+        }
+    }
+
+    pub fn pop_back(&mut self) -> Option<K> {
+        let tail = self.tail?;
+        let key = self.nodes[tail].key.clone().unwrap();
+        self.remove(&key);
+        Some(key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        let mut list = KeyList::new();
+        list.push_front(1);
+        list.push_front(2);
+        list.push_front(3);
+        list.move_to_front(&1);
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.pop_back(), Some(2));
+        assert!(list.remove(&3));
+        assert!(!list.remove(&3));
+        assert_eq!(list.pop_back(), Some(1));
+        assert_eq!(list.pop_back(), None);
+        list.push_front(4);
+        assert!(list.contains(&4));
+        assert_eq!(list.nodes.len(), 3);
+    }
+
+    #[test]
+    fn scan() {
+        // 热点数据在一次顺序扫描后是否仍在缓存中
+        let policies = [CachePolicy::LRU, CachePolicy::CLOCK, CachePolicy::TwoQ, CachePolicy::ARC];
+        let mut hits = vec![];
+        for policy in policies.iter() {
+            let mut cache = new_cache::<u32, String>(*policy, 16);
+            let mut access = vec![];
+            for _ in 0..4 {
+                access.extend(0..4);
+            }
+            access.extend(10..30);
+            access.extend(0..4);
+            access.extend(100..140);
+            for key in access {
+                if cache.get(&key).is_none() {
+                    cache.put(key, key.to_string());
+                }
+            }
+            cache.reset_stat();
+            for key in 0..4 {
+                cache.get(&key);
+            }
+            assert!(cache.get_size() <= 16);
+            hits.push(cache.get_stat().hit);
+        }
+        assert_eq!(hits[0], 0);
+        assert_eq!(hits[2], 4);
+        assert_eq!(hits[3], 4);
+    }
+}
@@ -0,0 +1,159 @@
+use std::hash::Hash;
+use std::collections::HashMap;
+use crate::util::cache::{Cache, CacheStat};
+
+struct ClockSlot<K, V> {
+    key: K,
+    value: V,
+    referenced: bool,
+}
+
+// 环形缓冲区，指针经过被访问过的元素时清除其访问位，替换第一个未被访问的元素
+pub struct ClockCache<K, V> {
+    capacity: usize,
+    hand: usize,
+    slots: Vec<Option<ClockSlot<K, V>>>,
+    free: Vec<usize>,
+    map: HashMap<K, usize>,
+    stat: CacheStat,
+}
+
+impl<K: Hash + Eq + Clone, V> ClockCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        if capacity == 0 {
+            panic!("ClockCache: capacity cannot be zero");
+        }
+        ClockCache {
+            capacity,
+            hand: 0,
+            slots: Vec::with_capacity(capacity),
+            free: vec![],
+            map: HashMap::with_capacity(capacity),
+            stat: CacheStat::new(),
+        }
+    }
+
+    fn evict(&mut self) -> (usize, (K, V)) {
+        loop {
+            let index = self.hand;
+            self.hand = (self.hand + 1) % self.slots.len();
+            let slot = self.slots[index].as_mut().unwrap();
+            if slot.referenced {
+                slot.referenced = false;
+                continue;
+            }
+            let slot = self.slots[index].take().unwrap();
+            self.map.remove(&slot.key);
+            return (index, (slot.key, slot.value));
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Cache<K, V> for ClockCache<K, V> {
+    fn get(&mut self, key: &K) -> Option<&V> {
+        let index = match self.map.get(key) {
+            Some(index) => *index,
+            None => {
+                self.stat.miss += 1;
+                return None;
+            }
+        };
+        self.stat.hit += 1;
+        let slot = self.slots[index].as_mut().unwrap();
+        slot.referenced = true;
+        Some(&slot.value)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let index = match self.map.get(key) {
+            Some(index) => *index,
+            None => {
+                self.stat.miss += 1;
+                return None;
+            }
+        };
+        self.stat.hit += 1;
+        let slot = self.slots[index].as_mut().unwrap();
+        slot.referenced = true;
+        Some(&mut slot.value)
+    }
+
+    fn put(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if let Some(index) = self.map.get(&key) {
+            let slot = self.slots[*index].as_mut().unwrap();
+            slot.value = value;
+            slot.referenced = true;
+            return None;
+        }
+        let mut res = None;
+        let index;
+        if let Some(free) = self.free.pop() {
+            index = free;
+        } else if self.slots.len() < self.capacity {
+            self.slots.push(None);
+            index = self.slots.len() - 1;
+        } else {
+            let (free, entry) = self.evict();
+            index = free;
+            res = Some(entry);
+        }
+        self.slots[index] = Some(ClockSlot {
+            key: key.clone(),
+            value,
+            referenced: false,
+        });
+        self.map.insert(key, index);
+        res
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let index = self.map.remove(key)?;
+        self.free.push(index);
+        self.slots[index].take().map(|slot| slot.value)
+    }
+
+    fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    fn get_size(&self) -> usize {
+        self.map.len()
+    }
+
+    fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn get_stat(&self) -> CacheStat {
+        self.stat
+    }
+
+    fn reset_stat(&mut self) {
+        self.stat = CacheStat::new();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        let mut clock = ClockCache::<u32, String>::new(3);
+        clock.put(0, "a".to_string());
+        clock.put(1, "b".to_string());
+        clock.put(2, "c".to_string());
+        assert_eq!(clock.get(&0).unwrap(), "a");
+
+        // 0被访问过，第二次机会后替换1
+        let res = clock.put(3, "d".to_string());
+        assert_eq!(res, Some((1, "b".to_string())));
+        assert!(clock.contains_key(&0));
+
+        assert_eq!(clock.remove(&2), Some("c".to_string()));
+        assert_eq!(clock.put(4, "e".to_string()), None);
+        assert_eq!(clock.get_size(), 3);
+        assert!(clock.get(&2).is_none());
+        assert_eq!(clock.get_stat(), CacheStat { hit: 1, miss: 1 });
+    }
+}
@@ -1,170 +1,89 @@
-use std::rc::Rc;
-use std::cell::{Ref, RefCell};
+use std::hash::Hash;
 use std::collections::HashMap;
+use crate::util::cache::{Cache, CacheStat, KeyList};
 
-pub struct LRUCache<T> {
-    size: usize,
+pub struct LRUCache<K, V> {
     capacity: usize,
-    head: Link<T>,
-    tail: Link<T>,
-    map: HashMap<u32, Link<T>>,
+    list: KeyList<K>,
+    map: HashMap<K, V>,
+    stat: CacheStat,
 }
 
-type Link<T> = Option<Rc<RefCell<Node<T>>>>;
-
-struct Node<T> {
-    key: u32,
-    elem: T,
-    next: Link<T>,
-    prev: Link<T>,
-}
-
-#[derive(Copy, Clone)]
-struct NodeEntry<T> {
-    key: u32,
-    elem: T,
-}
-
-impl<T> Node<T> {
-    fn new(entry: NodeEntry<T>) -> Rc<RefCell<Self>> {
-        Rc::new(RefCell::new(Node {
-            key: entry.key,
-            elem: entry.elem,
-            prev: None,
-            next: None,
-        }))
-    }
-}
-
-impl <T: Copy> LRUCache<T> {
+impl<K: Hash + Eq + Clone, V> LRUCache<K, V> {
     pub fn new(capacity: usize) -> Self {
+        if capacity == 0 {
+            panic!("LRUCache: capacity cannot be zero");
+        }
         LRUCache {
             capacity,
-            size: 0,
-            head: None, 
-            tail: None,
+            list: KeyList::new(),
             map: HashMap::with_capacity(capacity),
+            stat: CacheStat::new(),
         }
     }
+}
 
-    pub fn get_size(&self) -> usize {
-        self.size
-    }
-
-    pub fn contains_key(&self, key: u32) -> bool {
-        self.map.contains_key(&key)
+impl<K: Hash + Eq + Clone, V> Cache<K, V> for LRUCache<K, V> {
+    fn get(&mut self, key: &K) -> Option<&V> {
+        if !self.map.contains_key(key) {
+            self.stat.miss += 1;
+            return None;
+        }
+        self.stat.hit += 1;
+        self.list.move_to_front(key);
+        self.map.get(key)
     }
 
-    pub fn get(&mut self, key: u32) -> Option<Ref<T>> {
-        if !self.map.contains_key(&key) {
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        if !self.map.contains_key(key) {
+            self.stat.miss += 1;
             return None;
         }
-        let node = self.map.get(&key).unwrap();
-        let node = node.as_ref().unwrap();
-        let mut node = Some(Rc::clone(node));
-        let entry = self.delete_node(&mut node);
-        self.push_front(entry);
-        let node = self.map[&key].as_ref().unwrap();
-        Some(Ref::map(node.borrow(), |node| &node.elem))
+        self.stat.hit += 1;
+        self.list.move_to_front(key);
+        self.map.get_mut(key)
     }
 
-    pub fn put(&mut self, key: u32, value: T) {
+    fn put(&mut self, key: K, value: V) -> Option<(K, V)> {
         if self.map.contains_key(&key) {
-            let node = self.map.get(&key).unwrap();
-            let node = node.as_ref().unwrap();
-            let mut node = Some(Rc::clone(node));
-            let mut entry = self.delete_node(&mut node);
-            entry.elem = value;
-            self.push_front(entry);
-            return;
+            self.list.move_to_front(&key);
+            self.map.insert(key, value);
+            return None;
         }
-        if self.size == self.capacity {
-            let _ = self.pop_back();
+        let mut res = None;
+        if self.map.len() == self.capacity {
+            let old_key = self.list.pop_back().unwrap();
+            let old_value = self.map.remove(&old_key).unwrap();
+            res = Some((old_key, old_value));
         }
-        let entry = NodeEntry {
-            key,
-            elem: value,
-        };
-        self.push_front(entry);
+        self.list.push_front(key.clone());
+        self.map.insert(key, value);
+        res
     }
 
-    pub fn remove(&mut self, key: u32) {
-        if self.map.contains_key(&key) {
-            let node = self.map.get(&key).unwrap();
-            let node = node.as_ref().unwrap();
-            let mut node = Some(Rc::clone(node));
-            self.delete_node(&mut node);
-        }
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.list.remove(key);
+        self.map.remove(key)
     }
-}
 
-impl<T: Copy> LRUCache<T> {
-    fn delete_node(&mut self, node: &mut Link<T>) -> NodeEntry<T> {
-        let node = node.take().unwrap();
-        let pre_node = node.borrow_mut().prev.take();
-        let next_node = node.borrow_mut().next.take();
-        let entry = NodeEntry {
-            key: node.borrow().key,
-            elem: node.borrow().elem,
-        };
-        self.map.remove(&entry.key);
-        self.size -= 1;
-        if pre_node.is_none() && next_node.is_none() {
-            self.head.take();
-            self.tail.take();
-            return entry;
-        }
-        if pre_node.is_none() {
-            let next_node = next_node.unwrap();
-            next_node.borrow_mut().prev.take();
-            self.head = Some(Rc::clone(&next_node));
-            return entry;
-        }
-        if next_node.is_none() {
-            let pre_node = pre_node.unwrap();
-            pre_node.borrow_mut().next.take();
-            self.tail = Some(Rc::clone(&pre_node));
-            return entry;
-        }
-        let pre_node = pre_node.unwrap();
-        let next_node = next_node.unwrap();
-        pre_node.borrow_mut().next = Some(Rc::clone(&next_node));
-        next_node.borrow_mut().prev = Some(Rc::clone(&pre_node));
-        entry
+    fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
     }
 
-    fn push_front(&mut self, entry: NodeEntry<T>) {
-        let new_head = Node::new(entry);
-        match self.head.take() {
-            Some(old_head) => {
-                old_head.borrow_mut().prev = Some(new_head.clone());
-                new_head.borrow_mut().next = Some(old_head);
-                self.head = Some(new_head.clone());
-            }
-            None => {
-                self.tail = Some(new_head.clone());
-                self.head = Some(new_head.clone());
-            }
-        }
-        self.size += 1;
-        self.map.insert(entry.key,Some(new_head));
+    fn get_size(&self) -> usize {
+        self.map.len()
+    }
+
+    fn get_capacity(&self) -> usize {
+        self.capacity
     }
 
-    fn pop_back(&mut self) -> Option<T> {
-        self.tail.take().map(|old_tail| {
-            match old_tail.borrow_mut().prev.take() {
-                Some(new_tail) => {
-                    new_tail.borrow_mut().next.take();
-                    self.tail = Some(new_tail);
-                }
-                None => {
-                    self.head.take();
-                }
-            }
-            self.size -= 1;
-            self.map.remove(&old_tail.borrow().key);
-            Rc::try_unwrap(old_tail).ok().unwrap().into_inner().elem
-        })
+    fn get_stat(&self) -> CacheStat {
+        self.stat
+    }
+
+    fn reset_stat(&mut self) {
+        self.stat = CacheStat::new();
     }
 }
 
@@ -174,13 +93,13 @@ mod test {
 
     #[test]
     fn basics() {
-        let mut lru = LRUCache::<u32>::new(5);
+        let mut lru = LRUCache::<u32, u32>::new(5);
 
         lru.put(0,1);
         lru.put(1,2);
 
-        assert_eq!(*lru.get(0).unwrap(), 1);
-        assert_eq!(*lru.get(1).unwrap(), 2);
+        assert_eq!(*lru.get(&0).unwrap(), 1);
+        assert_eq!(*lru.get(&1).unwrap(), 2);
 
         lru.put(0,1);
         lru.put(1,2);
@@ -189,17 +108,31 @@ mod test {
         lru.put(4,5);
         lru.put(5,6);
 
-        assert_eq!(*lru.get(3).unwrap(), 4);
-        assert_eq!(*lru.get(4).unwrap(), 5);
-        assert_eq!(*lru.get(5).unwrap(), 6);
+        assert_eq!(*lru.get(&3).unwrap(), 4);
+        assert_eq!(*lru.get(&4).unwrap(), 5);
+        assert_eq!(*lru.get(&5).unwrap(), 6);
 
         {
-            lru.remove(5);
-            let res = lru.get(5);
+            lru.remove(&5);
+            let res = lru.get(&5);
             assert!(res.is_none());
         }
 
-        let res = lru.get(0);
+        let res = lru.get(&0);
         assert!(res.is_none());
+
+        assert_eq!(lru.get_stat(), CacheStat { hit: 5, miss: 2 });
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn generic() {
+        let mut lru = LRUCache::<String, Vec<u8>>::new(2);
+        lru.put("a".to_string(), vec![1]);
+        lru.put("b".to_string(), vec![2]);
+        lru.get(&"a".to_string());
+        let res = lru.put("c".to_string(), vec![3]);
+        assert_eq!(res, Some(("b".to_string(), vec![2])));
+        assert_eq!(lru.get_size(), 2);
+        assert_eq!(lru.remove(&"a".to_string()), Some(vec![1]));
+    }
+}
@@ -1,5 +1,10 @@
 pub mod array;
 pub mod s_array;
+pub mod cache;
 pub mod lru_cache;
+pub mod clock_cache;
+pub mod two_q_cache;
+pub mod arc_cache;
 pub mod safe_linked_list;
-pub mod unsafe_linked_list;
\ No newline at end of file
+pub mod unsafe_linked_list;
+pub mod time;
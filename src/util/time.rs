@@ -0,0 +1,8 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const NSEC_PER_SEC: u64 = 1_000_000_000;
+
+// 当前时间，以自UNIX纪元起的纳秒数表示
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_nanos() as u64)
+}
@@ -0,0 +1,149 @@
+use std::hash::Hash;
+use std::collections::HashMap;
+use crate::util::cache::{Cache, CacheStat, KeyList};
+
+// 2Q: 首次进入的元素放在FIFO队列A1in中，被替换后Key记录在A1out中
+// 只有在A1out中再次被访问的元素才会进入LRU队列Am，顺序扫描不会冲刷Am
+pub struct TwoQCache<K, V> {
+    capacity: usize,
+    kin: usize,
+    kout: usize,
+    a1in: KeyList<K>,
+    a1out: KeyList<K>,
+    am: KeyList<K>,
+    map: HashMap<K, V>,
+    stat: CacheStat,
+}
+
+impl<K: Hash + Eq + Clone, V> TwoQCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        if capacity == 0 {
+            panic!("TwoQCache: capacity cannot be zero");
+        }
+        TwoQCache {
+            capacity,
+            kin: (capacity / 4).max(1),
+            kout: (capacity / 2).max(1),
+            a1in: KeyList::new(),
+            a1out: KeyList::new(),
+            am: KeyList::new(),
+            map: HashMap::with_capacity(capacity),
+            stat: CacheStat::new(),
+        }
+    }
+
+    fn reclaim(&mut self) -> Option<(K, V)> {
+        if self.map.len() < self.capacity {
+            return None;
+        }
+        let key;
+        if self.a1in.len() > self.kin || self.am.len() == 0 {
+            key = self.a1in.pop_back().unwrap();
+            self.a1out.push_front(key.clone());
+            if self.a1out.len() > self.kout {
+                self.a1out.pop_back();
+            }
+        } else {
+            key = self.am.pop_back().unwrap();
+        }
+        let value = self.map.remove(&key).unwrap();
+        Some((key, value))
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Cache<K, V> for TwoQCache<K, V> {
+    fn get(&mut self, key: &K) -> Option<&V> {
+        if !self.map.contains_key(key) {
+            self.stat.miss += 1;
+            return None;
+        }
+        self.stat.hit += 1;
+        if self.am.contains(key) {
+            self.am.move_to_front(key);
+        }
+        self.map.get(key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        if !self.map.contains_key(key) {
+            self.stat.miss += 1;
+            return None;
+        }
+        self.stat.hit += 1;
+        if self.am.contains(key) {
+            self.am.move_to_front(key);
+        }
+        self.map.get_mut(key)
+    }
+
+    fn put(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if self.map.contains_key(&key) {
+            if self.am.contains(&key) {
+                self.am.move_to_front(&key);
+            }
+            self.map.insert(key, value);
+            return None;
+        }
+        let in_a1out = self.a1out.remove(&key);
+        let res = self.reclaim();
+        if in_a1out {
+            self.am.push_front(key.clone());
+        } else {
+            self.a1in.push_front(key.clone());
+        }
+        self.map.insert(key, value);
+        res
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.a1in.remove(key);
+        self.am.remove(key);
+        self.map.remove(key)
+    }
+
+    fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    fn get_size(&self) -> usize {
+        self.map.len()
+    }
+
+    fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn get_stat(&self) -> CacheStat {
+        self.stat
+    }
+
+    fn reset_stat(&mut self) {
+        self.stat = CacheStat::new();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basics() {
+        let mut cache = TwoQCache::<u32, u32>::new(4);
+        for i in 0..4 {
+            cache.put(i, i);
+        }
+        // A1in超过kin，按FIFO顺序替换，0进入A1out
+        assert_eq!(cache.put(4, 4), Some((0, 0)));
+        assert!(cache.a1out.contains(&0));
+        assert_eq!(cache.put(0, 0), Some((1, 1)));
+        assert!(cache.am.contains(&0));
+        assert!(!cache.a1out.contains(&0));
+        for i in 10..20 {
+            cache.put(i, i);
+        }
+        assert_eq!(*cache.get(&0).unwrap(), 0);
+        assert_eq!(cache.get_size(), 4);
+        assert_eq!(cache.remove(&0), Some(0));
+        assert!(cache.get(&0).is_none());
+    }
+}
@@ -23,25 +23,26 @@ impl WriteCache {
         }
     }
 
-    pub fn write(&mut self, address: u32, data: [u8; 4096]) {
-        let index = self.cache.len();
-        if index == self.capacity {
-            panic!("WriteCache: write has too much buf");
-        }
-        if index == self.capacity - 1 {
-            self.sync = true;
-        }
+    // 缓存已满且address不在缓存中时不写入并返回false，调用者需要先sync再重新写入
+    pub fn write(&mut self, address: u32, data: [u8; 4096]) -> bool {
         let buf = WriteBuf {
             address,
             data
         };
-        if !self.table.contains_key(&address) {
-            self.cache.push(buf);
-            self.table.insert(address, index);
-        } else {
-            let index = self.table.get(&address).unwrap();
+        if let Some(index) = self.table.get(&address) {
             self.cache[*index] = buf;
+            return true;
         }
+        let index = self.cache.len();
+        if index == self.capacity {
+            return false;
+        }
+        if index == self.capacity - 1 {
+            self.sync = true;
+        }
+        self.cache.push(buf);
+        self.table.insert(address, index);
+        true
     }
 
     pub fn read(&self, address: u32) -> Option<[u8; 4096]> {
@@ -62,9 +63,14 @@ impl WriteCache {
 
     pub fn recall_write(&mut self, address: u32) {
         if self.table.contains_key(&address) {
-            let index = self.table.get(&address).unwrap();
-            self.cache.remove(*index);
-            self.table.remove(&address);
+            let index = self.table.remove(&address).unwrap();
+            self.cache.remove(index);
+            for value in self.table.values_mut() {
+                if *value > index {
+                    *value -= 1;
+                }
+            }
+            self.sync = false;
         }
     }
 
@@ -91,11 +97,25 @@ mod test {
     fn basics() {
         let mut write_buf = WriteCache::new();
         for i in 0..128 {
-            write_buf.write(i, [0; 4096]);
+            assert_eq!(write_buf.write(i, [0; 4096]), true);
         }
         assert_eq!(write_buf.need_sync(), true);
+        // 已满时新的地址需要先sync，已在缓存中的地址可以直接覆盖
+        assert_eq!(write_buf.write(128, [1; 4096]), false);
+        assert_eq!(write_buf.write(5, [1; 4096]), true);
+        assert_eq!(write_buf.read(128), None);
         write_buf.sync();
         assert_eq!(write_buf.need_sync(), false);
         assert_eq!(write_buf.cache.len(), 0);
+
+        write_buf.write(1, [1; 4096]);
+        write_buf.write(2, [2; 4096]);
+        write_buf.write(3, [3; 4096]);
+        write_buf.recall_write(2);
+        assert_eq!(write_buf.read(3), Some([3; 4096]));
+        assert_eq!(write_buf.read(2), None);
+        write_buf.write(3, [4; 4096]);
+        assert_eq!(write_buf.read(3), Some([4; 4096]));
+        assert_eq!(write_buf.cache.len(), 2);
     }
 }
\ No newline at end of file